@@ -6,6 +6,13 @@ use proc_macro::TokenStream;
 use quasiquote::{quasiquote, quote::quote};
 use syn::{DataStruct, DeriveInput, Error, Fields, Ident, Index, Type, parse};
 
+/// Implements `BufferData` for `Vec<T>`, `[T]` and `[T; N]` of a struct with named fields.
+///
+/// Every field gets its own vertex buffer, with a stride of the field's size. Fields can be
+/// any `Pod` type, including fixed size arrays like `corners: [Vector2<f32>; 4]`. An array
+/// field is stored as one contiguous element of its buffer and needs one attribute per
+/// array element in the layout, e.g.
+/// `([[f32; 2]; 4], Instance, &vertex_attr_array![1 => Float32x2, 2 => Float32x2, 3 => Float32x2, 4 => Float32x2])`
 #[proc_macro_derive(VertexBufferData)]
 pub fn vertex_buffer_data(data: TokenStream) -> TokenStream {
     let strct: DeriveInput = parse(data).unwrap();
@@ -60,10 +67,42 @@ pub fn vertex_buffer_data(data: TokenStream) -> TokenStream {
 		)
 	).collect::<Vec<_>>();
 
-    let fill_buffers = fields.iter().enumerate().map(|(i, (ident, _))|
-		quasiquote!(buffers.#{Index::from(i)}.write_iter(self.iter().map(|x| &x.#ident), context))
+    // Fixed size arrays know their length at compile time
+    let create_array_buffers = fields.iter().map(|(_, type_name)|
+		quasiquote!(
+			crate::wgpu_context::WGPUBuffer::new_vertex((::std::mem::size_of::<#type_name>() * N) as u64, context)
+		)
 	).collect::<Vec<_>>();
 
+    // A struct with a single field has the same layout as that field, so the data is already
+    // contiguous and can be copied in one go. Otherwise each field is gathered separately
+    let fill_buffers = if fields.len() == 1 {
+        vec![quote!(buffers.write_slice(&self[..], context))]
+    } else {
+        fields.iter().enumerate().map(|(i, (ident, _))|
+			quasiquote!(buffers.#{Index::from(i)}.write_iter(self.iter().map(|x| &x.#ident), context))
+		).collect::<Vec<_>>()
+    };
+
+    // Whether every field buffer has room for all elements
+    let fits_buffers = if fields.len() == 1 {
+        let (_, type_name) = &fields[0];
+        vec![quasiquote!(buffers.size() >= (::std::mem::size_of::<#type_name>() * self.len()) as u64)]
+    } else {
+        fields.iter().enumerate().map(|(i, (_, type_name))|
+			quasiquote!(buffers.#{Index::from(i)}.size() >= (::std::mem::size_of::<#type_name>() * self.len()) as u64)
+		).collect::<Vec<_>>()
+    };
+
+    // Same as fill_buffers but recorded into a BufferUploader
+    let stage_buffers = if fields.len() == 1 {
+        vec![quote!(buffers.stage_slice(&self[..], uploader, context))]
+    } else {
+        fields.iter().enumerate().map(|(i, (ident, _))|
+			quasiquote!(buffers.#{Index::from(i)}.stage_iter(self.iter().map(|x| &x.#ident), uploader, context))
+		).collect::<Vec<_>>()
+    };
+
     let output = quasiquote!(
         impl crate::wgpu_context::BufferData for ::std::vec::Vec<#structname> {
             type Buffers = (#(#wgpu_buffer_path),*);
@@ -73,6 +112,44 @@ pub fn vertex_buffer_data(data: TokenStream) -> TokenStream {
             fn fill_buffers(&self, buffers: &mut Self::Buffers, context: &crate::wgpu_context::WGPUContext) {
                 #(#fill_buffers);*
             }
+            fn fits_buffers(&self, buffers: &Self::Buffers) -> bool {
+                #(#fits_buffers)&&*
+            }
+            fn stage_buffers(&self, buffers: &mut Self::Buffers, uploader: &mut crate::wgpu_context::BufferUploader, context: &crate::wgpu_context::WGPUContext) {
+                #(#stage_buffers);*
+            }
+        }
+
+        impl crate::wgpu_context::BufferData for [#structname] {
+            type Buffers = (#(#wgpu_buffer_path),*);
+            fn create_buffers(&self, context: &crate::wgpu_context::WGPUContext) -> Self::Buffers {
+                (#(#create_buffers),*)
+            }
+            fn fill_buffers(&self, buffers: &mut Self::Buffers, context: &crate::wgpu_context::WGPUContext) {
+                #(#fill_buffers);*
+            }
+            fn fits_buffers(&self, buffers: &Self::Buffers) -> bool {
+                #(#fits_buffers)&&*
+            }
+            fn stage_buffers(&self, buffers: &mut Self::Buffers, uploader: &mut crate::wgpu_context::BufferUploader, context: &crate::wgpu_context::WGPUContext) {
+                #(#stage_buffers);*
+            }
+        }
+
+        impl<const N: usize> crate::wgpu_context::BufferData for [#structname; N] {
+            type Buffers = (#(#wgpu_buffer_path),*);
+            fn create_buffers(&self, context: &crate::wgpu_context::WGPUContext) -> Self::Buffers {
+                (#(#create_array_buffers),*)
+            }
+            fn fill_buffers(&self, buffers: &mut Self::Buffers, context: &crate::wgpu_context::WGPUContext) {
+                #(#fill_buffers);*
+            }
+            fn fits_buffers(&self, _: &Self::Buffers) -> bool {
+                true
+            }
+            fn stage_buffers(&self, buffers: &mut Self::Buffers, uploader: &mut crate::wgpu_context::BufferUploader, context: &crate::wgpu_context::WGPUContext) {
+                #(#stage_buffers);*
+            }
         }
     );
     return output.into();
@@ -111,6 +188,12 @@ pub fn uniform_buffer_data(data: TokenStream) -> TokenStream {
             fn fill_buffers(&self, buffers: &mut Self::Buffers, context: &crate::wgpu_context::WGPUContext) {
                 buffers.write_data(::bytemuck::bytes_of(self), context);
             }
+            fn fits_buffers(&self, _: &Self::Buffers) -> bool {
+                true
+            }
+            fn stage_buffers(&self, buffers: &mut Self::Buffers, uploader: &mut crate::wgpu_context::BufferUploader, context: &crate::wgpu_context::WGPUContext) {
+                buffers.stage_data(::bytemuck::bytes_of(self), uploader, context);
+            }
         }
     );
     return output.into();
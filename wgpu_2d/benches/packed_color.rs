@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use wgpu_2d::math::Vector4;
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Compares uploading 1M circles with full and packed colors
+const INSTANCES: usize = 1_000_000;
+const ITERATIONS: u32 = 20;
+
+fn bench(name: &str, bytes: usize, context: &WGPUContext, mut f: impl FnMut()) {
+    // Warm up so buffer allocation isn't part of the measurement
+    f();
+    context.device().poll(wgpu::Maintain::Wait);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    context.device().poll(wgpu::Maintain::Wait);
+    let per_iter = start.elapsed() / ITERATIONS;
+    println!(
+        "{name:<8} {:>3} bytes per instance {:>10.3?} per upload",
+        bytes, per_iter
+    );
+}
+
+fn main() {
+    let Ok(context) = WGPUContext::try_new_headless([1, 1], &Default::default()) else {
+        eprintln!("No adapter available, skipping benchmark");
+        return;
+    };
+    let shader_manager = ShaderManager::new("");
+    let renderer = Renderer2D::new(&context);
+
+    let circle = Circle {
+        color: Vector4::new([1., 0.5, 0.25, 1.]),
+        radius: 4.,
+        ..Default::default()
+    };
+    let mut full = CircleRenderer::new(
+        vec![circle; INSTANCES],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let mut packed = PackedCircleRenderer::new(
+        vec![circle.into(); INSTANCES],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+
+    bench("full", size_of::<Circle>(), &context, || {
+        full.update(&context)
+    });
+    bench("packed", size_of::<PackedCircle>(), &context, || {
+        packed.update(&context)
+    });
+}
@@ -0,0 +1,92 @@
+use std::time::Instant;
+
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Compares render and render_batched for 500 circles and 500 rects drawn as
+// separate interleaved items, the worst case for pipeline switches
+const ITEMS: usize = 500;
+const ITERATIONS: u32 = 100;
+
+fn bench(name: &str, context: &WGPUContext, mut f: impl FnMut()) {
+    // Warm up so pipeline compilation isn't part of the measurement
+    f();
+    context.device().poll(wgpu::Maintain::Wait);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    context.device().poll(wgpu::Maintain::Wait);
+    let per_iter = start.elapsed() / ITERATIONS;
+    println!("{name:<15} {:>10.3?} per frame", per_iter);
+}
+
+fn main() {
+    let Ok(context) = WGPUContext::try_new_headless([256, 256], &Default::default()) else {
+        eprintln!("No adapter available, skipping benchmark");
+        return;
+    };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+
+    let position = |i: usize| Vector2::new([(i % 16) as f32 * 16., (i / 16) as f32 * 8.]);
+    let circles = (0..ITEMS)
+        .map(|i| {
+            CircleRenderer::new(
+                vec![Circle {
+                    position: position(i),
+                    radius: 4.,
+                    ..Default::default()
+                }],
+                renderer.uniform_bind_group_layout(),
+                &context,
+                &shader_manager,
+            )
+        })
+        .collect::<Vec<_>>();
+    let rects = (0..ITEMS)
+        .map(|i| {
+            RectangleRenderer::new(
+                vec![CenterRect {
+                    color: Vector4::new([1., 0., 0., 1.]),
+                    center: position(i),
+                    size: Vector2::new([6., 6.]),
+                    rotation: 0.,
+                    layer: 0,
+                }],
+                renderer.uniform_bind_group_layout(),
+                &context,
+                &shader_manager,
+            )
+        })
+        .collect::<Vec<_>>();
+    let items = || {
+        circles
+            .iter()
+            .zip(&rects)
+            .flat_map(|(circle, rect)| [circle as &dyn Render, rect as &dyn Render])
+    };
+
+    bench("render", &context, || {
+        renderer
+            .render(items(), &context, &shader_manager)
+            .expect("Could not render frame")
+    });
+    let mut stats = None;
+    bench("render_batched", &context, || {
+        stats = Some(
+            renderer
+                .render_batched(items(), &context, &shader_manager)
+                .expect("Could not render frame"),
+        )
+    });
+
+    let stats = stats.unwrap();
+    println!(
+        "{} items, {} pipeline switches with render, {} with render_batched",
+        stats.items, stats.items, stats.pipeline_switches
+    );
+}
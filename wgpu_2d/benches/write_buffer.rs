@@ -0,0 +1,45 @@
+use std::time::Instant;
+
+use wgpu_2d::math::Vector4;
+use wgpu_2d::wgpu_context::{BufferUploader, WGPUBuffer, WGPUContext};
+
+// Compares write_iter, write_slice and a staged write for uploading 100k elements
+const ELEMENTS: usize = 100_000;
+const ITERATIONS: u32 = 100;
+
+fn bench(name: &str, context: &WGPUContext, mut f: impl FnMut()) {
+    // Warm up so buffer allocation isn't part of the measurement
+    f();
+    context.device().poll(wgpu::Maintain::Wait);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    context.device().poll(wgpu::Maintain::Wait);
+    let per_iter = start.elapsed() / ITERATIONS;
+    println!("{name:<12} {:>10.3?} per write", per_iter);
+}
+
+fn main() {
+    let Ok(context) = WGPUContext::try_new_headless([1, 1], &Default::default()) else {
+        eprintln!("No adapter available, skipping benchmark");
+        return;
+    };
+
+    let data = vec![Vector4::new([1f32, 0.5, 0.25, 1.]); ELEMENTS];
+    let size = (std::mem::size_of::<Vector4<f32>>() * ELEMENTS) as u64;
+    let mut buffer = WGPUBuffer::new_vertex(size, &context);
+
+    bench("write_iter", &context, || {
+        buffer.write_iter(data.iter(), &context)
+    });
+    bench("write_slice", &context, || {
+        buffer.write_slice(&data, &context)
+    });
+    let mut uploader = BufferUploader::new(size);
+    bench("stage_slice", &context, || {
+        buffer.stage_slice(&data, &mut uploader, &context);
+        uploader.submit(&context);
+    });
+}
@@ -0,0 +1,34 @@
+use wgpu_2d::app::AppBuilder;
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+
+// A circle orbiting the middle of the window, written without implementing
+// `ApplicationHandler`. Escape closes the window
+fn main() {
+    AppBuilder::new("App builder", |context| {
+        let circles = CircleRenderer::new(
+            vec![Circle {
+                color: Vector4::new([0.2, 0.6, 1., 1.]),
+                radius: 40.,
+                ..Default::default()
+            }],
+            context.renderer.uniform_bind_group_layout(),
+            &context.render_context,
+            &context.shader_manager,
+        );
+        // Circles and the angle around the middle
+        (circles, 0f32)
+    })
+    .update(|(circles, angle), context, delta| {
+        *angle += delta;
+        let config = context.render_context.config();
+        let center = Vector2::new([config.width as f32, config.height as f32])
+            / context.renderer.uniform().scale_factor
+            / 2.;
+        circles.circles_mut()[0].position = center + Vector2::rotation(*angle) * 150.;
+        circles.sync(&context.render_context);
+    })
+    .render(|(circles, _), frame, shader_manager| frame.draw([circles], shader_manager))
+    .run()
+    .expect("Event loop failed");
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId};
+
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Draws a panel in the middle of the window with circles that spill past its edges.
+// Everything is clipped to the panel, so only the parts of the circles inside it show
+fn main() {
+    let event_loop = winit::event_loop::EventLoop::new().expect("Could not create event loop");
+
+    let mut app = App { inner: None };
+    _ = event_loop.run_app(&mut app);
+}
+
+struct App {
+    inner: Option<AppInner>,
+}
+
+struct AppInner {
+    window: Arc<Window>,
+    render_context: WGPUContext,
+    shader_manager: ShaderManager,
+    renderer: Renderer2D,
+    panel: RectangleRenderer,
+    circles: CircleRenderer,
+}
+
+impl AppInner {
+    fn init(window: Window) -> Self {
+        let window = Arc::new(window);
+        let shader_manager = ShaderManager::new("");
+        let render_context = WGPUContext::new(
+            Arc::clone(&window),
+            Vector2::<u32>::from(window.inner_size()).into_inner(),
+        );
+        let renderer = Renderer2D::new(&render_context);
+
+        let (min, size) = panel_region(&render_context);
+        let panel = RectangleRenderer::new(
+            vec![CenterRect {
+                color: Vector4::new([0.15, 0.15, 0.2, 1.]),
+                center: min + size / 2.,
+                size,
+                rotation: 0.,
+                layer: 0,
+            }],
+            renderer.uniform_bind_group_layout(),
+            &render_context,
+            &shader_manager,
+        );
+        let circles = CircleRenderer::new(
+            [min, min + size, min + size * Vector2::new([1., 0.])]
+                .into_iter()
+                .map(|position| Circle {
+                    color: Vector4::new([0.2, 0.6, 1., 1.]),
+                    position,
+                    radius: size[1] / 3.,
+                    layer: 1,
+                    ..Default::default()
+                })
+                .collect(),
+            renderer.uniform_bind_group_layout(),
+            &render_context,
+            &shader_manager,
+        );
+
+        Self {
+            window,
+            render_context,
+            shader_manager,
+            renderer,
+            panel,
+            circles,
+        }
+    }
+}
+
+/// Corner and size of the panel, half the size of the window
+fn panel_region(context: &WGPUContext) -> (Vector2<f32>, Vector2<f32>) {
+    let size = Vector2::new([context.config().width as f32, context.config().height as f32]);
+    (size / 4., size / 2.)
+}
+
+impl winit::application::ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.inner.is_none() {
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("Clipping"))
+                .expect("Could not create window");
+            self.inner = Some(AppInner::init(window));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let inner = self.inner.as_mut().unwrap();
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(new_size) => {
+                inner
+                    .render_context
+                    .resize(Vector2::<u32>::from(new_size).into_inner());
+                inner.renderer.get_uniform().screen_size = new_size.into();
+                inner.renderer.update_uniform(&inner.render_context);
+                inner.window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                let (min, size) = panel_region(&inner.render_context);
+                inner.renderer.push_clip(min, size);
+                let result = inner.renderer.render(
+                    [&inner.panel as &dyn Render, &inner.circles],
+                    &inner.render_context,
+                    &inner.shader_manager,
+                );
+                inner.renderer.pop_clip();
+                if let Err(err) = result {
+                    eprintln!("Could not render frame: {err}");
+                    event_loop.exit();
+                }
+                inner.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use egui_wgpu::ScreenDescriptor;
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId};
+
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// A circle in the middle of the window with an egui window on top to change it.
+// Run with `--features egui`
+fn main() {
+    let event_loop = winit::event_loop::EventLoop::new().expect("Could not create event loop");
+
+    let mut app = App { inner: None };
+    _ = event_loop.run_app(&mut app);
+}
+
+struct App {
+    inner: Option<AppInner>,
+}
+
+struct AppInner {
+    window: Arc<Window>,
+    render_context: WGPUContext,
+    shader_manager: ShaderManager,
+    renderer: Renderer2D,
+    circles: CircleRenderer,
+    ui: EguiPass,
+    radius: f32,
+    color: [f32; 3],
+}
+
+/// Paints the output of the last egui frame
+struct EguiPass {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    screen: ScreenDescriptor,
+}
+
+impl EguiPass {
+    fn new(window: &Window, render_context: &WGPUContext) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            context.viewport_id(),
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            Some(render_context.device().limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(
+            render_context.device(),
+            render_context.config().format,
+            None,
+            1,
+            false,
+        );
+        Self {
+            context,
+            state,
+            renderer,
+            paint_jobs: Vec::new(),
+            textures_delta: Default::default(),
+            screen: ScreenDescriptor {
+                size_in_pixels: [0, 0],
+                pixels_per_point: 1.,
+            },
+        }
+    }
+
+    /// Runs the UI for this frame, keeping its output for [UiPass::paint]
+    fn run(
+        &mut self,
+        window: &Window,
+        render_context: &WGPUContext,
+        ui: impl FnMut(&egui::Context),
+    ) {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, ui);
+        self.state
+            .handle_platform_output(window, output.platform_output);
+        self.paint_jobs = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        self.textures_delta.append(output.textures_delta);
+        self.screen = ScreenDescriptor {
+            size_in_pixels: [
+                render_context.config().width,
+                render_context.config().height,
+            ],
+            pixels_per_point: output.pixels_per_point,
+        };
+    }
+}
+
+impl UiPass for EguiPass {
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, context: &WGPUContext) {
+        for (id, delta) in &self.textures_delta.set {
+            self.renderer
+                .update_texture(context.device(), context.queue(), *id, delta);
+        }
+        let commands = self.renderer.update_buffers(
+            context.device(),
+            context.queue(),
+            encoder,
+            &self.paint_jobs,
+            &self.screen,
+        );
+        context.queue().submit(commands);
+    }
+
+    fn paint(&mut self, render_pass: &mut wgpu::RenderPass<'static>, _: &WGPUContext) {
+        self.renderer
+            .render(render_pass, &self.paint_jobs, &self.screen);
+        for id in std::mem::take(&mut self.textures_delta).free {
+            self.renderer.free_texture(&id);
+        }
+    }
+}
+
+impl AppInner {
+    fn init(window: Window) -> Self {
+        let window = Arc::new(window);
+        let shader_manager = ShaderManager::new("");
+        let render_context = WGPUContext::new(
+            Arc::clone(&window),
+            Vector2::<u32>::from(window.inner_size()).into_inner(),
+        );
+        let renderer = Renderer2D::new(&render_context);
+        let circles = CircleRenderer::new(
+            vec![Circle {
+                color: Vector4::new([0.2, 0.6, 1., 1.]),
+                position: center(&render_context),
+                radius: 80.,
+                ..Default::default()
+            }],
+            renderer.uniform_bind_group_layout(),
+            &render_context,
+            &shader_manager,
+        );
+        let ui = EguiPass::new(&window, &render_context);
+
+        Self {
+            window,
+            render_context,
+            shader_manager,
+            renderer,
+            circles,
+            ui,
+            radius: 80.,
+            color: [0.2, 0.6, 1.],
+        }
+    }
+}
+
+fn center(context: &WGPUContext) -> Vector2<f32> {
+    Vector2::new([
+        context.config().width as f32,
+        context.config().height as f32,
+    ]) / 2.
+}
+
+impl winit::application::ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.inner.is_none() {
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("egui overlay"))
+                .expect("Could not create window");
+            self.inner = Some(AppInner::init(window));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let inner = self.inner.as_mut().unwrap();
+        if inner
+            .ui
+            .state
+            .on_window_event(&inner.window, &event)
+            .consumed
+        {
+            return;
+        }
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(new_size) => {
+                inner
+                    .render_context
+                    .resize(Vector2::<u32>::from(new_size).into_inner());
+                inner.renderer.get_uniform().screen_size = new_size.into();
+                inner.renderer.update_uniform(&inner.render_context);
+                inner.window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                let (radius, color) = (&mut inner.radius, &mut inner.color);
+                inner
+                    .ui
+                    .run(&inner.window, &inner.render_context, |context| {
+                        egui::Window::new("Circle").show(context, |ui| {
+                            ui.add(egui::Slider::new(radius, 10.0..=300.0).text("Radius"));
+                            ui.color_edit_button_rgb(color);
+                        });
+                    });
+
+                let circle = &mut inner.circles.circles_mut()[0];
+                circle.position = center(&inner.render_context);
+                circle.radius = inner.radius;
+                circle.color = Vector4::new([inner.color[0], inner.color[1], inner.color[2], 1.]);
+                inner.circles.sync(&inner.render_context);
+
+                if let Err(err) = inner.renderer.render_with_ui(
+                    [&inner.circles],
+                    &mut inner.ui,
+                    &inner.render_context,
+                    &inner.shader_manager,
+                ) {
+                    eprintln!("Could not render frame: {err}");
+                    event_loop.exit();
+                }
+                inner.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+}
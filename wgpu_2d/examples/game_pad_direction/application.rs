@@ -1,29 +1,99 @@
+use std::collections::HashSet;
 use std::f32::consts::PI;
 use std::sync::Arc;
+use std::time::Duration;
 
 use gamepad_input::{GamepadMap, GamepadID, XInputGamepad};
 use kbm_input::keyboard::KeyMap;
 use kbm_input::mouse::MouseMap;
-use winit::event::{DeviceEvent, WindowEvent};
+use winit::dpi::PhysicalSize;
+use winit::event::{DeviceEvent, ElementState, KeyEvent, MouseButton, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
-use winit::keyboard::{Key, NamedKey};
-use winit::window::{Window, WindowId};
+use winit::keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey};
+use winit::window::{CursorGrabMode, Window, WindowAttributes, WindowId};
 
 use crate::shader_manager::*;
 use crate::wgpu_context::*;
 
 use crate::math::{Vector2, Vector4};
 use crate::rendering::*;
-use crate::timer::Timer;
+use crate::events::EventQueue;
+use crate::timer::{FrameLimiter, FrameStats, Timer};
+
+use crate::text_input::TextInput;
+
+/// Settings used to create the window. Sizes are in physical pixels
+pub struct WindowConfig {
+    pub title: String,
+    /// Initial size of the window. Chosen by the platform if `None`
+    pub size: Option<[u32; 2]>,
+    pub resizable: bool,
+    pub min_size: Option<[u32; 2]>,
+    pub max_size: Option<[u32; 2]>,
+    pub decorations: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("wgpu_2d"),
+            size: None,
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            decorations: true,
+        }
+    }
+}
+
+impl From<&str> for WindowConfig {
+    fn from(title: &str) -> Self {
+        Self {
+            title: title.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+impl WindowConfig {
+    fn attributes(&self) -> WindowAttributes {
+        let mut attributes = Window::default_attributes()
+            .with_title(self.title.clone())
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations);
+        if let Some([width, height]) = self.size {
+            attributes = attributes.with_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some([width, height]) = self.min_size {
+            attributes = attributes.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some([width, height]) = self.max_size {
+            attributes = attributes.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        attributes
+    }
+}
 
 pub struct App {
-    title: &'static str,
+    window_config: WindowConfig,
+    frame_limiter: FrameLimiter,
     inner: Option<AppInner>,
 }
 
 impl App {
-    pub fn new(title: &'static str) -> Self {
-        Self { title, inner: None }
+    /// Accepts a title on its own, which uses the defaults for everything else
+    pub fn new(window_config: impl Into<WindowConfig>) -> Self {
+        Self {
+            window_config: window_config.into(),
+            frame_limiter: FrameLimiter::new(None),
+            inner: None,
+        }
+    }
+
+    /// Limits how often the window is redrawn. `None` redraws as fast as the present
+    /// mode allows
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) {
+        self.frame_limiter.set_target_fps(target_fps);
     }
 }
 
@@ -33,9 +103,16 @@ struct AppInner {
     shader_manager: ShaderManager,
     renderer: Renderer2D,
     timer: Timer,
+    frame_stats: FrameStats,
     input: Input,
+    // Window events since the last frame for update_scene. The input maps are updated as
+    // events arrive, so they already include every queued event when it is drained
+    events: EventQueue<WindowEvent>,
     scene: (RingRenderer, RectangleRenderer),
     gamepad: Option<XInputGamepad>,
+    // Offset of the aim bar from the center while the mouse is captured
+    captured_aim: Vector2<f32>,
+    resize: ResizeTracker,
 }
 
 impl AppInner {
@@ -51,27 +128,28 @@ impl AppInner {
         // Create WGPU context
         let render_context = WGPUContext::new(
             Arc::clone(&window),
-            [window.inner_size().width, window.inner_size().height],
+            Vector2::<u32>::from(window.inner_size()).into_inner(),
         );
 
         // Create Timer
         let timer = Timer::new();
+        let frame_stats = FrameStats::new(120);
 
         // Create Renderer
-        let renderer = Renderer2D::new(&render_context);
+        let mut renderer = Renderer2D::new(&render_context);
+        renderer.set_scale_factor(window.scale_factor() as f32, &render_context);
 
         // Create scene
         //  - Ring
-        let center = Vector2::new([
-            render_context.config().width as f32 / 2.,
-            render_context.config().height as f32 / 2.,
-        ]);
+        let center = logical_center(&render_context, &renderer);
         const RADIUS: f32 = 200.;
         let rings = vec![Ring {
             color: Vector4::new([1., 1., 1., 1.]),
             position: center,
             outer_radius: RADIUS,
             inner_radius: RADIUS * 0.9,
+            edge_softness: 1.,
+            layer: 0,
         }];
         let rings = RingRenderer::new(
             rings,
@@ -87,6 +165,7 @@ impl AppInner {
             center: center + Vector2::rotation(START_ANGLE) * RADIUS / 2. * 0.98,
             size: Vector2::new([RADIUS * 0.95, 10.]),
             rotation: START_ANGLE,
+            layer: 0,
         }];
         let rects = RectangleRenderer::new(
             rects,
@@ -102,12 +181,28 @@ impl AppInner {
             render_context,
             shader_manager,
             timer,
+            frame_stats,
             input,
+            events: EventQueue::new(),
             gamepad: None,
+            captured_aim: Vector2::new([0., 0.]),
+            resize: ResizeTracker::new(),
         }
     }
 
     pub fn update_scene(&mut self) {
+        for event in self.events.drain() {
+            if let WindowEvent::KeyboardInput { event, .. } = event {
+                if event.state.is_pressed()
+                    && self
+                        .input
+                        .is_pressed_with(&Key::Character("s".into()), ModifiersState::CONTROL)
+                {
+                    log::info!("Ctrl+S pressed, nothing to save yet");
+                }
+            }
+        }
+
         // Handle Gamepad state
         match self.gamepad {
             None => self.gamepad = self.input.gamepad_map.current(GamepadID::Id0).copied(),
@@ -129,12 +224,7 @@ impl AppInner {
                             let prev = Vector2::new(prev.left_thumb);
                             let gamepad_vec =
                                 Vector2::new(gamepad.left_thumb).normalized() * current.mag();
-                            let mut angle_diff = current.angle() - prev.angle();
-                            if angle_diff < -PI {
-                                angle_diff = -2. * PI - angle_diff;
-                            } else if angle_diff > PI {
-                                angle_diff = 2. * PI - angle_diff
-                            }
+                            let angle_diff = prev.angle_between(&current);
                             gamepad.left_thumb =
                                 gamepad_vec.rotate(angle_diff / SENSITIVITY).into_inner();
                         }
@@ -146,12 +236,7 @@ impl AppInner {
                             let prev = Vector2::new(prev.right_thumb);
                             let gamepad_vec =
                                 Vector2::new(gamepad.right_thumb).normalized() * current.mag();
-                            let mut angle_diff = current.angle() - prev.angle();
-                            if angle_diff < -PI {
-                                angle_diff = -2. * PI - angle_diff;
-                            } else if angle_diff > PI {
-                                angle_diff = 2. * PI - angle_diff
-                            }
+                            let angle_diff = prev.angle_between(&current);
                             if angle_diff > 1. {
                                 println!("{:?}", angle_diff);
                             }
@@ -164,17 +249,50 @@ impl AppInner {
             }
         }
 
+        // Nothing in the scene takes text yet, so just log what was typed
+        let text = self.input.text_input.take_text();
+        let edits = self.input.text_input.take_edits();
+        if !text.is_empty() || !edits.is_empty() {
+            log::info!(
+                "Typed {text:?}, edits {edits:?}, composing {:?}",
+                self.input.text_input.preedit()
+            );
+        }
+
         let delta = self.timer.elapsed_reset();
         self.timer.reset();
+        self.frame_stats.record_frame(Duration::from_secs_f32(delta));
+        let render_stats = self.renderer.last_stats();
+        log::trace!(
+            "FPS: {:.1}, 99th percentile frame time: {:?}, draw calls: {}, instances: {}",
+            self.frame_stats.fps(),
+            self.frame_stats.percentile(0.99),
+            render_stats.draw_calls,
+            render_stats.instances
+        );
 
-        let center = Vector2::new([
-            self.render_context.config().width as f32 / 2.,
-            self.render_context.config().height as f32 / 2.,
-        ]);
+        let center = logical_center(&self.render_context, &self.renderer);
+        // Cursor positions are in physical pixels
+        let mouse_position = self
+            .renderer
+            .screen_to_world(Vector2::new(self.input.mouse_map.mouse_position()));
 
-        let stick_pos = Vector2::new(self.gamepad.map(|x| x.right_thumb).unwrap_or(
-			((Vector2::new(self.input.mouse_map.mouse_position()) - center) / 200. * Vector2::new([1., -1.])).into_inner()
-		));
+        // A captured cursor doesn't move, so aim with the raw mouse movement instead
+        let mouse_aim = match self.input.mode {
+            InputMode::Normal => mouse_position - center,
+            InputMode::CaptureMouse => {
+                let (dx, dy) = self.input.capture_delta();
+                self.captured_aim = self.captured_aim + Vector2::new([dx as f32, dy as f32]);
+                if self.captured_aim.mag() > 200. {
+                    self.captured_aim = self.captured_aim.normalized() * 200.;
+                }
+                self.captured_aim
+            }
+        };
+        let stick_pos =
+            Vector2::new(self.gamepad.map(|x| x.right_thumb).unwrap_or(
+                (mouse_aim / 200. * Vector2::new([1., -1.])).into_inner(),
+            ));
         let len = stick_pos.mag().min(1.) * 200.;
         let angle = stick_pos.angle();
 
@@ -184,8 +302,8 @@ impl AppInner {
         self.scene.1.rects_mut()[0].size[0] = len;
         self.scene.1.rects_mut()[0].rotation = -angle;
 
-        self.scene.0.update_rings(&self.render_context);
-        self.scene.1.update_rects(&self.render_context);
+        self.scene.0.sync(&self.render_context);
+        self.scene.1.sync(&self.render_context);
     }
 }
 
@@ -195,7 +313,7 @@ impl winit::application::ApplicationHandler for App {
             None => {
                 // Create window
                 let window = event_loop
-                    .create_window(Window::default_attributes().with_title(self.title.to_owned()))
+                    .create_window(self.window_config.attributes())
                     .expect("Could not create window");
                 self.inner = Some(AppInner::init(window));
             }
@@ -212,7 +330,8 @@ impl winit::application::ApplicationHandler for App {
         let inner = self.inner.as_mut().unwrap();
         match event {
             DeviceEvent::MouseMotion { delta } => {
-                inner.input.mouse_map.handle_raw_mouse_movement(delta)
+                inner.input.mouse_map.handle_raw_mouse_movement(delta);
+                inner.input.handle_capture_movement(delta);
             }
             DeviceEvent::MouseWheel { delta } => inner.input.mouse_map.handle_raw_scroll(delta),
             _ => (),
@@ -221,16 +340,40 @@ impl winit::application::ApplicationHandler for App {
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         let inner = self.inner.as_mut().unwrap();
+        if event != WindowEvent::RedrawRequested {
+            inner.events.push(event.clone());
+        }
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
                 event_loop.exit();
             }
-            WindowEvent::KeyboardInput { event, .. } => match event.logical_key {
-                Key::Named(NamedKey::Escape) => event_loop.exit(),
-                Key::Named(NamedKey::Space) => inner.shader_manager.reload(),
-                x => inner.input.key_map.handle_key(x, event.state),
-            },
+            WindowEvent::KeyboardInput { event, .. } => {
+                inner.input.text_input.handle_key(&event);
+                inner.input.handle_key(&event);
+                match &event.logical_key {
+                    // Escape releases a captured mouse first, so the window can be left
+                    Key::Named(NamedKey::Escape) if event.state.is_pressed() => {
+                        match inner.input.mode {
+                            InputMode::CaptureMouse => {
+                                inner.input.set_mode(&inner.window, InputMode::Normal)
+                            }
+                            InputMode::Normal => event_loop.exit(),
+                        }
+                    }
+                    Key::Named(NamedKey::Space) => inner.shader_manager.reload(),
+                    Key::Named(NamedKey::Tab) if event.state.is_pressed() => {
+                        let allowed = !inner.input.text_input.ime_allowed();
+                        inner
+                            .input
+                            .text_input
+                            .set_ime_allowed(&inner.window, allowed);
+                    }
+                    _ => (),
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => inner.input.modifiers = modifiers.state(),
+            WindowEvent::Ime(event) => inner.input.text_input.handle_ime(event),
             WindowEvent::CursorMoved { position, .. } => {
                 inner.input.mouse_map.handle_cursor_movement(position);
             }
@@ -238,21 +381,32 @@ impl winit::application::ApplicationHandler for App {
                 inner.input.mouse_map.handle_mouse_scroll(delta);
             }
             WindowEvent::MouseInput { button, state, .. } => {
+                if (button, state) == (MouseButton::Right, ElementState::Pressed) {
+                    inner.input.set_mode(&inner.window, InputMode::CaptureMouse);
+                }
                 inner.input.mouse_map.handle_button(button, state);
             }
             WindowEvent::Resized(new_size) => {
-                // inner.render_context.resize(winit::dpi::PhysicalSize::new(8, 8));
+                // Applied once on the next redraw, however many resizes arrive until then
                 inner
-                    .render_context
-                    .resize([new_size.width, new_size.height]);
-				*inner.renderer.get_uniform().screen_size = [new_size.width as f32, new_size.height as f32];
-                inner.renderer.update_uniform(&inner.render_context);
+                    .resize
+                    .request(Vector2::<u32>::from(new_size).into_inner());
                 inner.window.request_redraw();
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                inner
+                    .renderer
+                    .set_scale_factor(scale_factor as f32, &inner.render_context);
+            }
             WindowEvent::RedrawRequested => {
+                if let Some([width, height]) = inner.resize.apply(&mut inner.render_context) {
+                    *inner.renderer.get_uniform().screen_size = [width as f32, height as f32];
+                    inner.renderer.update_uniform(&inner.render_context);
+                }
+                self.frame_limiter.wait();
                 inner.input.gamepad_map.update();
                 inner.update_scene();
-                inner.renderer.render(
+                let result = inner.renderer.render(
                     [
                         &inner.scene.1 as &dyn Render,
                         &inner.scene.0 as &dyn Render,
@@ -260,6 +414,10 @@ impl winit::application::ApplicationHandler for App {
                     &inner.render_context,
                     &inner.shader_manager,
                 );
+                if let Err(err) = result {
+                    log::error!("Could not render frame: {err}");
+                    event_loop.exit();
+                }
                 inner.window.request_redraw();
             }
             _ => (),
@@ -267,18 +425,126 @@ impl winit::application::ApplicationHandler for App {
     }
 }
 
+/// Center of the window in world space (logical pixels)
+fn logical_center(context: &WGPUContext, renderer: &Renderer2D) -> Vector2<f32> {
+    Vector2::new([
+        context.config().width as f32 / 2.,
+        context.config().height as f32 / 2.,
+    ]) / renderer.uniform().scale_factor
+}
+
+/// How mouse movement is delivered to the application
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InputMode {
+    /// The cursor moves freely and is visible
+    Normal,
+    /// The cursor is hidden and held in place. Raw mouse movement is accumulated and read
+    /// with [`Input::capture_delta`], for camera control for example
+    CaptureMouse,
+}
+
 struct Input {
+    mode: InputMode,
+    // Raw mouse movement since the last call to `capture_delta`
+    capture_delta: (f64, f64),
+    modifiers: ModifiersState,
+    // Keys currently held, including the ones the app handles itself like Escape
+    held_keys: HashSet<Key>,
+    // The same keys by position, which doesn't depend on the keyboard layout
+    held_physical_keys: HashSet<KeyCode>,
     key_map: KeyMap,
     mouse_map: MouseMap,
     gamepad_map: GamepadMap,
+    text_input: TextInput,
 }
 
 impl Input {
     pub fn new() -> Self {
         Self {
+            mode: InputMode::Normal,
+            capture_delta: (0., 0.),
+            modifiers: ModifiersState::empty(),
+            held_keys: HashSet::new(),
+            held_physical_keys: HashSet::new(),
             key_map: KeyMap::new(),
             mouse_map: MouseMap::new(),
             gamepad_map: GamepadMap::new(),
+            text_input: TextInput::new(),
+        }
+    }
+
+    pub fn set_mode(&mut self, window: &Window, mode: InputMode) {
+        if self.mode == mode {
+            return;
+        }
+        match mode {
+            InputMode::Normal => {
+                if let Err(err) = window.set_cursor_grab(CursorGrabMode::None) {
+                    log::warn!("Could not release the cursor: {err}");
+                }
+                window.set_cursor_visible(true);
+            }
+            InputMode::CaptureMouse => {
+                // Not every platform can lock the cursor, so fall back to confining it to
+                // the window. Raw movement is reported either way
+                let grab = window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+                if let Err(err) = grab {
+                    log::warn!("Could not capture the cursor: {err}");
+                }
+                window.set_cursor_visible(false);
+            }
+        }
+        self.capture_delta = (0., 0.);
+        self.mode = mode;
+    }
+
+    fn handle_capture_movement(&mut self, (dx, dy): (f64, f64)) {
+        if self.mode == InputMode::CaptureMouse {
+            self.capture_delta.0 += dx;
+            self.capture_delta.1 += dy;
+        }
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent) {
+        let key = &event.logical_key;
+        match event.state {
+            ElementState::Pressed => self.held_keys.insert(key.clone()),
+            ElementState::Released => self.held_keys.remove(key),
+        };
+        // Keys the platform can't identify by position are only tracked logically
+        if let PhysicalKey::Code(code) = event.physical_key {
+            match event.state {
+                ElementState::Pressed => self.held_physical_keys.insert(code),
+                ElementState::Released => self.held_physical_keys.remove(&code),
+            };
         }
+        self.key_map.handle_key(key.clone(), event.state);
+    }
+
+    /// Shift, Ctrl, Alt and Super as last reported by the window
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Whether `key` is held with exactly `modifiers`, so Ctrl+S doesn't also match
+    /// Ctrl+Shift+S. Keys are compared by their logical value, which Shift can change
+    pub fn is_pressed_with(&self, key: &Key, modifiers: ModifiersState) -> bool {
+        self.held_keys.contains(key) && self.modifiers() == modifiers
+    }
+
+    /// Whether the key at a position is held. Keys are named after the key at that
+    /// position on a US QWERTY layout, so `KeyCode::KeyW` is Z on AZERTY. Use this for
+    /// movement and other bindings that depend on where keys are rather than what they type
+    #[allow(dead_code)]
+    pub fn is_physical_pressed(&self, code: KeyCode) -> bool {
+        self.held_physical_keys.contains(&code)
+    }
+
+    /// Raw mouse movement since the last call. Always zero outside of
+    /// [`InputMode::CaptureMouse`]
+    pub fn capture_delta(&mut self) -> (f64, f64) {
+        std::mem::take(&mut self.capture_delta)
     }
 }
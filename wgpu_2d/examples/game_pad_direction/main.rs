@@ -1,17 +1,20 @@
 use wgpu_2d::*;
 
 mod application;
-use application::App;
+mod text_input;
+use application::{App, WindowConfig};
 
 fn main() {
-    simple_logger::SimpleLogger::new()
-        .with_level(log::LevelFilter::Off)
-        .with_module_level("wgpu_2d", log::LevelFilter::Info)
-        .init()
-        .unwrap();
+    wgpu_2d::init_default_logger().unwrap();
 
     let event_loop = winit::event_loop::EventLoop::new().expect("Could not create event loop");
 
-    let mut app = App::new("My Application");
+    // The ring has a radius of 200 pixels, so keep the window big enough to show all of it
+    let mut app = App::new(WindowConfig {
+        title: String::from("My Application"),
+        min_size: Some([450, 450]),
+        ..Default::default()
+    });
+    app.set_target_fps(Some(144));
     _ = event_loop.run_app(&mut app);
 }
@@ -0,0 +1,87 @@
+use winit::event::{ElementState, Ime, KeyEvent};
+use winit::keyboard::{Key, NamedKey};
+use winit::window::Window;
+
+/// Editing keys that don't produce text, reported separately from the typed characters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEdit {
+    Backspace,
+    Delete,
+}
+
+/// Collects the characters typed into the window, including text committed by an IME.
+/// Meant to be drained once per frame with [`TextInput::take_text`] and
+/// [`TextInput::take_edits`]
+#[derive(Default)]
+pub struct TextInput {
+    text_buffer: String,
+    edits: Vec<TextEdit>,
+    // Text the IME is still composing. Not part of the buffer until it is committed
+    preedit: String,
+    ime_allowed: bool,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_key(&mut self, event: &KeyEvent) {
+        if event.state != ElementState::Pressed {
+            return;
+        }
+        match &event.logical_key {
+            Key::Named(NamedKey::Backspace) => self.edits.push(TextEdit::Backspace),
+            Key::Named(NamedKey::Delete) => self.edits.push(TextEdit::Delete),
+            // `text` also covers named keys that type something, like space, and respects
+            // dead keys. Control characters (tab, enter, escape) are left to the key map
+            _ => {
+                if let Some(text) = &event.text {
+                    self.text_buffer
+                        .extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+
+    pub fn handle_ime(&mut self, event: Ime) {
+        match event {
+            Ime::Preedit(text, _) => self.preedit = text,
+            Ime::Commit(text) => {
+                self.preedit.clear();
+                self.text_buffer.push_str(&text);
+            }
+            Ime::Enabled => (),
+            Ime::Disabled => self.preedit.clear(),
+        }
+    }
+
+    /// Returns everything typed since the last call and clears the buffer
+    pub fn take_text(&mut self) -> String {
+        std::mem::take(&mut self.text_buffer)
+    }
+
+    /// Returns the backspace and delete presses since the last call, in order
+    pub fn take_edits(&mut self) -> Vec<TextEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Text the IME is currently composing, for showing inline in a text field
+    pub fn preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// IME input is off by default on most platforms. Only turn it on while a text field
+    /// has focus, since IMEs can swallow key presses meant for other controls
+    pub fn set_ime_allowed(&mut self, window: &Window, allowed: bool) {
+        self.ime_allowed = allowed;
+        window.set_ime_allowed(allowed);
+        if !allowed {
+            self.preedit.clear();
+        }
+    }
+
+    pub fn ime_allowed(&self) -> bool {
+        self.ime_allowed
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId};
+
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Draws a row of circles along with an ID buffer holding the index of the circle under
+// each pixel. Clicking reads the ID under the cursor back from the GPU and highlights
+// that circle
+fn main() {
+    let event_loop = winit::event_loop::EventLoop::new().expect("Could not create event loop");
+
+    let mut app = App { inner: None };
+    _ = event_loop.run_app(&mut app);
+}
+
+const COLOR: [f32; 4] = [0.2, 0.6, 1., 1.];
+const PICKED_COLOR: [f32; 4] = [1., 0.6, 0.2, 1.];
+
+struct App {
+    inner: Option<AppInner>,
+}
+
+struct AppInner {
+    window: Arc<Window>,
+    render_context: WGPUContext,
+    shader_manager: ShaderManager,
+    renderer: Renderer2D,
+    circles: CircleRenderer,
+    ids: IdBuffer,
+    cursor: Vector2<f32>,
+}
+
+impl AppInner {
+    fn init(window: Window) -> Self {
+        let window = Arc::new(window);
+        let shader_manager = ShaderManager::new("");
+        let render_context = WGPUContext::new(
+            Arc::clone(&window),
+            Vector2::<u32>::from(window.inner_size()).into_inner(),
+        );
+        let renderer = Renderer2D::new(&render_context);
+
+        // Overlapping circles, so picking has to find the one on top
+        let circles = CircleRenderer::new(
+            (0..6)
+                .map(|index| Circle {
+                    color: Vector4::new(COLOR),
+                    position: Vector2::new([120. + index as f32 * 90., 200.]),
+                    radius: 60.,
+                    ..Default::default()
+                })
+                .collect(),
+            renderer.uniform_bind_group_layout(),
+            &render_context,
+            &shader_manager,
+        );
+        let ids = IdBuffer::new(&render_context);
+
+        Self {
+            window,
+            render_context,
+            shader_manager,
+            renderer,
+            circles,
+            ids,
+            cursor: Vector2::new([0., 0.]),
+        }
+    }
+
+    fn pick(&mut self) {
+        let pixel = self.cursor.map(|x| x as u32);
+        let picked = self.ids.read(pixel, &self.render_context);
+
+        for (index, circle) in self.circles.circles_mut().iter_mut().enumerate() {
+            let color = if picked == Some(index as u32) {
+                PICKED_COLOR
+            } else {
+                COLOR
+            };
+            circle.color = Vector4::new(color);
+        }
+        self.circles.sync(&self.render_context);
+    }
+}
+
+impl winit::application::ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.inner.is_none() {
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("GPU picking"))
+                .expect("Could not create window");
+            self.inner = Some(AppInner::init(window));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let inner = self.inner.as_mut().unwrap();
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(new_size) => {
+                inner
+                    .render_context
+                    .resize(Vector2::<u32>::from(new_size).into_inner());
+                inner.ids.resize(&inner.render_context);
+                inner.renderer.get_uniform().screen_size = new_size.into();
+                inner.renderer.update_uniform(&inner.render_context);
+                inner.window.request_redraw();
+            }
+            WindowEvent::CursorMoved { position, .. } => inner.cursor = position.into(),
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state: ElementState::Pressed,
+                ..
+            } => inner.pick(),
+            WindowEvent::RedrawRequested => {
+                let result = inner.renderer.render_with_targets(
+                    [inner.circles.with_ids()],
+                    &[inner.ids.view()],
+                    &inner.render_context,
+                    &inner.shader_manager,
+                );
+                if let Err(err) = result {
+                    eprintln!("Could not render frame: {err}");
+                    event_loop.exit();
+                }
+                inner.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId};
+
+use wgpu::*;
+use wgpu_2d::math::Vector2;
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::*;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Draws a triangle and a quad with two pipelines that share a single shader file.
+// Each pipeline names the vertex and fragment entry points it uses
+fn main() {
+    let event_loop = winit::event_loop::EventLoop::new().expect("Could not create event loop");
+
+    let mut app = App { inner: None };
+    _ = event_loop.run_app(&mut app);
+}
+
+const SHAPES_SHADER: &str = include_str!("shapes.wgsl");
+
+/// A shape drawn entirely by its vertex shader
+struct Shape {
+    label: &'static str,
+    vertex_count: u32,
+}
+
+impl Shape {
+    fn new(
+        label: &'static str,
+        topology: PrimitiveTopology,
+        vertex_count: u32,
+        entry_points: (&'static str, &'static str),
+        layout: &PipelineLayout,
+        context: &WGPUContext,
+        shader_manager: &ShaderManager,
+    ) -> Self {
+        let template = RenderPipelineDescriptorTemplate {
+            label: Some(label),
+            layout: Some(layout.clone()),
+            vertex: VertexStateTemplate {
+                module_path: "shapes.wgsl",
+                entry_point: Some(entry_points.0),
+                buffers: &[],
+            },
+            primitive: PrimitiveState {
+                topology,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(FragmentStateTemplate {
+                module_path: "shapes.wgsl",
+                entry_point: Some(entry_points.1),
+                targets: Box::new([Some(context.config().format.into())]),
+            }),
+            multiview: None,
+            cache: None,
+            push_constant_ranges: Box::new([]),
+            constants: HashMap::new(),
+        };
+        shader_manager.register_render_pipeline(label, template);
+        Self {
+            label,
+            vertex_count,
+        }
+    }
+}
+
+impl Render for Shape {
+    fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        context: &WGPUContext,
+        shader_manager: &ShaderManager,
+    ) {
+        render_pass.set_pipeline(&shader_manager.get_render_pipeline(self.label, context));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+struct App {
+    inner: Option<AppInner>,
+}
+
+struct AppInner {
+    window: Arc<Window>,
+    render_context: WGPUContext,
+    shader_manager: ShaderManager,
+    renderer: Renderer2D,
+    triangle: Shape,
+    quad: Shape,
+}
+
+impl AppInner {
+    fn init(window: Window) -> Self {
+        let window = Arc::new(window);
+        let shader_manager = ShaderManager::new("");
+        let render_context = WGPUContext::new(
+            Arc::clone(&window),
+            Vector2::<u32>::from(window.inner_size()).into_inner(),
+        );
+        let renderer = Renderer2D::new(&render_context);
+
+        shader_manager.register_constant_source("shapes.wgsl", SHAPES_SHADER.into());
+        // The shaders don't read the uniform, but the renderer binds it for every pass
+        let layout = render_context
+            .device()
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Shapes pipeline layout"),
+                bind_group_layouts: &[renderer.uniform_bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+        let triangle = Shape::new(
+            "triangle",
+            PrimitiveTopology::TriangleList,
+            3,
+            ("triangle_vs", "warm_fs"),
+            &layout,
+            &render_context,
+            &shader_manager,
+        );
+        let quad = Shape::new(
+            "quad",
+            PrimitiveTopology::TriangleStrip,
+            4,
+            ("quad_vs", "cool_fs"),
+            &layout,
+            &render_context,
+            &shader_manager,
+        );
+
+        Self {
+            window,
+            render_context,
+            shader_manager,
+            renderer,
+            triangle,
+            quad,
+        }
+    }
+}
+
+impl winit::application::ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.inner.is_none() {
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("Named entry points"))
+                .expect("Could not create window");
+            self.inner = Some(AppInner::init(window));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let inner = self.inner.as_mut().unwrap();
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(new_size) => {
+                inner
+                    .render_context
+                    .resize(Vector2::<u32>::from(new_size).into_inner());
+                inner.window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                let result = inner.renderer.render(
+                    [&inner.triangle, &inner.quad],
+                    &inner.render_context,
+                    &inner.shader_manager,
+                );
+                if let Err(err) = result {
+                    eprintln!("Could not render frame: {err}");
+                    event_loop.exit();
+                }
+                inner.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+}
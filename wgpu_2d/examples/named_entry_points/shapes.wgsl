@@ -0,0 +1,32 @@
+// Both shapes live in this one file. Each pipeline picks its stages by entry point
+
+@vertex
+fn triangle_vs(@builtin(vertex_index) v_id: u32) -> @builtin(position) vec4<f32> {
+	var corners = array<vec2<f32>, 3>(
+		vec2<f32>(-0.8, -0.5),
+		vec2<f32>(-0.2, -0.5),
+		vec2<f32>(-0.5, 0.5),
+	);
+	return vec4<f32>(corners[v_id], 0., 1.);
+}
+
+@vertex
+fn quad_vs(@builtin(vertex_index) v_id: u32) -> @builtin(position) vec4<f32> {
+	var corners = array<vec2<f32>, 4>(
+		vec2<f32>(0.2, -0.5),
+		vec2<f32>(0.8, -0.5),
+		vec2<f32>(0.2, 0.5),
+		vec2<f32>(0.8, 0.5),
+	);
+	return vec4<f32>(corners[v_id], 0., 1.);
+}
+
+@fragment
+fn warm_fs() -> @location(0) vec4<f32> {
+	return vec4<f32>(1., 0.5, 0.2, 1.);
+}
+
+@fragment
+fn cool_fs() -> @location(0) vec4<f32> {
+	return vec4<f32>(0.2, 0.6, 1., 1.);
+}
@@ -0,0 +1,53 @@
+use wgpu_2d::app::AppBuilder;
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+
+use rand::Rng;
+
+const CAPACITY: u32 = 20_000;
+const PER_SECOND: f32 = 4000.;
+
+// A fountain of particles that are moved by a compute shader and drawn straight from
+// the same buffer. The CPU only writes newly emitted particles
+fn main() {
+    AppBuilder::new("GPU particles", |context| {
+        let particles = GpuParticleSystem::new(
+            CAPACITY,
+            context.renderer.uniform_bind_group_layout(),
+            &context.render_context,
+            &context.shader_manager,
+        );
+        // Particles and the fraction of a particle left over from the last frame
+        (particles, 0f32)
+    })
+    .update(|(particles, carry), context, delta| {
+        let config = context.render_context.config();
+        let scale_factor = context.renderer.uniform().scale_factor;
+        let bottom = Vector2::new([config.width as f32 / 2., config.height as f32 - 20.])
+            / scale_factor;
+
+        *carry += PER_SECOND * delta;
+        let count = *carry as usize;
+        *carry -= count as f32;
+
+        let mut rng = rand::rng();
+        let emitted = (0..count)
+            .map(|_| Particle {
+                color: Vector4::new([1., rng.random_range(0.3..0.8), 0.2, 1.]),
+                position: bottom,
+                velocity: Vector2::new([
+                    rng.random_range(-80.0..80.),
+                    rng.random_range(-500.0..-300.),
+                ]),
+                acceleration: Vector2::new([0., 400.]),
+                size: 3.,
+                lifetime: 3.,
+            })
+            .collect::<Vec<_>>();
+        particles.emit(&emitted, &context.render_context);
+        particles.step(delta, &context.render_context);
+    })
+    .render(|(particles, _), frame, shader_manager| frame.draw([particles], shader_manager))
+    .run()
+    .expect("Event loop failed");
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId};
+
+use wgpu_2d::math::{Vector2, Vector4};
+use wgpu_2d::rendering::*;
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Renders the same circle into the top left and bottom right quarters of the window.
+// The bottom right one is a minimap that shows the world zoomed out
+fn main() {
+    let event_loop = winit::event_loop::EventLoop::new().expect("Could not create event loop");
+
+    let mut app = App { inner: None };
+    _ = event_loop.run_app(&mut app);
+}
+
+struct App {
+    inner: Option<AppInner>,
+}
+
+struct AppInner {
+    window: Arc<Window>,
+    render_context: WGPUContext,
+    shader_manager: ShaderManager,
+    renderer: Renderer2D,
+    circles: CircleRenderer,
+}
+
+impl AppInner {
+    fn init(window: Window) -> Self {
+        let window = Arc::new(window);
+        let shader_manager = ShaderManager::new("");
+        let render_context = WGPUContext::new(
+            Arc::clone(&window),
+            Vector2::<u32>::from(window.inner_size()).into_inner(),
+        );
+        let renderer = Renderer2D::new(&render_context);
+
+        let center = Vector2::new([
+            render_context.config().width as f32 / 2.,
+            render_context.config().height as f32 / 2.,
+        ]);
+        let circles = CircleRenderer::new(
+            vec![Circle {
+                color: Vector4::new([0.2, 0.6, 1., 1.]),
+                position: center,
+                radius: center[1] * 0.8,
+                ..Default::default()
+            }],
+            renderer.uniform_bind_group_layout(),
+            &render_context,
+            &shader_manager,
+        );
+
+        Self {
+            window,
+            render_context,
+            shader_manager,
+            renderer,
+            circles,
+        }
+    }
+
+    fn viewports(&self) -> [Viewport; 2] {
+        let half_width = self.render_context.config().width / 2;
+        let half_height = self.render_context.config().height / 2;
+        [
+            Viewport {
+                x: 0,
+                y: 0,
+                width: half_width,
+                height: half_height,
+            },
+            Viewport {
+                x: half_width,
+                y: half_height,
+                width: half_width,
+                height: half_height,
+            },
+        ]
+    }
+}
+
+impl winit::application::ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.inner.is_none() {
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("Viewports"))
+                .expect("Could not create window");
+            self.inner = Some(AppInner::init(window));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let inner = self.inner.as_mut().unwrap();
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(new_size) => {
+                inner
+                    .render_context
+                    .resize(Vector2::<u32>::from(new_size).into_inner());
+                inner.window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                let [main, minimap] = inner.viewports();
+                let minimap_uniform = Uniform {
+                    scale_factor: inner.renderer.uniform().scale_factor / 4.,
+                    ..*inner.renderer.uniform()
+                };
+                let result = inner.renderer.render_viewports_with_uniforms(
+                    [
+                        (main, None, [&inner.circles]),
+                        (minimap, Some(minimap_uniform), [&inner.circles]),
+                    ],
+                    &inner.render_context,
+                    &inner.shader_manager,
+                );
+                if let Err(err) = result {
+                    eprintln!("Could not render frame: {err}");
+                    event_loop.exit();
+                }
+                inner.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use crate::math::{Vector2, Vector4};
+use crate::timer::Timer;
+
+/// Shape of the curve used to go from the start to the end of an animation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EaseKind {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    /// Overshoots the end and springs back
+    ElasticOut,
+    /// Bounces against the end like a dropped ball
+    BounceOut,
+}
+
+/// Maps the progress `t` of an animation to the fraction of the way from start to end.
+/// `t` is clamped to `[0, 1]`. Every curve gives 0 at 0 and 1 at 1
+pub fn ease(t: f32, kind: EaseKind) -> f32 {
+    let t = t.clamp(0., 1.);
+    match kind {
+        EaseKind::Linear => t,
+        EaseKind::QuadIn => t * t,
+        EaseKind::QuadOut => 1. - (1. - t) * (1. - t),
+        EaseKind::QuadInOut => {
+            if t < 0.5 {
+                2. * t * t
+            } else {
+                1. - (-2. * t + 2.).powi(2) / 2.
+            }
+        }
+        EaseKind::CubicIn => t * t * t,
+        EaseKind::CubicOut => 1. - (1. - t).powi(3),
+        EaseKind::CubicInOut => {
+            if t < 0.5 {
+                4. * t * t * t
+            } else {
+                1. - (-2. * t + 2.).powi(3) / 2.
+            }
+        }
+        EaseKind::ElasticOut => {
+            if t == 0. || t == 1. {
+                t
+            } else {
+                2f32.powf(-10. * t) * ((t * 10. - 0.75) * TAU / 3.).sin() + 1.
+            }
+        }
+        EaseKind::BounceOut => bounce_out(t),
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1. / D {
+        N * t * t
+    } else if t < 2. / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// Values that can be interpolated by a [Tween]
+pub trait Lerp: Copy {
+    /// `self` at 0 and `other` at 1. `t` outside of `[0, 1]` extrapolates
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector2<f32> {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector4<f32> {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+/// Animates a value from `start` to `end` over `duration`, starting when it is created.
+///
+/// Time is read from a [Timer] passed to [Tween::value], so any number of tweens can
+/// share the app's timer
+#[derive(Clone, Copy, Debug)]
+pub struct Tween<T: Lerp> {
+    pub start: T,
+    pub end: T,
+    pub ease: EaseKind,
+    start_time: f32,
+    duration: f32,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(start: T, end: T, duration: Duration, ease: EaseKind, timer: &Timer) -> Self {
+        Self {
+            start,
+            end,
+            ease,
+            start_time: timer.elapsed_start(),
+            duration: duration.as_secs_f32(),
+        }
+    }
+
+    /// Current value. Stays at `end` once the tween has finished
+    pub fn value(&self, timer: &Timer) -> T {
+        self.value_at(timer.elapsed_start())
+    }
+
+    /// Value at `elapsed` seconds since the timer was started
+    pub fn value_at(&self, elapsed: f32) -> T {
+        self.start.lerp(self.end, ease(self.progress(elapsed), self.ease))
+    }
+
+    pub fn is_finished(&self, timer: &Timer) -> bool {
+        self.progress(timer.elapsed_start()) >= 1.
+    }
+
+    /// Starts the tween again from the current time
+    pub fn restart(&mut self, timer: &Timer) {
+        self.start_time = timer.elapsed_start();
+    }
+
+    fn progress(&self, elapsed: f32) -> f32 {
+        if self.duration <= 0. {
+            return 1.;
+        }
+        ((elapsed - self.start_time) / self.duration).clamp(0., 1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_hit_endpoints() {
+        use EaseKind::*;
+        for kind in [
+            Linear, QuadIn, QuadOut, QuadInOut, CubicIn, CubicOut, CubicInOut, ElasticOut,
+            BounceOut,
+        ] {
+            assert!(ease(0., kind).abs() < 1e-6, "{kind:?}");
+            assert!((ease(1., kind) - 1.).abs() < 1e-6, "{kind:?}");
+            assert!((ease(2., kind) - 1.).abs() < 1e-6, "{kind:?}");
+        }
+        assert_eq!(ease(0.5, QuadIn), 0.25);
+        assert_eq!(ease(0.5, QuadInOut), 0.5);
+        // Elastic overshoots the end on the way
+        assert!((0..100).any(|i| ease(i as f32 / 100., ElasticOut) > 1.));
+    }
+
+    #[test]
+    fn tween_interpolates_over_duration() {
+        let mut tween = Tween::new(
+            Vector2::new([0., 10.]),
+            Vector2::new([10., 0.]),
+            Duration::from_secs(2),
+            EaseKind::Linear,
+            &Timer::new(),
+        );
+        tween.start_time = 1.;
+        assert_eq!(*tween.value_at(0.), [0., 10.]);
+        assert_eq!(*tween.value_at(2.), [5., 5.]);
+        assert_eq!(*tween.value_at(5.), [10., 0.]);
+    }
+}
@@ -0,0 +1,229 @@
+//! A ready made winit application for small demos.
+//!
+//! [AppBuilder] takes closures for setting up, updating and rendering a scene and
+//! handles the window, resizes and default key bindings. Implement
+//! [winit::application::ApplicationHandler] directly for anything it doesn't cover
+
+use std::sync::Arc;
+
+use winit::application::ApplicationHandler;
+use winit::error::EventLoopError;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{Key, NamedKey};
+use winit::window::{Window, WindowAttributes, WindowId};
+
+use crate::events::EventQueue;
+use crate::rendering::{FrameContext, Renderer2D};
+use crate::shader_manager::ShaderManager;
+use crate::timer::Timer;
+use crate::wgpu_context::{ResizeTracker, WGPUContext};
+
+/// Everything an [AppBuilder] creates for the window, handed to each closure
+pub struct AppContext {
+    pub window: Arc<Window>,
+    pub render_context: WGPUContext,
+    pub shader_manager: ShaderManager,
+    pub renderer: Renderer2D,
+    /// Window events since the last frame, other than redraw requests. Drain them in
+    /// the update closure. Events left over are dropped once the frame is rendered
+    pub events: EventQueue<WindowEvent>,
+}
+
+type Setup<S> = Box<dyn FnOnce(&mut AppContext) -> S>;
+type Update<S> = Box<dyn FnMut(&mut S, &mut AppContext, f32)>;
+type Render<S> = Box<dyn FnMut(&S, &mut FrameContext, &ShaderManager)>;
+type OnWindowEvent<S> = Box<dyn FnMut(&mut S, &mut AppContext, &WindowEvent)>;
+type ConfigureWindow = Box<dyn FnOnce(WindowAttributes) -> WindowAttributes>;
+
+/// Builds and runs an application with state `S`.
+///
+/// The window closes on Escape and Space reloads the shaders. Resizes and scale factor
+/// changes are applied to the renderer before the next frame.
+///
+/// ```no_run
+/// # use wgpu_2d::app::AppBuilder;
+/// # use wgpu_2d::rendering::CircleRenderer;
+/// AppBuilder::new("Demo", |context| {
+///     CircleRenderer::new(
+///         Vec::new(),
+///         context.renderer.uniform_bind_group_layout(),
+///         &context.render_context,
+///         &context.shader_manager,
+///     )
+/// })
+/// .render(|circles, frame, shader_manager| frame.draw([circles], shader_manager))
+/// .run()
+/// .unwrap();
+/// ```
+pub struct AppBuilder<S> {
+    title: String,
+    configure_window: Option<ConfigureWindow>,
+    setup: Option<Setup<S>>,
+    update: Update<S>,
+    render: Render<S>,
+    on_window_event: OnWindowEvent<S>,
+}
+
+impl<S: 'static> AppBuilder<S> {
+    /// `setup` creates the state once the window exists
+    pub fn new(title: &str, setup: impl FnOnce(&mut AppContext) -> S + 'static) -> Self {
+        Self {
+            title: title.to_owned(),
+            configure_window: None,
+            setup: Some(Box::new(setup)),
+            update: Box::new(|_, _, _| ()),
+            render: Box::new(|_, _, _| ()),
+            on_window_event: Box::new(|_, _, _| ()),
+        }
+    }
+
+    /// Adjusts the attributes the window is created with, which only set the title by
+    /// default. E.g. `|attributes| attributes.with_transparent(true)` for an overlay
+    /// or `.with_decorations(false)` for a borderless window
+    pub fn window_attributes(
+        mut self,
+        configure: impl FnOnce(WindowAttributes) -> WindowAttributes + 'static,
+    ) -> Self {
+        self.configure_window = Some(Box::new(configure));
+        self
+    }
+
+    /// Called before rendering each frame with the seconds since the previous one
+    pub fn update(mut self, update: impl FnMut(&mut S, &mut AppContext, f32) + 'static) -> Self {
+        self.update = Box::new(update);
+        self
+    }
+
+    /// Draws each frame. The frame is cleared before and presented after
+    pub fn render(
+        mut self,
+        render: impl FnMut(&S, &mut FrameContext, &ShaderManager) + 'static,
+    ) -> Self {
+        self.render = Box::new(render);
+        self
+    }
+
+    /// Called with every window event before the default handling, e.g. for input
+    pub fn on_window_event(
+        mut self,
+        on_window_event: impl FnMut(&mut S, &mut AppContext, &WindowEvent) + 'static,
+    ) -> Self {
+        self.on_window_event = Box::new(on_window_event);
+        self
+    }
+
+    /// Creates the event loop and runs the application until the window is closed
+    pub fn run(self) -> Result<(), EventLoopError> {
+        let event_loop = EventLoop::new()?;
+        event_loop.run_app(&mut BuiltApp {
+            builder: self,
+            running: None,
+        })
+    }
+}
+
+struct BuiltApp<S> {
+    builder: AppBuilder<S>,
+    running: Option<Running<S>>,
+}
+
+struct Running<S> {
+    context: AppContext,
+    state: S,
+    timer: Timer,
+    resize: ResizeTracker,
+}
+
+impl<S: 'static> ApplicationHandler for BuiltApp<S> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(setup) = self.builder.setup.take() else {
+            return;
+        };
+        let mut attributes = Window::default_attributes().with_title(self.builder.title.clone());
+        if let Some(configure) = self.builder.configure_window.take() {
+            attributes = configure(attributes);
+        }
+        let window = event_loop
+            .create_window(attributes)
+            .expect("Could not create window");
+        let window = Arc::new(window);
+        let render_context = WGPUContext::new(
+            Arc::clone(&window),
+            [window.inner_size().width, window.inner_size().height],
+        );
+        let mut renderer = Renderer2D::new(&render_context);
+        renderer.set_scale_factor(window.scale_factor() as f32, &render_context);
+
+        let mut context = AppContext {
+            window,
+            render_context,
+            shader_manager: ShaderManager::new(""),
+            renderer,
+            events: EventQueue::new(),
+        };
+        let state = setup(&mut context);
+        self.running = Some(Running {
+            context,
+            state,
+            timer: Timer::new(),
+            resize: ResizeTracker::new(),
+        });
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let Some(running) = &mut self.running else {
+            return;
+        };
+        let context = &mut running.context;
+        (self.builder.on_window_event)(&mut running.state, context, &event);
+        if event != WindowEvent::RedrawRequested {
+            context.events.push(event.clone());
+        }
+
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() => {
+                match event.logical_key {
+                    Key::Named(NamedKey::Escape) => event_loop.exit(),
+                    Key::Named(NamedKey::Space) => context.shader_manager.reload(),
+                    _ => (),
+                }
+            }
+            WindowEvent::Resized(new_size) => {
+                running.resize.request([new_size.width, new_size.height]);
+                context.window.request_redraw();
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                context
+                    .renderer
+                    .set_scale_factor(scale_factor as f32, &context.render_context);
+            }
+            WindowEvent::RedrawRequested => {
+                if let Some([width, height]) = running.resize.apply(&mut context.render_context) {
+                    *context.renderer.get_uniform().screen_size = [width as f32, height as f32];
+                    context.renderer.update_uniform(&context.render_context);
+                }
+
+                let delta = running.timer.elapsed_reset();
+                running.timer.reset();
+                (self.builder.update)(&mut running.state, context, delta);
+
+                match context.renderer.begin_frame(&context.render_context) {
+                    Ok(Some(mut frame)) => {
+                        (self.builder.render)(&running.state, &mut frame, &context.shader_manager);
+                        frame.end_frame();
+                    }
+                    Ok(None) => (),
+                    Err(err) => {
+                        log::error!("Could not render frame: {err}");
+                        event_loop.exit();
+                    }
+                }
+                context.events.clear();
+                context.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Overlap tests between circles and axis aligned rects, e.g. for [crate::rendering::Circle]
+//! and unrotated [crate::rendering::CenterRect]s.
+//!
+//! Shapes that only touch along an edge don't overlap
+
+use crate::math::Vector2;
+
+/// An axis aligned rect
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Same layout as a [crate::rendering::CenterRect]
+    pub fn from_center(center: Vector2<f32>, size: Vector2<f32>) -> Self {
+        Self {
+            min: center - size / 2.,
+            max: center + size / 2.,
+        }
+    }
+
+    pub fn center(&self) -> Vector2<f32> {
+        (self.min + self.max) / 2.
+    }
+
+    /// The point inside the rect closest to `point`, which is `point` itself if it is
+    /// inside
+    pub fn closest_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new([
+            point[0].clamp(self.min[0], self.max[0]),
+            point[1].clamp(self.min[1], self.max[1]),
+        ])
+    }
+}
+
+pub fn circle_circle(c1: Vector2<f32>, r1: f32, c2: Vector2<f32>, r2: f32) -> bool {
+    let offset = c2 - c1;
+    offset.dot(&offset) < (r1 + r2).powi(2)
+}
+
+pub fn circle_rect(center: Vector2<f32>, radius: f32, rect: &Aabb) -> bool {
+    let offset = center - rect.closest_point(center);
+    offset.dot(&offset) < radius.powi(2)
+}
+
+/// The shortest translation that moves `a` out of `b`, or [None] if they don't overlap.
+/// The rects are pushed apart along the axis they overlap least on
+pub fn rect_rect(a: &Aabb, b: &Aabb) -> Option<Vector2<f32>> {
+    let overlap = |axis: usize| a.max[axis].min(b.max[axis]) - a.min[axis].max(b.min[axis]);
+    let (x, y) = (overlap(0), overlap(1));
+    if x <= 0. || y <= 0. {
+        return None;
+    }
+
+    let (axis, depth) = if x < y { (0, x) } else { (1, y) };
+    let mut translation = Vector2::new([0., 0.]);
+    translation[axis] = if a.center()[axis] < b.center()[axis] {
+        -depth
+    } else {
+        depth
+    };
+    Some(translation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32) -> Vector2<f32> {
+        Vector2::new([x, y])
+    }
+
+    #[test]
+    fn circles() {
+        assert!(circle_circle(v(0., 0.), 1., v(1.5, 0.), 1.));
+        assert!(!circle_circle(v(0., 0.), 1., v(2., 0.), 1.));
+        assert!(!circle_circle(v(0., 0.), 1., v(3., 3.), 1.));
+    }
+
+    #[test]
+    fn circle_and_rect() {
+        let rect = Aabb::new(v(0., 0.), v(10., 10.));
+        assert!(circle_rect(v(5., 5.), 1., &rect));
+        assert!(circle_rect(v(11., 5.), 2., &rect));
+        // Near the corner only the distance to the corner counts
+        assert!(!circle_rect(v(11.5, 11.5), 2., &rect));
+        assert!(circle_rect(v(11., 11.), 2., &rect));
+    }
+
+    #[test]
+    fn rect_translation() {
+        let b = Aabb::from_center(v(0., 0.), v(10., 10.));
+        let a = Aabb::from_center(v(8., 1.), v(10., 10.));
+        let translation = rect_rect(&a, &b).unwrap();
+        assert_eq!(*translation, [2., 0.]);
+
+        let a = Aabb::from_center(v(-1., -9.), v(10., 10.));
+        assert_eq!(*rect_rect(&a, &b).unwrap(), [0., -1.]);
+
+        let a = Aabb::from_center(v(10., 0.), v(10., 10.));
+        assert!(rect_rect(&a, &b).is_none());
+    }
+}
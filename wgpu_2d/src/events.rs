@@ -0,0 +1,73 @@
+//! Buffering of events for code that runs once per frame
+
+/// Collects events as they arrive so per-frame update code can handle them all at once
+/// instead of being interleaved with the event loop.
+///
+/// Events are drained in the order they were pushed. Anything derived from the same
+/// events elsewhere, e.g. held keys in an input map updated as each event arrives,
+/// already reflects all queued events when they are drained, not the state at the time
+/// of each event
+pub struct EventQueue<E> {
+    events: Vec<E>,
+}
+
+impl<E> Default for EventQueue<E> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl<E> EventQueue<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: E) {
+        self.events.push(event);
+    }
+
+    /// Removes and returns the queued events, oldest first
+    pub fn drain(&mut self) -> std::vec::Drain<'_, E> {
+        self.events.drain(..)
+    }
+
+    /// The queued events, oldest first, without removing them
+    pub fn iter(&self) -> std::slice::Iter<'_, E> {
+        self.events.iter()
+    }
+
+    /// Drops the queued events, e.g. at the end of a frame so unhandled events don't
+    /// pile up
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drains_in_order() {
+        let mut queue = EventQueue::new();
+        queue.push(1);
+        queue.push(2);
+        queue.push(3);
+        assert_eq!(queue.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(queue.drain().collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(queue.is_empty());
+
+        queue.push(4);
+        assert_eq!(queue.len(), 1);
+        queue.clear();
+        assert_eq!(queue.drain().count(), 0);
+    }
+}
@@ -1,18 +1,27 @@
+pub mod animation;
+#[cfg(feature = "winit")]
+pub mod app;
+pub mod collision;
+pub mod events;
 pub mod math;
 pub mod rendering;
+pub mod scene;
 pub mod shader_manager;
+#[cfg(test)]
+mod test_utils;
 pub mod timer;
 pub mod wgpu_context;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
+/// Logs this crate at info level and everything else at warn level to stdout.
+///
+/// The library itself only logs through the `log` facade and never sets a logger, so
+/// applications with their own logger can ignore this. Fails if a logger is already set
+#[cfg(feature = "logger")]
+pub fn init_default_logger() -> Result<(), log::SetLoggerError> {
+    simple_logger::SimpleLogger::new()
+        .with_level(log::LevelFilter::Warn)
+        .with_module_level("wgpu_2d", log::LevelFilter::Info)
+        .init()
 }
 
 // (Finished): Finish Gamepad map and gamepad aim-assist
@@ -35,8 +44,9 @@ mod tests {
 // (Finished) : Add derive macros for Buffer data
 // 		- One macro for Vertex data
 // 		- One macro for Uniform data
-// TODO (Changed) : Unify the renderers for each type of primitive (point, triangle,
+// (Finished) : Unify the renderers for each type of primitive (point, triangle,
 //        center_rect and circle for now) into a single struct with a generic parameter
+//      - Instanced primitives (rect, circle, ring) use PrimitiveRenderer<P: Primitive>
 //      - Define a trait for each type of primitive
 //      	- This trait should include
 //      		- registering shaders
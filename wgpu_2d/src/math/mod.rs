@@ -57,6 +57,28 @@ mod vector {
                     unsafe { &mut *(self.data.as_mut_ptr().cast()) }
                 }
             }
+
+            // Only the logical elements are (de)serialized, not the padding
+            #[cfg(feature = "serde")]
+            impl<T: serde::Serialize> serde::Serialize for $outer_name<T> {
+                fn serialize<S: serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    <[T; $deref_len]>::serialize(self, serializer)
+                }
+            }
+
+            #[cfg(feature = "serde")]
+            impl<'de, T: serde::Deserialize<'de> + Zeroable> serde::Deserialize<'de>
+                for $outer_name<T>
+            {
+                fn deserialize<D: serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    <[T; $deref_len]>::deserialize(deserializer).map(Self::new)
+                }
+            }
         };
     }
 
@@ -478,6 +500,7 @@ mod vector {
 	}
 
     // tests for the above implemenations
+    #[cfg(test)]
     macro_rules! impl_math_tests {
 		($inner_ty: ty, $outer_ty: tt, $size: literal, $($indeces: literal),+) => {
 			#[cfg(test)]
@@ -557,7 +580,7 @@ mod vector {
 
 					let dot_normal = strip_plus!($(+ x[$indeces] * y[$indeces])+);
 
-					let z = $outer_ty::<$inner_ty>::new(x).dot($outer_ty::<$inner_ty>::new(y));
+					let z = $outer_ty::<$inner_ty>::new(x).dot(&$outer_ty::<$inner_ty>::new(y));
 					assert_eq!(dot_normal, z);
 				});
 			}
@@ -611,11 +634,134 @@ mod vector {
         pub fn angle(&self) -> f32 {
             self.data[1].atan2(self.data[0])
         }
+
+        /// The angle to rotate `self` by to point along `other`, in `[-PI, PI]`.
+        /// Positive angles turn from the x axis towards the y axis
+        pub fn signed_angle_to(&self, other: &Self) -> f32 {
+            let cross = self.data[0] * other.data[1] - self.data[1] * other.data[0];
+            cross.atan2(self.dot(other))
+        }
+
+        /// The change in [Self::angle] from `self` to `other`, wrapped the short way
+        /// around into `[-PI, PI)`
+        pub fn angle_between(&self, other: &Self) -> f32 {
+            use std::f32::consts::{PI, TAU};
+            (other.angle() - self.angle() + PI).rem_euclid(TAU) - PI
+        }
+
+        /// Mirrors `self` about the line perpendicular to `normal`, e.g. to bounce a
+        /// velocity off a wall. `normal` must be normalized
+        pub fn reflect(&self, normal: &Self) -> Self {
+            *self - *normal * (2. * self.dot(normal))
+        }
+
+        /// The component of `self` along `onto`, which doesn't have to be normalized.
+        /// Zero if `onto` is zero
+        pub fn project_onto(&self, onto: &Self) -> Self {
+            let length_squared = onto.dot(onto);
+            if length_squared == 0. {
+                return Self::new([0., 0.]);
+            }
+            *onto * (self.dot(onto) / length_squared)
+        }
+
+        /// Rounds each component to the nearest multiple of `grid`, e.g. to place a tile.
+        /// Halfway values round away from zero
+        pub fn snap(&self, grid: f32) -> Self {
+            self.map_to_grid(grid, f32::round)
+        }
+
+        /// Rounds each component down to a multiple of `grid`, which is the corner of the
+        /// cell containing `self` with the smallest coordinates
+        pub fn floor_to_grid(&self, grid: f32) -> Self {
+            self.map_to_grid(grid, f32::floor)
+        }
+
+        /// Rounds each component up to a multiple of `grid`
+        pub fn ceil_to_grid(&self, grid: f32) -> Self {
+            self.map_to_grid(grid, f32::ceil)
+        }
+
+        /// Index of the grid cell containing `self`, where cell `[0, 0]` spans from the
+        /// origin to `[cell_size, cell_size]`. Negative positions are in negative cells,
+        /// so `-0.5` is in cell `-1`
+        pub fn to_grid(&self, cell_size: f32) -> Vector2<i32> {
+            Vector2::new(self.data.map(|x| (x / cell_size).floor() as i32))
+        }
+
+        fn map_to_grid(&self, grid: f32, round: fn(f32) -> f32) -> Self {
+            Self::new(self.data.map(|x| round(x / grid) * grid))
+        }
     }
     impl Vector2<f64> {
         pub fn angle(&self) -> f64 {
             self.data[1].atan2(self.data[0])
         }
+
+        /// The angle to rotate `self` by to point along `other`, in `[-PI, PI]`.
+        /// Positive angles turn from the x axis towards the y axis
+        pub fn signed_angle_to(&self, other: &Self) -> f64 {
+            let cross = self.data[0] * other.data[1] - self.data[1] * other.data[0];
+            cross.atan2(self.dot(other))
+        }
+
+        /// The change in [Self::angle] from `self` to `other`, wrapped the short way
+        /// around into `[-PI, PI)`
+        pub fn angle_between(&self, other: &Self) -> f64 {
+            use std::f64::consts::{PI, TAU};
+            (other.angle() - self.angle() + PI).rem_euclid(TAU) - PI
+        }
+
+        /// Mirrors `self` about the line perpendicular to `normal`, e.g. to bounce a
+        /// velocity off a wall. `normal` must be normalized
+        pub fn reflect(&self, normal: &Self) -> Self {
+            *self - *normal * (2. * self.dot(normal))
+        }
+
+        /// The component of `self` along `onto`, which doesn't have to be normalized.
+        /// Zero if `onto` is zero
+        pub fn project_onto(&self, onto: &Self) -> Self {
+            let length_squared = onto.dot(onto);
+            if length_squared == 0. {
+                return Self::new([0., 0.]);
+            }
+            *onto * (self.dot(onto) / length_squared)
+        }
+    }
+
+    // Color helpers for RGBA vectors, as used by the `color` fields of the primitives
+    impl Vector4<f32> {
+        /// Clamps every channel to `[0, 1]`
+        pub fn saturate(&self) -> Self {
+            Self::new(self.data.map(|channel| channel.clamp(0., 1.)))
+        }
+
+        /// Decodes sRGB encoded color channels to linear ones, e.g. for colors picked in
+        /// an image editor. Alpha is left untouched
+        pub fn to_linear(&self) -> Self {
+            let [r, g, b, a] = self.data;
+            let decode = |channel: f32| {
+                if channel <= 0.04045 {
+                    channel / 12.92
+                } else {
+                    ((channel + 0.055) / 1.055).powf(2.4)
+                }
+            };
+            Self::new([decode(r), decode(g), decode(b), a])
+        }
+
+        /// Encodes linear color channels as sRGB. Alpha is left untouched
+        pub fn to_srgb(&self) -> Self {
+            let [r, g, b, a] = self.data;
+            let encode = |channel: f32| {
+                if channel <= 0.0031308 {
+                    channel * 12.92
+                } else {
+                    1.055 * channel.powf(1. / 2.4) - 0.055
+                }
+            };
+            Self::new([encode(r), encode(g), encode(b), a])
+        }
     }
 
     // Cross Product is only available in 3 dimensions
@@ -632,6 +778,19 @@ mod vector {
         }
     }
 
+    impl Vector3<f64> {
+        pub fn cross_product(&self, other: &Self) -> Self {
+            Self {
+                data: [
+                    self.data[1] * other.data[2] - self.data[2] * other.data[1],
+                    -self.data[0] * other.data[2] + self.data[2] * other.data[0],
+                    self.data[0] * other.data[1] - self.data[1] * other.data[0],
+                    0.,
+                ],
+            }
+        }
+    }
+
     impl Vector3<i32> {
         pub fn cross_product(&self, other: &Self) -> Self {
             Self {
@@ -645,6 +804,24 @@ mod vector {
         }
     }
 
+    // Swizzles keeping the leading elements, e.g. the RGB of a color or the position
+    // of a 3D point in the plane
+    impl<T: Copy + Zeroable> Vector3<T> {
+        pub fn xy(&self) -> Vector2<T> {
+            Vector2::new([self.data[0], self.data[1]])
+        }
+    }
+
+    impl<T: Copy + Zeroable> Vector4<T> {
+        pub fn xy(&self) -> Vector2<T> {
+            Vector2::new([self.data[0], self.data[1]])
+        }
+
+        pub fn xyz(&self) -> Vector3<T> {
+            Vector3::new([self.data[0], self.data[1], self.data[2]])
+        }
+    }
+
     impl_def!(Vector2, 2, 2);
     impl_def!(Vector3, 4, 3);
     impl_def!(Vector4, 4, 4);
@@ -654,23 +831,296 @@ mod vector {
     impl_math!(Vector2<f64>, f64, 0, 1);
     impl_math!(Vector2<i32>, i32, 0, 1);
 
-    impl_math!(Vector3<f32>, f32, 0, 1, 2, 3);
-    impl_math!(Vector3<f64>, f64, 0, 1, 2, 3);
+    // The padding element is passed through rather than operated on, so it stays zero
+    // and never shows up in dot products (0. / 0. would make it NaN)
+    impl_math!(Vector3<f32>, f32, 0, 1, 2; 3);
+    impl_math!(Vector3<f64>, f64, 0, 1, 2; 3);
     impl_math!(Vector3<i32>, i32, 0, 1, 2; 3);
 
     impl_math!(Vector4<f32>, f32, 0, 1, 2, 3);
     impl_math!(Vector4<f64>, f64, 0, 1, 2, 3);
     impl_math!(Vector4<i32>, i32, 0, 1, 2, 3);
 
+    // Cursor positions and window sizes from winit, e.g. for `WGPUContext::resize` with
+    // `Vector2::<u32>::from(size).into_inner()`
+    #[cfg(feature = "winit")]
+    impl From<winit::dpi::PhysicalPosition<f64>> for Vector2<f32> {
+        fn from(position: winit::dpi::PhysicalPosition<f64>) -> Self {
+            Self::new([position.x as f32, position.y as f32])
+        }
+    }
+
+    #[cfg(feature = "winit")]
+    impl From<winit::dpi::PhysicalSize<u32>> for Vector2<f32> {
+        fn from(size: winit::dpi::PhysicalSize<u32>) -> Self {
+            Self::new([size.width as f32, size.height as f32])
+        }
+    }
+
+    #[cfg(feature = "winit")]
+    impl From<winit::dpi::PhysicalSize<u32>> for Vector2<u32> {
+        fn from(size: winit::dpi::PhysicalSize<u32>) -> Self {
+            Self::new([size.width, size.height])
+        }
+    }
+
+    #[cfg(test)]
     mod vector2_f32_tests {
+        use super::*;
+        use rand::{rng, Rng};
         impl_math_tests!(f32, Vector2, 2, 0, 1);
+
+        #[test]
+        fn angles_wrap_around() {
+            use std::f32::consts::PI;
+
+            let x = Vector2::new([1f32, 0.]);
+            let y = Vector2::new([0f32, 1.]);
+            assert!((x.signed_angle_to(&y) - PI / 2.).abs() < 1e-6);
+            assert!((y.signed_angle_to(&x) + PI / 2.).abs() < 1e-6);
+            assert!((y.angle_between(&x) + PI / 2.).abs() < 1e-6);
+
+            // Crossing the negative x axis takes the short way around
+            let above = Vector2::rotation(PI - 0.1);
+            let below = Vector2::rotation(-PI + 0.1);
+            assert!((above.signed_angle_to(&below) - 0.2).abs() < 1e-5);
+            assert!((below.signed_angle_to(&above) + 0.2).abs() < 1e-5);
+            assert!((above.angle_between(&below) - 0.2).abs() < 1e-5);
+            assert!((below.angle_between(&above) + 0.2).abs() < 1e-5);
+
+            assert!((x.angle_between(&Vector2::new([-1., 0.])).abs() - PI).abs() < 1e-6);
+            assert_eq!(x.angle_between(&x), 0.);
+
+            let above = Vector2::new([-1f64, 0.1]);
+            let below = Vector2::new([-1f64, -0.1]);
+            let expected = 2. * 0.1f64.atan();
+            assert!((above.angle_between(&below) - expected).abs() < 1e-12);
+            assert!((below.angle_between(&above) + expected).abs() < 1e-12);
+            assert_eq!(x.signed_angle_to(&x), 0.);
+        }
+
+        #[test]
+        fn reflect_and_project() {
+            let up = Vector2::new([0f32, 1.]);
+            assert_eq!(*Vector2::new([1f32, -1.]).reflect(&up), [1., 1.]);
+            let v = Vector2::new([3f32, 4.]);
+            assert_eq!(*v.project_onto(&Vector2::new([2., 0.])), [3., 0.]);
+            assert_eq!(*v.project_onto(&Vector2::new([0., 0.])), [0., 0.]);
+        }
+
+        #[test]
+        fn grid() {
+            let v = Vector2::new([-5f32, 37.]);
+            assert_eq!(*v.snap(16.), [0., 32.]);
+            assert_eq!(*v.floor_to_grid(16.), [-16., 32.]);
+            assert_eq!(*v.ceil_to_grid(16.), [0., 48.]);
+            assert_eq!(*Vector2::new([-9f32, 8.]).snap(16.), [-16., 16.]);
+
+            assert_eq!(v.to_grid(16.).into_inner(), [-1, 2]);
+            assert_eq!(Vector2::new([-16f32, 0.]).to_grid(16.).into_inner(), [-1, 0]);
+            assert_eq!(Vector2::new([-16.5f32, -0.5]).to_grid(16.).into_inner(), [-2, -1]);
+        }
     }
+    #[cfg(test)]
     mod vector3_f32_tests {
+        use super::*;
+        use rand::{rng, Rng};
         impl_math_tests!(f32, Vector3, 3, 0, 1, 2);
+
+        #[test]
+        fn padding_is_ignored() {
+            // Dividing used to compute 0. / 0. in the padding, which made dot NaN
+            let x = Vector3::new([1f32, 2., 3.]) / Vector3::new([1., 1., 1.]);
+            assert_eq!(bytemuck::cast::<_, [f32; 4]>(x)[3], 0.);
+            assert_eq!(x.dot(&x), 14.);
+
+            let y = Vector3::new([3f32, 0., 4.]);
+            assert_eq!(y.mag(), 5.);
+            assert_eq!(*y.normalized(), [0.6, 0., 0.8]);
+            assert_eq!(y.normalized().dot(&y.normalized()), 1.);
+
+            let z = Vector3::new([1f32, 0., 0.]).cross_product(&Vector3::new([0., 1., 0.]));
+            assert_eq!(*z, [0., 0., 1.]);
+            assert_eq!(bytemuck::cast::<_, [f32; 4]>(z)[3], 0.);
+        }
     }
+    #[cfg(test)]
     mod vector4_f32_tests {
+        use super::*;
+        use rand::{rng, Rng};
         impl_math_tests!(f32, Vector4, 4, 0, 1, 2, 3);
+
+        fn assert_close(a: Vector4<f32>, b: [f32; 4]) {
+            for (a, b) in a.into_inner().into_iter().zip(b) {
+                assert!((a - b).abs() < 1e-4, "{a} != {b}");
+            }
+        }
+
+        #[test]
+        fn color_conversions() {
+            let srgb = Vector4::new([0., 0.5, 1., 0.5]);
+            assert_close(srgb.to_linear(), [0., 0.21404, 1., 0.5]);
+            // Both sides of the switch to the linear segment
+            assert_close(
+                Vector4::new([0.5, 0.04045, 0.2, 1.]).to_linear(),
+                [0.21404, 0.0031308, 0.033105, 1.],
+            );
+            assert_close(
+                Vector4::new([0.5, 0.0031308, 0.214041, 0.25]).to_srgb(),
+                [0.735357, 0.04045, 0.5, 0.25],
+            );
+
+            let color = Vector4::new([0.1, 0.3, 0.7, 0.9]);
+            assert_close(color.to_srgb().to_linear(), [0.1, 0.3, 0.7, 0.9]);
+        }
+
+        #[test]
+        fn swizzle() {
+            let vector = Vector4::new([1, 2, 3, 4]);
+            assert_eq!(*vector.xy(), [1, 2]);
+            assert_eq!(*vector.xyz(), [1, 2, 3]);
+            assert_eq!(*Vector3::new([1., 2., 3.]).xy(), [1., 2.]);
+        }
+
+        #[test]
+        fn saturate() {
+            let color = Vector4::new([-0.5, 0.5, 1.5, 2.]).saturate();
+            assert_eq!(*color, [0., 0.5, 1., 1.]);
+        }
+    }
+    #[cfg(all(test, feature = "winit"))]
+    mod winit_tests {
+        use super::*;
+        use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+        #[test]
+        fn from_physical() {
+            let position = Vector2::<f32>::from(PhysicalPosition::new(1.5, 2.));
+            assert_eq!(*position, [1.5, 2.]);
+            let size = Vector2::<f32>::from(PhysicalSize::new(800u32, 600));
+            assert_eq!(*size, [800., 600.]);
+            let size = Vector2::<u32>::from(PhysicalSize::new(800u32, 600));
+            assert_eq!(size.into_inner(), [800, 600]);
+        }
+    }
+    #[cfg(all(test, feature = "serde"))]
+    mod serde_tests {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let vector = Vector2::new([1f32, 2.]);
+            let json = serde_json::to_string(&vector).unwrap();
+            assert_eq!(json, "[1.0,2.0]");
+            let output: Vector2<f32> = serde_json::from_str(&json).unwrap();
+            assert_eq!(*output, *vector);
+
+            // Padding element is not part of the serialized form
+            let vector = Vector3::new([1f32, 2., 3.]);
+            let json = serde_json::to_string(&vector).unwrap();
+            assert_eq!(json, "[1.0,2.0,3.0]");
+            let output: Vector3<f32> = serde_json::from_str(&json).unwrap();
+            assert_eq!(*output, *vector);
+
+            let vector = Vector4::new([1f32, 2., 3., 4.]);
+            let json = serde_json::to_string(&vector).unwrap();
+            assert_eq!(json, "[1.0,2.0,3.0,4.0]");
+            let output: Vector4<f32> = serde_json::from_str(&json).unwrap();
+            assert_eq!(*output, *vector);
+        }
     }
 }
 
 pub use vector::*;
+
+pub use random::*;
+mod random {
+    use super::{Vector2, Vector4};
+
+    /// A small seeded random number generator for building scenes.
+    ///
+    /// The same seed gives the same sequence on every platform and run, so scenes built
+    /// with it can be compared against reference images. Not suitable for anything
+    /// security related
+    #[derive(Debug, Clone)]
+    pub struct SceneRng {
+        state: u64,
+    }
+
+    impl SceneRng {
+        pub fn new(seed: u64) -> Self {
+            Self { state: seed }
+        }
+
+        // SplitMix64
+        pub fn next_u64(&mut self) -> u64 {
+            self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        /// Uniform in `[0, 1)`
+        pub fn next_f32(&mut self) -> f32 {
+            // The top 24 bits fill the mantissa exactly
+            (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        /// Uniform in `[min, max)`
+        pub fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next_f32()
+        }
+
+        /// Uniform inside the region starting at `min` with the given `size`, in the form
+        /// [crate::rendering::Uniform::visible_region] returns
+        pub fn random_position(
+            &mut self,
+            (min, size): (Vector2<f32>, Vector2<f32>),
+        ) -> Vector2<f32> {
+            min + Vector2::new([self.next_f32(), self.next_f32()]) * size
+        }
+
+        /// Opaque color with each channel uniform in `[0, 1)`
+        pub fn random_color(&mut self) -> Vector4<f32> {
+            Vector4::new([self.next_f32(), self.next_f32(), self.next_f32(), 1.])
+        }
+
+        /// Uniform over the area of a circle of `radius` centered on the origin
+        pub fn random_in_circle(&mut self, radius: f32) -> Vector2<f32> {
+            // The square root keeps points from bunching up at the center
+            let distance = radius * self.next_f32().sqrt();
+            Vector2::rotation(self.range(0., std::f32::consts::TAU)) * distance
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn same_seed_same_scene() {
+            let mut a = SceneRng::new(7);
+            let mut b = SceneRng::new(7);
+            for _ in 0..100 {
+                assert_eq!(a.next_u64(), b.next_u64());
+            }
+            assert_ne!(SceneRng::new(8).next_u64(), SceneRng::new(7).next_u64());
+        }
+
+        #[test]
+        fn values_stay_in_bounds() {
+            let mut rng = SceneRng::new(0);
+            let bounds = (Vector2::new([10., -5.]), Vector2::new([20., 10.]));
+            for _ in 0..1000 {
+                let position = rng.random_position(bounds);
+                assert!((10. ..30.).contains(&position[0]));
+                assert!((-5. ..5.).contains(&position[1]));
+                assert!(rng.random_in_circle(3.).mag() <= 3.);
+                let color = rng.random_color();
+                assert!(color[..3].iter().all(|c| (0. ..1.).contains(c)));
+                assert_eq!(color[3], 1.);
+            }
+        }
+    }
+}
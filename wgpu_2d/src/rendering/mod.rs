@@ -1,4 +1,547 @@
 const COMMON_INCLUDE: &str = include_str!("../shaders/common.wgsl");
+const RENDERER_CONSTANTS_INCLUDE: &str = include_str!("../shaders/renderer_constants.wgsl");
+
+mod color {
+    use crate::math::Vector4;
+
+    /// A linear RGBA color with every channel in `[0, 1]`.
+    ///
+    /// Converts into the `Vector4<f32>` used by the `color` fields of the primitives. Those
+    /// expect premultiplied alpha, see [Color::premultiplied]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Color {
+        rgba: [f32; 4],
+    }
+
+    /// Errors that can occur while parsing a hex color
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ColorParseError {
+        /// Expected 6 or 8 hex digits
+        InvalidLength(usize),
+        InvalidDigit(char),
+    }
+
+    impl std::fmt::Display for ColorParseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::InvalidLength(len) => {
+                    write!(f, "Expected 6 or 8 hex digits but found {len}")
+                }
+                Self::InvalidDigit(digit) => write!(f, "Invalid hex digit {digit:?}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ColorParseError {}
+
+    impl Color {
+        pub const WHITE: Self = Self::rgba(1., 1., 1., 1.);
+        pub const BLACK: Self = Self::rgba(0., 0., 0., 1.);
+        pub const TRANSPARENT: Self = Self::rgba(0., 0., 0., 0.);
+
+        /// Channels are clamped to `[0, 1]`
+        pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+            Self {
+                rgba: [
+                    r.clamp(0., 1.),
+                    g.clamp(0., 1.),
+                    b.clamp(0., 1.),
+                    a.clamp(0., 1.),
+                ],
+            }
+        }
+
+        pub const fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+            Self::rgba(
+                r as f32 / 255.,
+                g as f32 / 255.,
+                b as f32 / 255.,
+                a as f32 / 255.,
+            )
+        }
+
+        /// Parses `RRGGBB` or `RRGGBBAA`, with or without a leading `#`
+        pub fn from_hex(hex: &str) -> Result<Self, ColorParseError> {
+            let hex = hex.strip_prefix('#').unwrap_or(hex);
+            let digits = hex
+                .chars()
+                .map(|c| c.to_digit(16).ok_or(ColorParseError::InvalidDigit(c)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let channel = |i: usize| (digits[i * 2] * 16 + digits[i * 2 + 1]) as u8;
+            match digits.len() {
+                6 => Ok(Self::rgba8(channel(0), channel(1), channel(2), 255)),
+                8 => Ok(Self::rgba8(channel(0), channel(1), channel(2), channel(3))),
+                len => Err(ColorParseError::InvalidLength(len)),
+            }
+        }
+
+        /// Opaque color from hue in degrees and saturation and value in `[0, 1]`
+        pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+            let saturation = saturation.clamp(0., 1.);
+            let value = value.clamp(0., 1.);
+            let hue = hue.rem_euclid(360.) / 60.;
+            let chroma = value * saturation;
+            let x = chroma * (1. - (hue % 2. - 1.).abs());
+            let (r, g, b) = match hue as u32 {
+                0 => (chroma, x, 0.),
+                1 => (x, chroma, 0.),
+                2 => (0., chroma, x),
+                3 => (0., x, chroma),
+                4 => (x, 0., chroma),
+                _ => (chroma, 0., x),
+            };
+            let m = value - chroma;
+            Self::rgba(r + m, g + m, b + m, 1.)
+        }
+
+        pub fn with_alpha(self, alpha: f32) -> Self {
+            let [r, g, b, _] = self.rgba;
+            Self::rgba(r, g, b, alpha)
+        }
+
+        /// Multiplies the color channels by alpha, as expected by the blending of the renderers
+        pub fn premultiplied(self) -> Self {
+            let [r, g, b, a] = self.rgba;
+            Self::rgba(r * a, g * a, b * a, a)
+        }
+
+        pub fn to_array(self) -> [f32; 4] {
+            self.rgba
+        }
+    }
+
+    impl From<Color> for Vector4<f32> {
+        fn from(color: Color) -> Self {
+            Vector4::new(color.rgba)
+        }
+    }
+
+    impl From<Color> for [f32; 4] {
+        fn from(color: Color) -> Self {
+            color.rgba
+        }
+    }
+
+    /// Packs a color into one byte per channel, red in the lowest byte. This is the
+    /// layout of `Unorm8x4` vertex attributes and `unpack4x8unorm` in WGSL, so shaders
+    /// get the color back as a `vec4<f32>`.
+    ///
+    /// Channels are clamped to `[0, 1]` and rounded to the nearest 1/255. That is coarse
+    /// for dark linear colors, so keep full precision where banding shows
+    pub fn pack_color(color: Vector4<f32>) -> u32 {
+        let bytes = color.map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8);
+        u32::from_le_bytes(bytes)
+    }
+
+    /// Inverse of [pack_color]
+    pub fn unpack_color(packed: u32) -> Vector4<f32> {
+        Vector4::new(packed.to_le_bytes().map(|channel| channel as f32 / 255.))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn from_hex() {
+            assert_eq!(
+                Color::from_hex("#ff8800"),
+                Ok(Color::rgba8(255, 136, 0, 255))
+            );
+            assert_eq!(
+                Color::from_hex("FF880080"),
+                Ok(Color::rgba8(255, 136, 0, 128))
+            );
+            assert_eq!(
+                Color::from_hex("#ff88"),
+                Err(ColorParseError::InvalidLength(4))
+            );
+            assert_eq!(
+                Color::from_hex("#ff88zz"),
+                Err(ColorParseError::InvalidDigit('z'))
+            );
+        }
+
+        #[test]
+        fn hsv() {
+            assert_eq!(Color::hsv(0., 1., 1.), Color::rgba(1., 0., 0., 1.));
+            assert_eq!(Color::hsv(120., 1., 1.), Color::rgba(0., 1., 0., 1.));
+            assert_eq!(Color::hsv(240., 1., 1.), Color::rgba(0., 0., 1., 1.));
+            assert_eq!(Color::hsv(-120., 1., 1.), Color::rgba(0., 0., 1., 1.));
+            assert_eq!(Color::hsv(0., 0., 1.), Color::WHITE);
+        }
+
+        #[test]
+        fn packed_colors() {
+            let packed = pack_color(Vector4::new([1., 0.5, 0., 2.]));
+            assert_eq!(packed.to_le_bytes(), [255, 128, 0, 255]);
+            assert_eq!(*unpack_color(packed), [1., 128. / 255., 0., 1.]);
+            assert_eq!(pack_color(Color::from_hex("#ff880080").unwrap().into()), 0x8000_88ff);
+        }
+    }
+}
+
+mod debug_overlay {
+    use wgpu::*;
+
+    use crate::math::{Vector2, Vector4};
+    use crate::shader_manager::ShaderManager;
+    use crate::wgpu_context::{BufferData, WGPUContext};
+
+    use super::{
+        CenterRect, Primitive, Render, RenderStats, RendererConstants, Uniform, VertexBuffers,
+        MAX_LAYER,
+    };
+
+    /// Grids with more lines than this along an axis are not drawn
+    const MAX_GRID_LINES: usize = 1024;
+
+    /// Draws a world space grid and rectangle outlines on top of a scene, for debugging layout.
+    ///
+    /// Lines are drawn as thin rectangles with a constant width in pixels. Since the grid
+    /// covers the region of world space described by the [Uniform], call [Self::upload] after
+    /// changing the uniform or the overlay, and render this after the rest of the scene
+    pub struct DebugOverlay {
+        pub grid_color: Vector4<f32>,
+        /// Width of every line in pixels
+        pub line_width: f32,
+        grid_spacing: Option<f32>,
+        aabbs: Vec<(Vector2<f32>, Vector2<f32>, Vector4<f32>)>,
+        lines: Vec<CenterRect>,
+        buffers: Option<<Vec<CenterRect> as BufferData>::Buffers>,
+        capacity: usize,
+        instance_count: u32,
+        constants: RendererConstants,
+    }
+
+    impl DebugOverlay {
+        pub fn new(
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            CenterRect::register(uniform_bind_group_layout, context, shader_manager);
+
+            Self {
+                grid_color: Vector4::new([0.3, 0.3, 0.3, 0.3]),
+                line_width: 1.,
+                grid_spacing: None,
+                aabbs: Vec::new(),
+                lines: Vec::new(),
+                buffers: None,
+                capacity: 0,
+                instance_count: 0,
+                constants: RendererConstants::new(context),
+            }
+        }
+
+        /// Draws grid lines every `spacing` world units
+        pub fn grid(&mut self, spacing: f32) {
+            self.grid_spacing = Some(spacing);
+        }
+
+        pub fn hide_grid(&mut self) {
+            self.grid_spacing = None;
+        }
+
+        /// Outlines the axis aligned box from `min` to `max` in world space
+        pub fn push_aabb(&mut self, min: Vector2<f32>, max: Vector2<f32>, color: Vector4<f32>) {
+            self.aabbs.push((min, max, color));
+        }
+
+        pub fn clear_aabbs(&mut self) {
+            self.aabbs.clear();
+        }
+
+        /// Regenerates the lines for the region of world space described by `uniform` and
+        /// uploads them, growing the instance buffers if needed
+        pub fn upload(&mut self, uniform: &Uniform, context: &WGPUContext) {
+            // Visible region of world space and the world size of a physical pixel
+            let (visible_min, visible_size) = uniform.visible_region();
+            let width = self.line_width * visible_size[0] / context.config().width as f32;
+
+            self.lines.clear();
+            if let Some(spacing) = self.grid_spacing {
+                self.lines.extend(grid_lines(
+                    spacing,
+                    visible_min,
+                    visible_size,
+                    width,
+                    self.grid_color,
+                ));
+            }
+            for &(min, max, color) in &self.aabbs {
+                self.lines.extend(aabb_lines(min, max, width, color));
+            }
+
+            self.instance_count = self.lines.len() as u32;
+            if self.lines.len() > self.capacity {
+                self.capacity = self.lines.len();
+                self.buffers = Some(self.lines.create_buffers(context));
+            }
+            if let Some(buffers) = &mut self.buffers {
+                self.lines.fill_buffers(buffers, context);
+            }
+        }
+    }
+
+    impl Render for DebugOverlay {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            if self.buffers.is_none() || self.instance_count == 0 {
+                return;
+            }
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline(CenterRect::LABEL, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(CenterRect::LABEL)
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            let Some(buffers) = &self.buffers else {
+                return;
+            };
+            self.constants.bind(render_pass);
+            buffers.set_vertex_buffers(render_pass);
+            render_pass.draw(0..CenterRect::VERTEX_COUNT, 0..self.instance_count);
+        }
+
+        fn stats(&self) -> RenderStats {
+            match self.buffers {
+                Some(_) if self.instance_count > 0 => {
+                    RenderStats::draw(CenterRect::VERTEX_COUNT, self.instance_count)
+                }
+                _ => RenderStats::default(),
+            }
+        }
+    }
+
+    fn line(center: [f32; 2], size: [f32; 2], color: Vector4<f32>) -> CenterRect {
+        CenterRect {
+            color,
+            center: Vector2::new(center),
+            size: Vector2::new(size),
+            rotation: 0.,
+            layer: MAX_LAYER,
+        }
+    }
+
+    /// Lines at every multiple of `spacing` that lies within the region
+    fn grid_lines(
+        spacing: f32,
+        origin: Vector2<f32>,
+        size: Vector2<f32>,
+        width: f32,
+        color: Vector4<f32>,
+    ) -> Vec<CenterRect> {
+        if !spacing.is_finite() || spacing <= 0. {
+            log::warn!("Invalid grid spacing {spacing}, skipping grid");
+            return Vec::new();
+        }
+        let first = [
+            (origin[0] / spacing).ceil() as i64,
+            (origin[1] / spacing).ceil() as i64,
+        ];
+        let last = [
+            ((origin[0] + size[0]) / spacing).floor() as i64,
+            ((origin[1] + size[1]) / spacing).floor() as i64,
+        ];
+        if last[0] - first[0] >= MAX_GRID_LINES as i64
+            || last[1] - first[1] >= MAX_GRID_LINES as i64
+        {
+            log::warn!("Grid spacing {spacing} is too small for the visible region, skipping grid");
+            return Vec::new();
+        }
+
+        let middle = origin + size / 2.;
+        let vertical = (first[0]..=last[0])
+            .map(|i| line([i as f32 * spacing, middle[1]], [width, size[1]], color));
+        let horizontal = (first[1]..=last[1])
+            .map(|i| line([middle[0], i as f32 * spacing], [size[0], width], color));
+        vertical.chain(horizontal).collect()
+    }
+
+    fn aabb_lines(
+        min: Vector2<f32>,
+        max: Vector2<f32>,
+        width: f32,
+        color: Vector4<f32>,
+    ) -> [CenterRect; 4] {
+        let middle = (min + max) / 2.;
+        let size = max - min;
+        [
+            line([middle[0], min[1]], [size[0] + width, width], color),
+            line([middle[0], max[1]], [size[0] + width, width], color),
+            line([min[0], middle[1]], [width, size[1] + width], color),
+            line([max[0], middle[1]], [width, size[1] + width], color),
+        ]
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn grid_covers_region() {
+            let color = Vector4::new([1., 1., 1., 1.]);
+            let lines = grid_lines(
+                25.,
+                Vector2::new([0., 0.]),
+                Vector2::new([100., 50.]),
+                1.,
+                color,
+            );
+            // x = 0, 25, 50, 75, 100 and y = 0, 25, 50
+            assert_eq!(lines.len(), 8);
+
+            // Lines stay at multiples of the spacing when the view pans
+            let lines = grid_lines(
+                25.,
+                Vector2::new([10., 10.]),
+                Vector2::new([100., 50.]),
+                1.,
+                color,
+            );
+            assert_eq!(lines[0].center[0], 25.);
+            assert_eq!(lines.len(), 6);
+
+            assert!(grid_lines(
+                0.,
+                Vector2::new([0., 0.]),
+                Vector2::new([1., 1.]),
+                1.,
+                color
+            )
+            .is_empty());
+        }
+
+        #[test]
+        fn aabb_outline() {
+            let lines = aabb_lines(
+                Vector2::new([0., 0.]),
+                Vector2::new([10., 20.]),
+                2.,
+                Vector4::new([1., 1., 1., 1.]),
+            );
+            assert_eq!(*lines[0].center, [5., 0.]);
+            assert_eq!(*lines[0].size, [12., 2.]);
+            assert_eq!(*lines[3].center, [10., 10.]);
+            assert_eq!(*lines[3].size, [2., 22.]);
+        }
+    }
+}
+
+mod draw_constants {
+    use bytemuck::Pod;
+    use wgpu::*;
+
+    use crate::wgpu_context::{WGPUBuffer, WGPUContext};
+
+    /// Sets `data` as the push constants for `stages`, starting at offset 0.
+    ///
+    /// The pipeline layout must declare a matching range, which needs
+    /// [WGPUContext::supports_push_constants]
+    pub fn set_push_constants<T: Pod>(render_pass: &mut RenderPass, stages: ShaderStages, data: &T) {
+        render_pass.set_push_constants(stages, 0, bytemuck::bytes_of(data));
+    }
+
+    /// Small per-draw data, like a tint, that avoids a bind group per object where possible.
+    ///
+    /// Sent as push constants when the device supports them. Otherwise it falls back to a
+    /// uniform buffer at binding 0 of its own bind group. Shaders declare the data as
+    /// `var<push_constant>` in the first case and as a uniform in the second, so use
+    /// [Self::uses_push_constants] to pick the shader and pipeline layout.
+    /// The size of `T` must be a multiple of 4
+    pub struct DrawConstants<T: Pod> {
+        data: T,
+        stages: ShaderStages,
+        fallback: Option<(WGPUBuffer, BindGroup)>,
+    }
+
+    impl<T: Pod> DrawConstants<T> {
+        pub fn new(data: T, stages: ShaderStages, context: &WGPUContext) -> Self {
+            let fallback = (!context.supports_push_constants()).then(|| {
+                let mut buffer = WGPUBuffer::new_uniform(size_of::<T>() as u64, context);
+                buffer.write_data(bytemuck::bytes_of(&data), context);
+                let bind_group = context.device().create_bind_group(&BindGroupDescriptor {
+                    label: Some("Draw constants bind group"),
+                    layout: &Self::bind_group_layout(stages, context),
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+                (buffer, bind_group)
+            });
+            Self {
+                data,
+                stages,
+                fallback,
+            }
+        }
+
+        /// Layout of the fallback bind group, for the pipeline layout when push constants
+        /// are not supported
+        pub fn bind_group_layout(stages: ShaderStages, context: &WGPUContext) -> BindGroupLayout {
+            context
+                .device()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("Draw constants bind group layout"),
+                    entries: &[BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: stages,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                })
+        }
+
+        /// Range to declare in the pipeline layout when push constants are supported
+        pub fn push_constant_range(&self) -> PushConstantRange {
+            PushConstantRange {
+                stages: self.stages,
+                range: 0..size_of::<T>() as u32,
+            }
+        }
+
+        pub fn uses_push_constants(&self) -> bool {
+            self.fallback.is_none()
+        }
+
+        pub fn data(&self) -> &T {
+            &self.data
+        }
+
+        /// The fallback buffer is written through the queue, so every draw submitted
+        /// together sees the last value set
+        pub fn set(&mut self, data: T, context: &WGPUContext) {
+            self.data = data;
+            if let Some((buffer, _)) = &mut self.fallback {
+                buffer.write_data(bytemuck::bytes_of(&self.data), context);
+            }
+        }
+
+        /// Makes the data available to the following draws. `group` is the bind group
+        /// index used by the fallback and is ignored for push constants
+        pub fn bind(&self, render_pass: &mut RenderPass, group: u32) {
+            match &self.fallback {
+                None => set_push_constants(render_pass, self.stages, &self.data),
+                Some((_, bind_group)) => render_pass.set_bind_group(group, bind_group, &[]),
+            }
+        }
+    }
+}
 
 mod point {
     use wgpu::*;
@@ -10,12 +553,13 @@ mod point {
 
     use derive::VertexBufferData;
 
-    use super::Render;
+    use super::{Render, RenderStats};
 
     use bytemuck::{Pod, Zeroable};
 
     #[repr(C)]
     #[derive(Zeroable, Pod, Clone, Copy, Debug, VertexBufferData)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Point {
         pub color: Vector4<f32>,
         pub position: Vector2<f32>,
@@ -25,15 +569,17 @@ mod point {
 
     pub struct PointRenderer {
         points: BufferAndData<Vec<Point>>,
+        dirty: bool,
     }
 
     impl PointRenderer {
-        pub fn new(
-            points: Vec<Point>,
+        /// Registers the shader and render pipeline used by every [PointRenderer].
+        /// Registration is idempotent, see [super::Renderer2D::preregister_all]
+        pub fn register(
             uniform_bind_group_layout: &BindGroupLayout,
             context: &WGPUContext,
             shader_manager: &ShaderManager,
-        ) -> Self {
+        ) {
             let pipeline_layout =
                 context
                     .device()
@@ -85,22 +631,47 @@ mod point {
                 multisample: Default::default(),
                 multiview: None,
                 cache: None,
+                push_constant_ranges: Box::new([]),
+                constants: Default::default(),
             };
             shader_manager.register_constant_source("points.wgsl", POINTS_SHADER.into());
             shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
             shader_manager.register_render_pipeline("Point Renderer Pipeline", descriptor_template);
+        }
+
+        pub fn new(
+            points: Vec<Point>,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            Self::register(uniform_bind_group_layout, context, shader_manager);
 
             let points = BufferAndData::new(points, context);
 
-            Self { points }
+            Self {
+                points,
+                dirty: false,
+            }
         }
 
+        /// Marks the points as changed so the next [Self::sync] uploads them
         pub fn points_mut(&mut self) -> &mut Vec<Point> {
+            self.dirty = true;
             &mut self.points.data
         }
 
+        /// Uploads the points regardless of whether they changed
         pub fn update_points_buffer(&mut self, context: &WGPUContext) {
             self.points.update_buffer(context);
+            self.dirty = false;
+        }
+
+        /// Uploads the points only if they were accessed mutably since the last upload
+        pub fn sync(&mut self, context: &WGPUContext) {
+            if self.dirty {
+                self.update_points_buffer(context);
+            }
         }
     }
 
@@ -112,12 +683,24 @@ mod point {
             shader_manager: &ShaderManager,
         ) {
             render_pass.set_pipeline(
-                shader_manager.get_render_pipeline("Point Renderer Pipeline", context),
+                &shader_manager.get_render_pipeline("Point Renderer Pipeline", context),
             );
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some("Point Renderer Pipeline")
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
             render_pass.set_vertex_buffer(0, self.points.buffers.0.slice(..));
             render_pass.set_vertex_buffer(1, self.points.buffers.1.slice(..));
             render_pass.draw(0..(self.points.data.len()) as u32, 0..1);
         }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(self.points.data.len() as u32, 1)
+        }
     }
 
     pub fn create_circle_point_list(
@@ -146,7 +729,7 @@ mod triangle {
 
     use crate::shader_manager::*;
 
-    use super::Render;
+    use super::{Render, RenderStats};
 
     use super::point::Point;
     use crate::vertex_buffer_layout;
@@ -180,6 +763,10 @@ mod triangle {
                 context,
             );
         }
+        fn fits_buffers(&self, buffers: &Self::Buffers) -> bool {
+            buffers.0.size() >= (std::mem::size_of::<[f32; 4]>() * self.len() * 3) as u64
+                && buffers.1.size() >= (std::mem::size_of::<[f32; 2]>() * self.len() * 3) as u64
+        }
     }
 
 	const TRIANGLE_SHADER: &str = include_str!("../shaders/triangle.wgsl");
@@ -189,14 +776,13 @@ mod triangle {
     }
 
     impl TriangleListRenderer {
-        pub fn new(
-            data: Vec<Triangle>,
+        /// Registers the shader and render pipeline used by every [TriangleListRenderer].
+        /// Registration is idempotent, see [super::Renderer2D::preregister_all]
+        pub fn register(
             uniform_bind_group_layout: &BindGroupLayout,
             context: &WGPUContext,
             shader_manager: &ShaderManager,
-        ) -> Self {
-            let triangles = BufferAndData::new(data, context);
-
+        ) {
             let pipeline_layout =
                 context
                     .device()
@@ -245,10 +831,23 @@ mod triangle {
                 }),
                 multiview: None,
                 cache: None,
+                push_constant_ranges: Box::new([]),
+                constants: Default::default(),
             };
             shader_manager.register_constant_source("triangle.wgsl", TRIANGLE_SHADER.into());
             shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
             shader_manager.register_render_pipeline("triangles", render_pipeline_template);
+        }
+
+        pub fn new(
+            data: Vec<Triangle>,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            let triangles = BufferAndData::new(data, context);
+
+            Self::register(uniform_bind_group_layout, context, shader_manager);
 
             Self { triangles }
         }
@@ -261,17 +860,27 @@ mod triangle {
             context: &WGPUContext,
             shader_manager: &ShaderManager,
         ) {
-            render_pass.set_pipeline(shader_manager.get_render_pipeline("triangles", context));
+            render_pass.set_pipeline(&shader_manager.get_render_pipeline("triangles", context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some("triangles")
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
             render_pass.set_vertex_buffer(0, self.triangles.buffers.0.slice(..));
             render_pass.set_vertex_buffer(1, self.triangles.buffers.1.slice(..));
             render_pass.draw(0..(self.triangles.data.len() * 3) as u32, 0..1);
         }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(self.triangles.data.len() as u32 * 3, 1)
+        }
     }
 }
 
-mod rect {
-    use derive::*;
-
+mod polyline {
     use wgpu::*;
 
     use crate::math::{Vector2, Vector4};
@@ -279,53 +888,118 @@ mod rect {
     use crate::vertex_buffer_layout;
     use crate::wgpu_context::*;
 
-    use super::Render;
+    use super::point::Point;
+    use super::{Render, RenderStats};
 
-    use bytemuck::{Pod, Zeroable};
-    #[derive(Clone, Copy, Pod, Zeroable, UniformBufferData, VertexBufferData)]
-    #[repr(C)]
-    pub struct CenterRect {
+    /// A continuous path of connected line segments drawn with a constant width
+    #[derive(Clone, Debug)]
+    pub struct Polyline {
+        pub points: Vec<Vector2<f32>>,
+        pub width: f32,
         pub color: Vector4<f32>,
-        pub center: Vector2<f32>,
-        pub size: Vector2<f32>,
-        pub rotation: f32,
     }
 
-	const RECT_SHADER: &str = include_str!("../shaders/rect.wgsl");
+    /// Miter joins longer than this multiple of the half width are clamped so that
+    /// sharp turns don't produce long spikes
+    const MITER_LIMIT: f32 = 4.;
+
+    impl Polyline {
+        /// Generates the vertices of a triangle strip ribbon along the path with miter
+        /// joins at interior points.
+        ///
+        /// Consecutive coincident points are skipped. Paths with fewer than two distinct
+        /// points produce no vertices
+        pub fn ribbon_vertices(&self) -> Vec<Point> {
+            let mut points: Vec<Vector2<f32>> = Vec::with_capacity(self.points.len());
+            for point in &self.points {
+                match points.last() {
+                    Some(last) if (*point - *last).mag() <= f32::EPSILON => (),
+                    _ => points.push(*point),
+                }
+            }
+            if points.len() < 2 {
+                return Vec::new();
+            }
+
+            let half_width = self.width / 2.;
+            let normal = |from: &Vector2<f32>, to: &Vector2<f32>| {
+                let direction = (to - from).normalized();
+                Vector2::new([-direction[1], direction[0]])
+            };
 
-    pub struct RectangleRenderer {
-        rectangles: BufferAndData<Vec<CenterRect>>,
+            let mut vertices = Vec::with_capacity(points.len() * 2);
+            for i in 0..points.len() {
+                let offset = if i == 0 {
+                    normal(&points[0], &points[1]) * half_width
+                } else if i == points.len() - 1 {
+                    normal(&points[i - 1], &points[i]) * half_width
+                } else {
+                    let incoming = normal(&points[i - 1], &points[i]);
+                    let outgoing = normal(&points[i], &points[i + 1]);
+                    let miter = incoming + outgoing;
+                    // The path doubles back on itself, so there is no meaningful miter
+                    if miter.mag() <= f32::EPSILON {
+                        incoming * half_width
+                    } else {
+                        let miter = miter.normalized();
+                        let length =
+                            (half_width / miter.dot(&incoming)).min(half_width * MITER_LIMIT);
+                        miter * length
+                    }
+                };
+                vertices.push(Point {
+                    color: self.color,
+                    position: points[i] + offset,
+                });
+                vertices.push(Point {
+                    color: self.color,
+                    position: points[i] - offset,
+                });
+            }
+            vertices
+        }
     }
 
-    impl RectangleRenderer {
-        pub fn new(
-            data: Vec<CenterRect>,
+	const POLYLINE_SHADER: &str = include_str!("../shaders/polyline.wgsl");
+
+    /// Draws a [Polyline] as a single triangle strip.
+    ///
+    /// Unlike drawing one instance per segment, the whole path is uploaded as one
+    /// vertex buffer and drawn with one draw call
+    pub struct PolylineRenderer {
+        polyline: Polyline,
+        colors: WGPUBuffer,
+        positions: WGPUBuffer,
+        vertex_count: u32,
+        dirty: bool,
+    }
+
+    impl PolylineRenderer {
+        /// Registers the shader and render pipeline used by every [PolylineRenderer].
+        /// Registration is idempotent, see [super::Renderer2D::preregister_all]
+        pub fn register(
             uniform_bind_group_layout: &BindGroupLayout,
             context: &WGPUContext,
             shader_manager: &ShaderManager,
-        ) -> Self {
-            let rectangles = BufferAndData::new(data, context);
-
+        ) {
             let pipeline_layout =
                 context
                     .device()
                     .create_pipeline_layout(&PipelineLayoutDescriptor {
-                        label: None,
-                        bind_group_layouts: &[&uniform_bind_group_layout],
+                        label: Some("Polyline pipeline layout"),
+                        bind_group_layouts: &[uniform_bind_group_layout],
                         push_constant_ranges: &[],
                     });
 
             let render_pipeline_template = RenderPipelineDescriptorTemplate {
-                label: Some("Rectangle Pipeline"),
+                label: Some("Polyline Pipeline"),
                 layout: Some(pipeline_layout),
                 vertex: VertexStateTemplate {
-                    module_path: "rect.wgsl",
+                    module_path: "polyline.wgsl",
                     entry_point: None,
                     buffers: &vertex_buffer_layout!(
-                        ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
-                        ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
-                        ([f32; 2], Instance, &vertex_attr_array![2 => Float32x2]),
-                        (f32, Instance, &vertex_attr_array![3 => Float32]),
+                        ([f32; 4], Vertex, &vertex_attr_array![0 => Float32x4]),
+                        ([f32; 2], Vertex, &vertex_attr_array![1 => Float32x2]),
                     ),
                 },
                 primitive: PrimitiveState {
@@ -335,7 +1009,7 @@ mod rect {
                 depth_stencil: None,
                 multisample: Default::default(),
                 fragment: Some(FragmentStateTemplate {
-                    module_path: "rect.wgsl",
+                    module_path: "polyline.wgsl",
                     entry_point: None,
                     targets: Box::new([Some(ColorTargetState {
                         format: context.config().format,
@@ -356,751 +1030,4718 @@ mod rect {
                 }),
                 multiview: None,
                 cache: None,
+                push_constant_ranges: Box::new([]),
+                constants: Default::default(),
             };
-            shader_manager.register_constant_source("rect.wgsl", RECT_SHADER.into());
+            shader_manager.register_constant_source("polyline.wgsl", POLYLINE_SHADER.into());
             shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
-            shader_manager.register_render_pipeline("rects", render_pipeline_template);
-
-            Self { rectangles }
+            shader_manager.register_render_pipeline("polyline", render_pipeline_template);
         }
 
-        pub fn rects_mut(&mut self) -> &mut Vec<CenterRect> {
-            &mut self.rectangles.data
-        }
+        pub fn new(
+            polyline: Polyline,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            Self::register(uniform_bind_group_layout, context, shader_manager);
 
-        pub fn update_rects(&mut self, context: &WGPUContext) {
-            self.rectangles.update_buffer(context);
-        }
+            // Buffers are never created empty so that they can always be written to
+            let mut output = Self {
+                polyline,
+                colors: WGPUBuffer::new_vertex(std::mem::size_of::<[f32; 4]>() as u64 * 2, context),
+                positions: WGPUBuffer::new_vertex(
+                    std::mem::size_of::<[f32; 2]>() as u64 * 2,
+                    context,
+                ),
+                vertex_count: 0,
+                dirty: false,
+            };
+            output.update_polyline(context);
+            output
+        }
+
+        /// Marks the polyline as changed so the next [Self::sync] uploads it
+        pub fn polyline_mut(&mut self) -> &mut Polyline {
+            self.dirty = true;
+            &mut self.polyline
+        }
+
+        /// Regenerates and uploads the ribbon only if the polyline was accessed mutably
+        /// since the last upload
+        pub fn sync(&mut self, context: &WGPUContext) {
+            if self.dirty {
+                self.update_polyline(context);
+            }
+        }
+
+        /// Regenerates the ribbon from the current [Polyline] and uploads it,
+        /// growing the buffers if necessary
+        pub fn update_polyline(&mut self, context: &WGPUContext) {
+            self.dirty = false;
+            let vertices = self.polyline.ribbon_vertices();
+            self.vertex_count = vertices.len() as u32;
+            if vertices.is_empty() {
+                return;
+            }
+            self.colors.resize(
+                (std::mem::size_of::<[f32; 4]>() * vertices.len()) as u64,
+                context,
+            );
+            self.positions.resize(
+                (std::mem::size_of::<[f32; 2]>() * vertices.len()) as u64,
+                context,
+            );
+            self.colors
+                .write_iter(vertices.iter().map(|x| &x.color), context);
+            self.positions
+                .write_iter(vertices.iter().map(|x| &x.position), context);
+        }
     }
 
-    impl Render for RectangleRenderer {
+    impl Render for PolylineRenderer {
         fn render(
             &self,
             render_pass: &mut RenderPass,
             context: &WGPUContext,
             shader_manager: &ShaderManager,
         ) {
-            render_pass.set_pipeline(shader_manager.get_render_pipeline("rects", context));
-            render_pass.set_vertex_buffer(0, self.rectangles.buffers.0.slice(..));
-            render_pass.set_vertex_buffer(1, self.rectangles.buffers.1.slice(..));
-            render_pass.set_vertex_buffer(2, self.rectangles.buffers.2.slice(..));
-            render_pass.set_vertex_buffer(3, self.rectangles.buffers.3.slice(..));
-            render_pass.draw(0..4 as u32, 0..self.rectangles.data.len() as u32);
+            if self.vertex_count == 0 {
+                return;
+            }
+            render_pass.set_pipeline(&shader_manager.get_render_pipeline("polyline", context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some("polyline")
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            if self.vertex_count == 0 {
+                return;
+            }
+            render_pass.set_vertex_buffer(0, self.colors.slice(..));
+            render_pass.set_vertex_buffer(1, self.positions.slice(..));
+            render_pass.draw(0..self.vertex_count, 0..1);
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(self.vertex_count, 1)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn polyline(points: &[[f32; 2]]) -> Polyline {
+            Polyline {
+                points: points.iter().map(|x| Vector2::new(*x)).collect(),
+                width: 2.,
+                color: Vector4::new([1., 1., 1., 1.]),
+            }
+        }
+
+        #[test]
+        fn degenerate_polylines() {
+            assert!(polyline(&[]).ribbon_vertices().is_empty());
+            assert!(polyline(&[[1., 1.]]).ribbon_vertices().is_empty());
+            assert!(polyline(&[[1., 1.], [1., 1.]]).ribbon_vertices().is_empty());
+            // Coincident points are skipped
+            assert_eq!(
+                polyline(&[[0., 0.], [0., 0.], [1., 0.]]).ribbon_vertices().len(),
+                4
+            );
+        }
+
+        #[test]
+        fn miter_join() {
+            let vertices = polyline(&[[0., 0.], [10., 0.], [10., 10.]]).ribbon_vertices();
+            assert_eq!(vertices.len(), 6);
+            // The corner is offset along the miter by sqrt(2) * half width
+            let corner = vertices[2].position - vertices[3].position;
+            assert!((corner.mag() - 2. * 2f32.sqrt()).abs() < 1e-5);
         }
     }
 }
 
-mod circle {
-    use crate::shader_manager::*;
-    use crate::vertex_buffer_layout;
-    use crate::wgpu_context::{BufferAndData, WGPUContext};
-	use crate::math::{Vector2, Vector4};
+mod picking {
+    use std::collections::HashMap;
 
-    use derive::VertexBufferData;
+    use crate::math::Vector2;
+
+    use crate::wgpu_context::WGPUContext;
     use wgpu::*;
 
+    use super::{CenterRect, Circle, Primitive, Ring};
 
-    use super::Render;
+    /// Whether `point` is inside the circle, edge included
+    pub fn hit_test_circle(circle: &Circle, point: Vector2<f32>) -> bool {
+        (point - circle.position).mag() <= circle.radius
+    }
 
-    use bytemuck::{Pod, Zeroable};
+    /// Whether `point` is inside the rect, taking its rotation into account
+    pub fn hit_test_rect(rect: &CenterRect, point: Vector2<f32>) -> bool {
+        // Rotate into the rect's own frame, undoing the rotation the shader applies
+        let local = (point - rect.center).rotate(-rect.rotation);
+        local[0].abs() <= rect.size[0] / 2. && local[1].abs() <= rect.size[1] / 2.
+    }
 
-    #[derive(Pod, Zeroable, Clone, Copy, VertexBufferData)]
-    #[repr(C)]
-    pub struct Circle {
-        pub color: Vector4<f32>,
-        pub position: Vector2<f32>,
-        pub radius: f32,
+    /// Shapes a [Picker] can test points against
+    pub trait Pickable: Primitive {
+        /// Corners of an axis aligned box containing the whole shape, smallest first
+        fn bounds(&self) -> (Vector2<f32>, Vector2<f32>);
+        fn contains(&self, point: Vector2<f32>) -> bool;
     }
 
-	const CIRCLE_SHADER: &str = include_str!("../shaders/circle.wgsl");
+    impl Pickable for Circle {
+        fn bounds(&self) -> (Vector2<f32>, Vector2<f32>) {
+            let extent = Vector2::new([self.radius, self.radius]);
+            (self.position - extent, self.position + extent)
+        }
 
-    pub struct CircleRenderer {
-        circles: BufferAndData<Vec<Circle>>,
+        fn contains(&self, point: Vector2<f32>) -> bool {
+            hit_test_circle(self, point)
+        }
     }
 
-    impl CircleRenderer {
-        pub fn new(
-            data: Vec<Circle>,
-            uniform_bind_group_layout: &BindGroupLayout,
-            context: &WGPUContext,
-            shader_manager: &ShaderManager,
-        ) -> Self {
-            let circles = BufferAndData::new(data, context);
+    impl Pickable for Ring {
+        fn bounds(&self) -> (Vector2<f32>, Vector2<f32>) {
+            let extent = Vector2::new([self.outer_radius, self.outer_radius]);
+            (self.position - extent, self.position + extent)
+        }
 
-            let pipeline_layout =
-                context
-                    .device()
-                    .create_pipeline_layout(&PipelineLayoutDescriptor {
-                        label: None,
-                        bind_group_layouts: &[&uniform_bind_group_layout],
-                        push_constant_ranges: &[],
-                    });
+        fn contains(&self, point: Vector2<f32>) -> bool {
+            let distance = (point - self.position).mag();
+            (self.inner_radius..=self.outer_radius).contains(&distance)
+        }
+    }
 
-            let render_pipeline_template = RenderPipelineDescriptorTemplate {
-                label: Some("Circle Pipeline"),
-                layout: Some(pipeline_layout),
-                vertex: VertexStateTemplate {
-                    module_path: "circle.wgsl",
-                    entry_point: None,
-                    buffers: &vertex_buffer_layout!(
-                        ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
-                        ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
-                        (f32, Instance, &vertex_attr_array![2 => Float32]),
-                    ),
-                },
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleStrip,
-                    ..Default::default()
+    impl Pickable for CenterRect {
+        fn bounds(&self) -> (Vector2<f32>, Vector2<f32>) {
+            // Half extents of the rotated rect along each axis
+            let (sin, cos) = self.rotation.sin_cos();
+            let [width, height] = *(self.size / 2.);
+            let extent = Vector2::new([
+                width * cos.abs() + height * sin.abs(),
+                width * sin.abs() + height * cos.abs(),
+            ]);
+            (self.center - extent, self.center + extent)
+        }
+
+        fn contains(&self, point: Vector2<f32>) -> bool {
+            hit_test_rect(self, point)
+        }
+    }
+
+    /// Finds the shape under a point among many instances.
+    ///
+    /// Shapes are bucketed into a uniform grid by their bounds, so a pick only tests the
+    /// shapes in one cell. The grid is built once in [Picker::new] and has to be rebuilt
+    /// when the shapes move. Pick with world coordinates, e.g. from
+    /// [super::Renderer2D::screen_to_world]
+    ///
+    /// Shapes covering more than [Self::MAX_CELLS] cells, or with non-finite bounds, are
+    /// kept out of the grid and tested on every pick
+    pub struct Picker<'a, P: Pickable> {
+        shapes: &'a [P],
+        cell_size: f32,
+        cells: HashMap<[i32; 2], Vec<usize>>,
+        large: Vec<usize>,
+    }
+
+    impl<'a, P: Pickable> Picker<'a, P> {
+        /// `cell_size` should be around the size of a typical shape. Much smaller cells
+        /// put large shapes in many cells, much larger ones test more shapes per pick
+        ///
+        /// # Panics
+        /// If `cell_size` is not positive
+        pub fn new(shapes: &'a [P], cell_size: f32) -> Self {
+            assert!(cell_size > 0., "Picker cell size must be positive");
+            let mut cells: HashMap<[i32; 2], Vec<usize>> = HashMap::new();
+            let mut large = Vec::new();
+            for (index, shape) in shapes.iter().enumerate() {
+                let (min, max) = shape.bounds();
+                let finite = min.iter().chain(max.iter()).all(|value| value.is_finite());
+                let [min_x, min_y] = cell(min, cell_size);
+                let [max_x, max_y] = cell(max, cell_size);
+                // Widened so that spans across the whole i32 range can't overflow
+                let span = |min: i32, max: i32| max as i64 - min as i64 + 1;
+                let covered = span(min_x, max_x).saturating_mul(span(min_y, max_y));
+                if !finite || covered > Self::MAX_CELLS {
+                    large.push(index);
+                    continue;
+                }
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        cells.entry([x, y]).or_default().push(index);
+                    }
+                }
+            }
+            Self {
+                shapes,
+                cell_size,
+                cells,
+                large,
+            }
+        }
+
+        /// Number of cells above which a shape is tested on every pick instead
+        pub const MAX_CELLS: i64 = 1024;
+
+        /// Index of the topmost shape containing `point`. Higher layers are on top, and
+        /// within a layer later shapes are drawn over earlier ones
+        pub fn pick(&self, point: Vector2<f32>) -> Option<usize> {
+            let cell = self.cells.get(&cell(point, self.cell_size));
+            cell.map_or(&[][..], Vec::as_slice)
+                .iter()
+                .chain(&self.large)
+                .copied()
+                .filter(|&index| self.shapes[index].contains(point))
+                .max_by_key(|&index| (self.shapes[index].layer(), index))
+        }
+    }
+
+    fn cell(point: Vector2<f32>, cell_size: f32) -> [i32; 2] {
+        [
+            (point[0] / cell_size).floor() as i32,
+            (point[1] / cell_size).floor() as i32,
+        ]
+    }
+
+    /// Format of the texture in an [IdBuffer]
+    pub const ID_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+    /// A window sized texture that instance IDs are drawn to alongside the colors, for
+    /// picking on the GPU.
+    ///
+    /// Pass [Self::view] to [super::Renderer2D::render_with_targets] and draw renderers
+    /// through [super::PrimitiveRenderer::with_ids]. Each pixel then holds the index of
+    /// the topmost instance drawn there plus one, or 0 where nothing was drawn
+    pub struct IdBuffer {
+        texture: Texture,
+        view: TextureView,
+    }
+
+    impl IdBuffer {
+        pub fn new(context: &WGPUContext) -> Self {
+            let texture = context.device().create_texture(&TextureDescriptor {
+                label: Some("ID buffer"),
+                size: Extent3d {
+                    width: context.config().width,
+                    height: context.config().height,
+                    depth_or_array_layers: 1,
                 },
-                depth_stencil: None,
-                multisample: Default::default(),
-                fragment: Some(FragmentStateTemplate {
-                    module_path: "circle.wgsl",
-                    entry_point: None,
-                    targets: Box::new([Some(ColorTargetState {
-                        format: context.config().format,
-                        blend: Some(BlendState {
-                            color: BlendComponent {
-                                src_factor: BlendFactor::One,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
-                            },
-                            alpha: BlendComponent {
-                                src_factor: BlendFactor::One,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
-                            },
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })]),
-                }),
-                multiview: None,
-                cache: None,
-            };
-            shader_manager.register_constant_source("circle.wgsl", CIRCLE_SHADER.into());
-            shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
-            shader_manager.register_render_pipeline("circle", render_pipeline_template);
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: ID_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&Default::default());
+            Self { texture, view }
+        }
 
-            Self { circles }
+        /// Recreates the texture if the window size changed. Call after
+        /// [WGPUContext::resize]
+        pub fn resize(&mut self, context: &WGPUContext) {
+            let size = [context.config().width, context.config().height];
+            if size != [self.texture.width(), self.texture.height()] {
+                *self = Self::new(context);
+            }
         }
 
-        pub fn circles_mut(&mut self) -> &mut Vec<Circle> {
-            &mut self.circles.data
+        pub fn view(&self) -> &TextureView {
+            &self.view
         }
 
-        pub fn update_circles(&mut self, context: &WGPUContext) {
-            self.circles.update_buffer(context);
+        /// Index of the instance drawn at a pixel in the last rendered frame. [None]
+        /// where nothing was drawn or outside the texture.
+        ///
+        /// This waits for the GPU to finish, so call it on clicks rather than every frame
+        pub fn read(&self, pixel: [u32; 2], context: &WGPUContext) -> Option<u32> {
+            if pixel[0] >= self.texture.width() || pixel[1] >= self.texture.height() {
+                return None;
+            }
+            let readback = context.device().create_buffer(&BufferDescriptor {
+                label: Some("ID readback buffer"),
+                size: 4,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let mut encoder = context.get_encoder();
+            encoder.copy_texture_to_buffer(
+                TexelCopyTextureInfo {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: pixel[0],
+                        y: pixel[1],
+                        z: 0,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                TexelCopyBufferInfo {
+                    buffer: &readback,
+                    layout: TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: None,
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+            context.queue().submit([encoder.finish()]);
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            readback
+                .slice(..)
+                .map_async(MapMode::Read, move |result| _ = sender.send(result));
+            let _ = context.device().poll(Maintain::Wait);
+            if let Err(err) = receiver.recv().ok()? {
+                log::warn!("Could not read the ID buffer: {err}");
+                return None;
+            }
+            let id = bytemuck::pod_read_unaligned::<u32>(&readback.slice(..).get_mapped_range());
+            id.checked_sub(1)
         }
     }
 
-    impl Render for CircleRenderer {
-        fn render(
-            &self,
-            render_pass: &mut RenderPass,
-            context: &WGPUContext,
-            shader_manager: &ShaderManager,
-        ) {
-            render_pass.set_pipeline(shader_manager.get_render_pipeline("circle", context));
-            render_pass.set_vertex_buffer(0, self.circles.buffers.0.slice(..));
-            render_pass.set_vertex_buffer(1, self.circles.buffers.1.slice(..));
-            render_pass.set_vertex_buffer(2, self.circles.buffers.2.slice(..));
-            render_pass.draw(0..4 as u32, 0..self.circles.data.len() as u32);
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::math::Vector4;
+
+        fn circle(position: [f32; 2], radius: f32, layer: u32) -> Circle {
+            Circle {
+                position: Vector2::new(position),
+                radius,
+                layer,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn rotated_rect_hit_test() {
+            let rect = CenterRect {
+                color: Vector4::new([1., 1., 1., 1.]),
+                center: Vector2::new([10., 10.]),
+                size: Vector2::new([20., 2.]),
+                rotation: std::f32::consts::FRAC_PI_2,
+                layer: 0,
+            };
+            // Rotated a quarter turn the rect is tall and thin
+            assert!(hit_test_rect(&rect, Vector2::new([10., 19.])));
+            assert!(!hit_test_rect(&rect, Vector2::new([19., 10.])));
+            let (min, max) = rect.bounds();
+            assert!((min[0] - 9.).abs() < 1e-5 && (max[1] - 20.).abs() < 1e-5);
+        }
+
+        #[test]
+        fn picks_topmost() {
+            let circles = [
+                circle([0., 0.], 10., 1),
+                circle([5., 0.], 10., 0),
+                circle([8., 0.], 10., 0),
+                circle([100., 100.], 10., 0),
+            ];
+            let picker = Picker::new(&circles, 16.);
+            // The first circle is on a higher layer than the later ones
+            assert_eq!(picker.pick(Vector2::new([6., 0.])), Some(0));
+            // Outside the first, the last one drawn wins
+            assert_eq!(picker.pick(Vector2::new([14., 0.])), Some(2));
+            assert_eq!(picker.pick(Vector2::new([-100., 0.])), None);
+            assert_eq!(picker.pick(Vector2::new([95., 105.])), Some(3));
+        }
+
+        #[test]
+        fn picks_oversized_shapes() {
+            let circles = [
+                circle([0., 0.], 1e30, 0),
+                circle([f32::NAN, 0.], 1., 1),
+                circle([5., 0.], 1., 1),
+            ];
+            // Filling every cell of the first circle would never finish
+            let picker = Picker::new(&circles, 1.);
+            assert_eq!(picker.pick(Vector2::new([5., 0.])), Some(2));
+            assert_eq!(picker.pick(Vector2::new([-1e15, 1e15])), Some(0));
         }
     }
 }
 
-mod ring {
-    use crate::shader_manager::*;
-    use crate::vertex_buffer_layout;
-    use crate::wgpu_context::{BufferAndData, WGPUContext};
-    use derive::VertexBufferData;
+mod primitive {
+    use std::borrow::Cow;
+    use std::ops::Range;
+
+    use bytemuck::{Pod, Zeroable};
     use wgpu::*;
 
-    use super::Render;
+    use crate::math::Vector4;
+    use crate::shader_manager::*;
+    use crate::wgpu_context::{BufferAndData, BufferData, BufferUploader, WGPUBuffer, WGPUContext};
 
-    use crate::math::{Vector2, Vector4};
-    use bytemuck::{Pod, Zeroable};
+    use super::{Render, RenderStats};
 
-    #[derive(Pod, Zeroable, Clone, Copy, VertexBufferData)]
-    #[repr(C)]
-    pub struct Ring {
-        pub color: Vector4<f32>,
-        pub position: Vector2<f32>,
-        pub outer_radius: f32,
-        pub inner_radius: f32,
+    /// A tuple of vertex buffers that get bound to consecutive slots starting at 0
+    pub trait VertexBuffers {
+        fn set_vertex_buffers(&self, render_pass: &mut RenderPass);
     }
 
-	const RING_SHADER: &str = include_str!("../shaders/rings.wgsl");
+    impl VertexBuffers for WGPUBuffer {
+        fn set_vertex_buffers(&self, render_pass: &mut RenderPass) {
+            render_pass.set_vertex_buffer(0, self.slice(..));
+        }
+    }
 
-    pub struct RingRenderer {
-        rings: BufferAndData<Vec<Ring>>,
+    macro_rules! impl_vertex_buffers {
+        ($($index: tt),+) => {
+            impl VertexBuffers for ($(impl_vertex_buffers!(@buffer $index),)+) {
+                fn set_vertex_buffers(&self, render_pass: &mut RenderPass) {
+                    $(render_pass.set_vertex_buffer($index, self.$index.slice(..));)+
+                }
+            }
+        };
+        (@buffer $index: tt) => { WGPUBuffer };
     }
 
-    impl RingRenderer {
-        pub fn new(
-            data: Vec<Ring>,
+    impl_vertex_buffers!(0, 1);
+    impl_vertex_buffers!(0, 1, 2);
+    impl_vertex_buffers!(0, 1, 2, 3);
+    impl_vertex_buffers!(0, 1, 2, 3, 4);
+    impl_vertex_buffers!(0, 1, 2, 3, 4, 5);
+    impl_vertex_buffers!(0, 1, 2, 3, 4, 5, 6);
+
+    /// Highest layer an instance can be on. Layers range from 0 to this and instances
+    /// on higher layers are drawn on top of lower ones.
+    ///
+    /// The vertex shaders turn the layer into a depth, with higher layers closer, for use
+    /// with a depth buffer. Without one, instances are drawn in the order they are
+    /// stored, so keep them sorted with [PrimitiveRenderer::sort_by_layer]
+    pub const MAX_LAYER: u32 = u16::MAX as u32;
+
+    /// A shape that is drawn by instancing, with one instance per element of data.
+    ///
+    /// The buffers are the ones generated by `VertexBufferData`, so [Self::VERTEX_BUFFERS] must
+    /// describe the fields of the struct in order
+    pub trait Primitive: Sized {
+        /// Source of the shader for both the vertex and fragment stages
+        const SHADER: &'static str;
+        /// Path the shader source is registered under in the [ShaderManager]
+        const SHADER_PATH: &'static str;
+        /// Name of the render pipeline in the [ShaderManager]
+        const LABEL: &'static str;
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>];
+        /// Number of vertices drawn for each instance
+        const VERTEX_COUNT: u32 = 4;
+        /// Fragment entry point that also writes the ID of each instance to
+        /// `@location(1)`, see [PrimitiveRenderer::with_ids]. `None` if the shader
+        /// has none
+        const ID_ENTRY_POINT: Option<&'static str> = None;
+
+        /// Layer of this instance, see [MAX_LAYER]
+        fn layer(&self) -> u32;
+
+        fn topology() -> PrimitiveTopology {
+            PrimitiveTopology::TriangleStrip
+        }
+
+        /// Registers the shader and render pipeline for this primitive, plus the one
+        /// writing IDs if there is an [Self::ID_ENTRY_POINT]. Registration is
+        /// idempotent so every renderer of this primitive can call this
+        fn register(
             uniform_bind_group_layout: &BindGroupLayout,
             context: &WGPUContext,
             shader_manager: &ShaderManager,
-        ) -> Self {
-            let rings = BufferAndData::new(data, context);
+        ) {
+            shader_manager.register_constant_source(Self::SHADER_PATH, Self::SHADER.into());
+            shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
+            shader_manager.register_constant_source(
+                "renderer_constants.wgsl",
+                super::RENDERER_CONSTANTS_INCLUDE.into(),
+            );
+            shader_manager.register_render_pipeline(
+                Self::LABEL,
+                Self::pipeline_template(
+                    "f_main",
+                    Box::new([Some(surface_color_target(context))]),
+                    uniform_bind_group_layout,
+                    context,
+                ),
+            );
+            if let Some(entry_point) = Self::ID_ENTRY_POINT {
+                shader_manager.register_render_pipeline(
+                    &id_pipeline_label::<Self>(),
+                    Self::pipeline_template(
+                        entry_point,
+                        Box::new([Some(surface_color_target(context)), Some(id_color_target())]),
+                        uniform_bind_group_layout,
+                        context,
+                    ),
+                );
+            }
+        }
+
+        /// Registers the pipeline drawing this primitive into color targets of `format`,
+        /// e.g. an `Rgba16Float` texture for HDR or data passes, and returns its label.
+        /// The surface format uses the pipeline of [Self::register] and [Self::LABEL]
+        fn register_for_format(
+            format: TextureFormat,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Cow<'static, str> {
+            Self::register(uniform_bind_group_layout, context, shader_manager);
+            if format == context.config().format {
+                return Cow::Borrowed(Self::LABEL);
+            }
+            let label = format!("{}_{format:?}", Self::LABEL);
+            shader_manager.register_render_pipeline(
+                &label,
+                Self::pipeline_template(
+                    "f_main",
+                    Box::new([Some(color_target(format))]),
+                    uniform_bind_group_layout,
+                    context,
+                ),
+            );
+            Cow::Owned(label)
+        }
 
+        /// Template of the pipeline drawing this primitive with the given fragment
+        /// entry point and color targets. The layout has the uniform at group 0 and the
+        /// [RendererConstants] at group 1
+        fn pipeline_template(
+            fragment_entry_point: &'static str,
+            targets: Box<[Option<ColorTargetState>]>,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+        ) -> RenderPipelineDescriptorTemplate {
             let pipeline_layout =
                 context
                     .device()
                     .create_pipeline_layout(&PipelineLayoutDescriptor {
                         label: None,
-                        bind_group_layouts: &[&uniform_bind_group_layout],
+                        bind_group_layouts: &[
+                            uniform_bind_group_layout,
+                            &RendererConstants::bind_group_layout(context),
+                        ],
                         push_constant_ranges: &[],
                     });
 
-            let render_pipeline_template = RenderPipelineDescriptorTemplate {
-                label: Some("Ring Pipeline"),
+            RenderPipelineDescriptorTemplate {
+                label: Some(Self::LABEL),
                 layout: Some(pipeline_layout),
                 vertex: VertexStateTemplate {
-                    module_path: "rings.wgsl",
+                    module_path: Self::SHADER_PATH,
                     entry_point: None,
-                    buffers: &vertex_buffer_layout!(
-                        ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
-                        ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
-                        (f32, Instance, &vertex_attr_array![2 => Float32]),
-                        (f32, Instance, &vertex_attr_array![3 => Float32]),
-                    ),
+                    buffers: Self::VERTEX_BUFFERS,
                 },
                 primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleStrip,
+                    topology: Self::topology(),
                     ..Default::default()
                 },
                 depth_stencil: None,
                 multisample: Default::default(),
                 fragment: Some(FragmentStateTemplate {
-                    module_path: "rings.wgsl",
-                    entry_point: None,
-                    targets: Box::new([Some(ColorTargetState {
-                        format: context.config().format,
-                        blend: Some(BlendState {
-                            color: BlendComponent {
-                                src_factor: BlendFactor::One,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
-                            },
-                            alpha: BlendComponent {
-                                src_factor: BlendFactor::One,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
-                            },
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })]),
+                    module_path: Self::SHADER_PATH,
+                    entry_point: Some(fragment_entry_point),
+                    targets,
                 }),
                 multiview: None,
                 cache: None,
-            };
-            shader_manager.register_constant_source("rings.wgsl", RING_SHADER.into());
-            shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
-            shader_manager.register_render_pipeline("Ring", render_pipeline_template);
-
-            Self { rings }
+                push_constant_ranges: Box::new([]),
+                constants: Default::default(),
+            }
         }
+    }
 
-        pub fn rings_mut(&mut self) -> &mut Vec<Ring> {
-            &mut self.rings.data
-        }
+    /// The color target for drawing to the surface with premultiplied alpha blending
+    pub fn surface_color_target(context: &WGPUContext) -> ColorTargetState {
+        color_target(context.config().format)
+    }
 
-        pub fn update_rings(&mut self, context: &WGPUContext) {
-            self.rings.update_buffer(context);
+    /// A color target of any format with the same blending as [surface_color_target]
+    pub fn color_target(format: TextureFormat) -> ColorTargetState {
+        ColorTargetState {
+            format,
+            blend: Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+            }),
+            write_mask: ColorWrites::ALL,
         }
     }
 
-    impl Render for RingRenderer {
-        fn render(
-            &self,
-            render_pass: &mut RenderPass,
-            context: &WGPUContext,
-            shader_manager: &ShaderManager,
-        ) {
-            render_pass.set_pipeline(shader_manager.get_render_pipeline("Ring", context));
-            render_pass.set_vertex_buffer(0, self.rings.buffers.0.slice(..));
-            render_pass.set_vertex_buffer(1, self.rings.buffers.1.slice(..));
-            render_pass.set_vertex_buffer(2, self.rings.buffers.2.slice(..));
-            render_pass.set_vertex_buffer(3, self.rings.buffers.3.slice(..));
-            render_pass.draw(0..4 as u32, 0..self.rings.data.len() as u32);
+    /// The color target IDs are written to, see [super::IdBuffer]
+    pub fn id_color_target() -> ColorTargetState {
+        ColorTargetState {
+            format: super::ID_FORMAT,
+            blend: None,
+            write_mask: ColorWrites::ALL,
         }
     }
-}
 
-mod texture {
-    use super::Render;
-    use crate::math::{Vector2, Vector4};
-    use crate::rendering::CenterRect;
-    use crate::shader_manager::{
-        FragmentStateTemplate, RenderPipelineDescriptorTemplate, ShaderManager, VertexStateTemplate,
-    };
-    use crate::wgpu_context::{BufferAndData, WGPUContext};
-    use wgpu::*;
+    fn id_pipeline_label<P: Primitive>() -> String {
+        format!("{}_ids", P::LABEL)
+    }
 
-    // struct TextureData {
-    // 	data: Vec<[f32; 4]>,
-    // 	rows: usize,
-    // 	columns: usize,
-    // }
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct RendererConstantsData {
+        tint: [f32; 4],
+        time: f32,
+        // The WGSL struct is rounded up to the 16 byte alignment of its vec4
+        _padding: [f32; 3],
+    }
 
-    // impl TextureData {
-    // 	pub fn new (data: Vec<[f32;4]>, rows: usize, columns: usize) -> Self {
-    // 		assert!(rows * columns == data.len());
-    // 		Self {
-    // 			data,
-    // 			rows,
-    // 			columns,
-    // 		}
-    // 	}
+    /// Data shared by every instance a renderer draws, bound at group 1 of the [Primitive]
+    /// pipelines. Shaders declare it by including `renderer_constants.wgsl`.
+    ///
+    /// Like the [super::Uniform], the buffer is written through the queue, so every draw
+    /// submitted together sees the last value set
+    pub struct RendererConstants {
+        data: RendererConstantsData,
+        buffer: WGPUBuffer,
+        bind_group: BindGroup,
+    }
 
-    // 	pub fn generate_next_mip(&self) -> Option<Self> {
-    // 		todo!();
-    // 		// if self.rows == 1 && self.columns == 1 {
-    // 		// 	return None;
-    // 		// }
-    // 		// let mip_rows = std::cmp::max(self.rows / 2, 1);
-    // 		// let mip_columns = std::cmp::max(self.columns / 2, 1);
-    // 		// let mut output = Vec::new();
+    impl RendererConstants {
+        /// Starts with a white tint, which leaves colors unchanged, and a time of 0
+        pub fn new(context: &WGPUContext) -> Self {
+            let data = RendererConstantsData {
+                tint: [1.; 4],
+                time: 0.,
+                _padding: [0.; 3],
+            };
+            let mut buffer =
+                WGPUBuffer::new_uniform(size_of::<RendererConstantsData>() as u64, context);
+            buffer.write_data(bytemuck::bytes_of(&data), context);
+            let bind_group = context.device().create_bind_group(&BindGroupDescriptor {
+                label: Some("Renderer constants bind group"),
+                layout: &Self::bind_group_layout(context),
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            Self {
+                data,
+                buffer,
+                bind_group,
+            }
+        }
 
-    // 		// for y in 0..mip_rows {
-    // 		// 	for x in 0..mip_columns {
-    // 		// 		// uv of next mip
-    // 		// 		let u = (x as f32 + 0.5) / mip_columns;
-    // 		// 		let v = (y as f32 + 0.5) / mip_rows;
+        /// Layout of group 1 of the [Primitive] pipelines
+        pub fn bind_group_layout(context: &WGPUContext) -> BindGroupLayout {
+            context
+                .device()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("Renderer constants bind group layout"),
+                    entries: &[BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::VERTEX_FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                })
+        }
 
-    // 		// 		let au = (u * self.rows - 0.5);
-    // 		// 		let av = (v * self.columns - 0.5);
+        pub fn tint(&self) -> Vector4<f32> {
+            Vector4::new(self.data.tint)
+        }
 
-    // 		// 		// compute the src top left texel coord (not texcoord)
-    // 		// 		let tx = au;
-    // 		// 		let ty = av;
+        /// Multiplied with the color of every instance
+        pub fn set_tint(&mut self, tint: Vector4<f32>, context: &WGPUContext) {
+            self.data.tint = tint.into_inner();
+            self.write(context);
+        }
 
-    // 		// 		// compute the mix amounts between pixels
-    // 		// 		let t1 = au % 1;
-    // 		// 		let t2 = av % 1;
-    // 		// 	}
-    // 		// }
-    // 	}
-    // }
+        pub fn time(&self) -> f32 {
+            self.data.time
+        }
 
-    // impl std::ops::Index<(f32, f32)> for TextureData {
-    // 	type Output = [[f32;4]];
-    // 	fn index (&self, index: usize) -> &Self::Output {
-    // 		&self.data[(index * self.columns)..((index + 1) * self.columns)]
-    // 	}
-    // }
+        /// Passed to the shaders as is, e.g. for animations
+        pub fn set_time(&mut self, time: f32, context: &WGPUContext) {
+            self.data.time = time;
+            self.write(context);
+        }
 
-    // impl std::ops::Index<usize> for TextureData {
-    // 	type Output = [[f32;4]];
-    // 	fn index (&self, index: usize) -> &Self::Output {
-    // 		&self.data[(index * self.columns)..((index + 1) * self.columns)]
-    // 	}
-    // }
+        /// Binds the constants to group 1 for the following draws
+        pub fn bind(&self, render_pass: &mut RenderPass) {
+            render_pass.set_bind_group(1, &self.bind_group, &[]);
+        }
 
-	const TEXTURE_SHADER: &str = include_str!("../shaders/texture.wgsl");
+        fn write(&mut self, context: &WGPUContext) {
+            self.buffer.write_data(bytemuck::bytes_of(&self.data), context);
+        }
+    }
 
-    pub struct TextureRenderer {
-        rect: BufferAndData<CenterRect>,
-        #[allow(dead_code)]
-        texture: Texture,
-        #[allow(dead_code)]
-        view: TextureView,
-        #[allow(dead_code)]
-        sampler: Sampler,
-        bind_group: BindGroup,
+    /// Draws every element of a `Vec<P>` as an instance of `P`
+    pub struct PrimitiveRenderer<P: Primitive>
+    where
+        Vec<P>: BufferData,
+    {
+        data: BufferAndData<Vec<P>>,
+        dirty: bool,
+        constants: RendererConstants,
+        pipeline_label: Cow<'static, str>,
+        active_range: Option<Range<u32>>,
     }
 
-    impl TextureRenderer {
+    impl<P: Primitive> PrimitiveRenderer<P>
+    where
+        Vec<P>: BufferData,
+    {
         pub fn new(
+            data: Vec<P>,
             uniform_bind_group_layout: &BindGroupLayout,
             context: &WGPUContext,
             shader_manager: &ShaderManager,
         ) -> Self {
-            let rect = BufferAndData::new(
-                CenterRect {
-                    color: Vector4::new([0., 0., 0., 1.]),
-                    center: Vector2::new([4.5, 3.5]),
-                    size: Vector2::new([1.0, 1.0]),
-                    rotation: 0.,
-                },
+            let format = context.config().format;
+            Self::with_format(data, format, uniform_bind_group_layout, context, shader_manager)
+        }
+
+        /// Like [Self::new], but draws into color targets of `target_format` instead of
+        /// the surface, e.g. an offscreen texture. See [Primitive::register_for_format]
+        pub fn with_format(
+            data: Vec<P>,
+            target_format: TextureFormat,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            let data = BufferAndData::new(data, context);
+
+            let pipeline_label = P::register_for_format(
+                target_format,
+                uniform_bind_group_layout,
                 context,
+                shader_manager,
             );
 
-            // Texture data
-            let x: [u8; 4] = [255, 0, 0, 255];
-            let y: [u8; 4] = [255, 255, 0, 255];
-            let b: [u8; 4] = [0, 0, 255, 255];
-            let texture_data = &[
-                [b, x, x, x, x],
-                [x, y, y, y, x],
-                [x, y, x, x, x],
-                [x, y, y, x, x],
-                [x, y, x, x, x],
-                [x, y, x, x, x],
-                [x, x, x, x, x],
-            ];
+            Self {
+                data,
+                dirty: false,
+                constants: RendererConstants::new(context),
+                pipeline_label,
+                active_range: None,
+            }
+        }
 
-            // Create Texture
-            let texture = context.device().create_texture(&TextureDescriptor {
-                label: Some("Test Texture"),
-                size: Extent3d {
-                    height: texture_data.len() as u32,
-                    width: texture_data[0].len() as u32,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8Unorm,
-                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
-                view_formats: &[TextureFormat::Rgba8Unorm],
-            });
+        pub fn constants(&self) -> &RendererConstants {
+            &self.constants
+        }
 
-            let texture_view = texture.create_view(&TextureViewDescriptor {
-                label: Some("Texture View"),
-                format: None,
-                dimension: None,
-                usage: None,
-                aspect: TextureAspect::All,
-                base_mip_level: 0,
-                mip_level_count: None,
-                base_array_layer: 0,
-                array_layer_count: None,
-            });
+        /// Multiplies the color of every instance by `tint`, see [RendererConstants]
+        pub fn set_tint(&mut self, tint: Vector4<f32>, context: &WGPUContext) {
+            self.constants.set_tint(tint, context);
+        }
 
-            // Copy data to texture
-            context.queue().write_texture(
-                TexelCopyTextureInfo {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: Origin3d { x: 0, y: 0, z: 0 },
-                    aspect: TextureAspect::All,
-                },
-                bytemuck::cast_slice(texture_data),
-                TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(
-                        (std::mem::size_of_val(texture_data) / texture_data.len()) as u32,
-                    ),
-                    rows_per_image: Some(texture_data.len() as u32),
-                },
-                Extent3d {
-                    width: texture_data[0].len() as u32,
-                    height: texture_data.len() as u32,
-                    depth_or_array_layers: 1,
-                },
-            );
+        pub fn set_time(&mut self, time: f32, context: &WGPUContext) {
+            self.constants.set_time(time, context);
+        }
 
-            // Create Sampler
-            let sampler = context.device().create_sampler(&SamplerDescriptor {
-                label: Some("Test Sampler"),
-                address_mode_u: AddressMode::Repeat,
-                address_mode_v: AddressMode::Repeat,
-                address_mode_w: AddressMode::Repeat,
-                mag_filter: FilterMode::Linear,
-                min_filter: FilterMode::Linear,
-                mipmap_filter: FilterMode::Nearest,
-                lod_min_clamp: 0.,
-                lod_max_clamp: 0.,
-                compare: None,
-                anisotropy_clamp: 1,
-                border_color: None,
-            });
+        /// Draws only the instances in `range`, e.g. the live part of a pool, without
+        /// uploading anything or resizing the buffers. `None`, the default, draws every
+        /// instance. Parts of the range past the last instance are ignored
+        pub fn set_active_range(&mut self, range: Option<Range<u32>>) {
+            self.active_range = range;
+        }
 
-            let bind_group_layout =
-                context
-                    .device()
-                    .create_bind_group_layout(&BindGroupLayoutDescriptor {
-                        label: Some("Texture bind group layout"),
-                        entries: &[
-                            BindGroupLayoutEntry {
-                                binding: 0,
-                                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
-                                ty: BindingType::Buffer {
-                                    ty: BufferBindingType::Uniform,
-                                    has_dynamic_offset: false,
-                                    min_binding_size: None,
-                                },
-                                count: None,
-                            },
-                            BindGroupLayoutEntry {
-                                binding: 1,
-                                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
-                                ty: BindingType::Texture {
-                                    sample_type: TextureSampleType::Float { filterable: true },
-                                    view_dimension: TextureViewDimension::D2,
-                                    multisampled: false,
-                                },
-                                count: None,
-                            },
-                            BindGroupLayoutEntry {
-                                binding: 2,
-                                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
-                                ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                                count: None,
-                            },
-                        ],
-                    });
+        /// The instances that are drawn, see [Self::set_active_range]
+        pub fn active_range(&self) -> Range<u32> {
+            clamp_range(&self.active_range, self.data.data.len() as u32)
+        }
 
-            let pipeline_layout =
-                context
-                    .device()
-                    .create_pipeline_layout(&PipelineLayoutDescriptor {
-                        label: Some("Texture pipeline layout"),
-                        bind_group_layouts: &[uniform_bind_group_layout, &bind_group_layout],
-                        push_constant_ranges: &[],
-                    });
+        /// Marks the data as changed so the next [Self::sync] uploads it
+        pub fn data_mut(&mut self) -> &mut Vec<P> {
+            self.dirty = true;
+            &mut self.data.data
+        }
 
-            let render_pipeline_template = RenderPipelineDescriptorTemplate {
-                label: Some("Texture quad Pipeline"),
-                layout: Some(pipeline_layout),
-                vertex: VertexStateTemplate {
-                    module_path: "texture.wgsl",
-                    entry_point: None,
-                    buffers: &[],
-                },
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleStrip,
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: Default::default(),
-                fragment: Some(FragmentStateTemplate {
-                    module_path: "texture.wgsl",
-                    entry_point: None,
+        /// Applies `f` to every instance and marks the data as changed
+        pub fn for_each_mut(&mut self, f: impl FnMut(&mut P)) {
+            self.data_mut().iter_mut().for_each(f);
+        }
+
+        /// Applies `f` to the instance at `index` and marks the data as changed.
+        /// Returns false without changing anything if `index` is out of range
+        pub fn modify(&mut self, index: usize, f: impl FnOnce(&mut P)) -> bool {
+            let Some(instance) = self.data.data.get_mut(index) else {
+                return false;
+            };
+            f(instance);
+            self.dirty = true;
+            true
+        }
+
+        /// Uploads the data regardless of whether it changed
+        pub fn update(&mut self, context: &WGPUContext) {
+            self.data.update_buffer(context);
+            self.dirty = false;
+        }
+
+        /// Copy of the instances, see [BufferAndData::snapshot]
+        pub fn snapshot(&self) -> Vec<P>
+        where
+            P: Clone,
+        {
+            self.data.snapshot()
+        }
+
+        /// Replaces the instances with `snapshot` and uploads them, see
+        /// [BufferAndData::restore]
+        pub fn restore(&mut self, snapshot: Vec<P>, context: &WGPUContext)
+        where
+            P: Clone,
+        {
+            self.data.restore(snapshot, context);
+            self.dirty = false;
+        }
+
+        /// Uploads the data only if it was accessed mutably since the last upload
+        pub fn sync(&mut self, context: &WGPUContext) {
+            if self.dirty {
+                self.update(context);
+            }
+        }
+
+        /// Stably sorts the instances by layer so higher layers are drawn on top without
+        /// a depth buffer. Instances on the same layer keep their order
+        pub fn sort_by_layer(&mut self) {
+            self.data_mut().sort_by_key(P::layer);
+        }
+
+        /// Same as [Self::sync] but records the upload into `uploader`, which has to be
+        /// submitted before rendering
+        pub fn sync_with(&mut self, uploader: &mut BufferUploader, context: &WGPUContext) {
+            if self.dirty {
+                self.data.update_buffer_with(Some(uploader), context);
+                self.dirty = false;
+            }
+        }
+
+        /// Draws the instances like this renderer does, but also writes the index of
+        /// each instance plus one to a second color target. Only for use with
+        /// [super::Renderer2D::render_with_targets], e.g. with an [super::IdBuffer].
+        ///
+        /// Panics if `P` has no [Primitive::ID_ENTRY_POINT]
+        pub fn with_ids(&self) -> WithIds<'_, P> {
+            assert!(
+                P::ID_ENTRY_POINT.is_some(),
+                "{} can't write IDs",
+                P::LABEL
+            );
+            WithIds {
+                renderer: self,
+                pipeline_label: id_pipeline_label::<P>(),
+            }
+        }
+    }
+
+    /// A [PrimitiveRenderer] that also writes instance IDs, see
+    /// [PrimitiveRenderer::with_ids]
+    pub struct WithIds<'a, P: Primitive>
+    where
+        Vec<P>: BufferData,
+    {
+        renderer: &'a PrimitiveRenderer<P>,
+        pipeline_label: String,
+    }
+
+    impl<P: Primitive> Render for WithIds<'_, P>
+    where
+        Vec<P>: BufferData<Buffers: VertexBuffers>,
+    {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline(&self.pipeline_label, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(&self.pipeline_label)
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, context: &WGPUContext, shader_manager: &ShaderManager) {
+            self.renderer.draw(render_pass, context, shader_manager);
+        }
+
+        fn stats(&self) -> RenderStats {
+            self.renderer.stats()
+        }
+    }
+
+    /// Draws instances of `P` whose data is owned elsewhere, e.g. by component storage.
+    ///
+    /// Unlike [PrimitiveRenderer] no copy of the data is kept on the CPU. The data is
+    /// copied into the GPU buffers on creation and on every [Self::update]
+    pub struct SlicePrimitiveRenderer<P: Primitive>
+    where
+        [P]: BufferData,
+    {
+        buffers: <[P] as BufferData>::Buffers,
+        // Number of instances the buffers have room for
+        capacity: usize,
+        instance_count: u32,
+        constants: RendererConstants,
+        pipeline_label: Cow<'static, str>,
+        active_range: Option<Range<u32>>,
+    }
+
+    impl<P: Primitive> SlicePrimitiveRenderer<P>
+    where
+        [P]: BufferData,
+    {
+        pub fn from_slice(
+            data: &[P],
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            let format = context.config().format;
+            Self::from_slice_with_format(
+                data,
+                format,
+                uniform_bind_group_layout,
+                context,
+                shader_manager,
+            )
+        }
+
+        /// Like [Self::from_slice], but draws into color targets of `target_format`
+        /// instead of the surface. See [Primitive::register_for_format]
+        pub fn from_slice_with_format(
+            data: &[P],
+            target_format: TextureFormat,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            let mut buffers = data.create_buffers(context);
+            data.fill_buffers(&mut buffers, context);
+
+            let pipeline_label = P::register_for_format(
+                target_format,
+                uniform_bind_group_layout,
+                context,
+                shader_manager,
+            );
+
+            Self {
+                buffers,
+                capacity: data.len(),
+                instance_count: data.len() as u32,
+                constants: RendererConstants::new(context),
+                pipeline_label,
+                active_range: None,
+            }
+        }
+
+        /// Uploads `data`, replacing the previous instances. The buffers are only
+        /// recreated when `data` doesn't fit
+        pub fn update(&mut self, data: &[P], context: &WGPUContext) {
+            if data.len() > self.capacity {
+                self.buffers = data.create_buffers(context);
+                self.capacity = data.len();
+            }
+            data.fill_buffers(&mut self.buffers, context);
+            self.instance_count = data.len() as u32;
+        }
+
+        pub fn instance_count(&self) -> u32 {
+            self.instance_count
+        }
+
+        /// See [PrimitiveRenderer::set_active_range]
+        pub fn set_active_range(&mut self, range: Option<Range<u32>>) {
+            self.active_range = range;
+        }
+
+        /// The instances that are drawn, see [PrimitiveRenderer::set_active_range]
+        pub fn active_range(&self) -> Range<u32> {
+            clamp_range(&self.active_range, self.instance_count)
+        }
+
+        pub fn constants(&self) -> &RendererConstants {
+            &self.constants
+        }
+
+        /// Multiplies the color of every instance by `tint`, see [RendererConstants]
+        pub fn set_tint(&mut self, tint: Vector4<f32>, context: &WGPUContext) {
+            self.constants.set_tint(tint, context);
+        }
+
+        pub fn set_time(&mut self, time: f32, context: &WGPUContext) {
+            self.constants.set_time(time, context);
+        }
+    }
+
+    impl<P: Primitive> Render for SlicePrimitiveRenderer<P>
+    where
+        [P]: BufferData<Buffers: VertexBuffers>,
+    {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline(&self.pipeline_label, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(&self.pipeline_label)
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            self.constants.bind(render_pass);
+            self.buffers.set_vertex_buffers(render_pass);
+            render_pass.draw(0..P::VERTEX_COUNT, self.active_range());
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(P::VERTEX_COUNT, self.active_range().len() as u32)
+        }
+    }
+
+    impl<P: Primitive> Render for PrimitiveRenderer<P>
+    where
+        Vec<P>: BufferData<Buffers: VertexBuffers>,
+    {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline(&self.pipeline_label, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(&self.pipeline_label)
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            self.constants.bind(render_pass);
+            self.data.buffers.set_vertex_buffers(render_pass);
+            render_pass.draw(0..P::VERTEX_COUNT, self.active_range());
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(P::VERTEX_COUNT, self.active_range().len() as u32)
+        }
+    }
+
+    /// `range` cut off at `instance_count`, or every instance if there is no range.
+    /// Reversed ranges are empty
+    fn clamp_range(range: &Option<Range<u32>>, instance_count: u32) -> Range<u32> {
+        match range {
+            Some(range) => {
+                let end = range.end.min(instance_count);
+                range.start.min(end)..end
+            }
+            None => 0..instance_count,
+        }
+    }
+}
+
+mod rect {
+    use derive::*;
+
+    use wgpu::*;
+
+    use crate::math::{Vector2, Vector4};
+    use crate::vertex_buffer_layout;
+    use crate::wgpu_context::*;
+
+    use super::{Primitive, PrimitiveRenderer};
+
+    use bytemuck::{Pod, Zeroable};
+    #[derive(Clone, Copy, Pod, Zeroable, UniformBufferData, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct CenterRect {
+        pub color: Vector4<f32>,
+        pub center: Vector2<f32>,
+        pub size: Vector2<f32>,
+        pub rotation: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+	const RECT_SHADER: &str = include_str!("../shaders/rect.wgsl");
+
+    impl Primitive for CenterRect {
+        const SHADER: &'static str = RECT_SHADER;
+        const SHADER_PATH: &'static str = "rect.wgsl";
+        const LABEL: &'static str = "rects";
+        const ID_ENTRY_POINT: Option<&'static str> = Some("f_main_id");
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            ([f32; 2], Instance, &vertex_attr_array![2 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            (u32, Instance, &vertex_attr_array![4 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type RectangleRenderer = PrimitiveRenderer<CenterRect>;
+
+    /// A [CenterRect] with its color packed by [super::pack_color], so the color takes 4
+    /// bytes of the instance buffer instead of 16. Draws with the same shader
+    #[derive(Clone, Copy, Pod, Zeroable, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PackedRect {
+        pub color: u32,
+        pub center: Vector2<f32>,
+        pub size: Vector2<f32>,
+        pub rotation: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+    impl From<CenterRect> for PackedRect {
+        fn from(rect: CenterRect) -> Self {
+            Self {
+                color: super::pack_color(rect.color),
+                center: rect.center,
+                size: rect.size,
+                rotation: rect.rotation,
+                layer: rect.layer,
+            }
+        }
+    }
+
+    impl Primitive for PackedRect {
+        const SHADER: &'static str = RECT_SHADER;
+        const SHADER_PATH: &'static str = "rect.wgsl";
+        const LABEL: &'static str = "rects_packed";
+        const ID_ENTRY_POINT: Option<&'static str> = Some("f_main_id");
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            (u32, Instance, &vertex_attr_array![0 => Unorm8x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            ([f32; 2], Instance, &vertex_attr_array![2 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            (u32, Instance, &vertex_attr_array![4 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type PackedRectRenderer = PrimitiveRenderer<PackedRect>;
+
+    impl RectangleRenderer {
+        pub fn rects_mut(&mut self) -> &mut Vec<CenterRect> {
+            self.data_mut()
+        }
+
+        pub fn update_rects(&mut self, context: &WGPUContext) {
+            self.update(context);
+        }
+    }
+
+    /// A [CenterRect] with a border drawn inside its edges
+    #[derive(Clone, Copy, Pod, Zeroable, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct BorderedRect {
+        /// Color of the fill inside the border
+        pub color: Vector4<f32>,
+        pub center: Vector2<f32>,
+        pub size: Vector2<f32>,
+        pub rotation: f32,
+        pub border_color: Vector4<f32>,
+        /// In the same units as `size`. 0 draws no border
+        pub border_width: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+	const BORDERED_RECT_SHADER: &str = include_str!("../shaders/bordered_rect.wgsl");
+
+    impl Primitive for BorderedRect {
+        const SHADER: &'static str = BORDERED_RECT_SHADER;
+        const SHADER_PATH: &'static str = "bordered_rect.wgsl";
+        const LABEL: &'static str = "bordered_rects";
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            ([f32; 2], Instance, &vertex_attr_array![2 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            ([f32; 4], Instance, &vertex_attr_array![4 => Float32x4]),
+            (f32, Instance, &vertex_attr_array![5 => Float32]),
+            (u32, Instance, &vertex_attr_array![6 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type BorderedRectRenderer = PrimitiveRenderer<BorderedRect>;
+
+    impl BorderedRectRenderer {
+        pub fn rects_mut(&mut self) -> &mut Vec<BorderedRect> {
+            self.data_mut()
+        }
+
+        pub fn update_rects(&mut self, context: &WGPUContext) {
+            self.update(context);
+        }
+    }
+
+    /// A rect that is placed and rotated about a pivot point instead of its center,
+    /// e.g. for UI anchored at a corner
+    #[derive(Clone, Copy, Pod, Zeroable, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PivotRect {
+        pub color: Vector4<f32>,
+        /// Where the pivot ends up
+        pub position: Vector2<f32>,
+        pub size: Vector2<f32>,
+        /// Rotation about the pivot
+        pub rotation: f32,
+        /// Point within the rect relative to its size, from (0, 0) at the corner with the
+        /// smallest coordinates to (1, 1). The center (0.5, 0.5) places the rect like a
+        /// [CenterRect] and is used when deserializing without a pivot
+        #[cfg_attr(feature = "serde", serde(default = "center_pivot"))]
+        pub pivot: Vector2<f32>,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+    #[cfg(feature = "serde")]
+    fn center_pivot() -> Vector2<f32> {
+        Vector2::new([0.5, 0.5])
+    }
+
+	const PIVOT_RECT_SHADER: &str = include_str!("../shaders/pivot_rect.wgsl");
+
+    impl Primitive for PivotRect {
+        const SHADER: &'static str = PIVOT_RECT_SHADER;
+        const SHADER_PATH: &'static str = "pivot_rect.wgsl";
+        const LABEL: &'static str = "pivot_rects";
+        const ID_ENTRY_POINT: Option<&'static str> = Some("f_main_id");
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            ([f32; 2], Instance, &vertex_attr_array![2 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            ([f32; 2], Instance, &vertex_attr_array![4 => Float32x2]),
+            (u32, Instance, &vertex_attr_array![5 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type PivotRectRenderer = PrimitiveRenderer<PivotRect>;
+
+    impl PivotRectRenderer {
+        pub fn rects_mut(&mut self) -> &mut Vec<PivotRect> {
+            self.data_mut()
+        }
+
+        pub fn update_rects(&mut self, context: &WGPUContext) {
+            self.update(context);
+        }
+    }
+}
+
+mod line {
+    use derive::*;
+
+    use wgpu::*;
+
+    use crate::math::{Vector2, Vector4};
+    use crate::vertex_buffer_layout;
+
+    use super::{Point, Primitive, PrimitiveRenderer};
+
+    use bytemuck::{Pod, Zeroable};
+
+    /// A straight line segment with square ends
+    #[derive(Clone, Copy, Debug, Pod, Zeroable, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Line {
+        pub color: Vector4<f32>,
+        pub start: Vector2<f32>,
+        pub end: Vector2<f32>,
+        pub width: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+	const LINE_SHADER: &str = include_str!("../shaders/line.wgsl");
+
+    impl Primitive for Line {
+        const SHADER: &'static str = LINE_SHADER;
+        const SHADER_PATH: &'static str = "line.wgsl";
+        const LABEL: &'static str = "lines";
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            ([f32; 2], Instance, &vertex_attr_array![2 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            (u32, Instance, &vertex_attr_array![4 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    /// Draws each [Line] as its own instance. Use a [super::PolylineRenderer] for
+    /// paths that need joins between segments
+    pub type LineRenderer = PrimitiveRenderer<Line>;
+
+    impl LineRenderer {
+        pub fn lines_mut(&mut self) -> &mut Vec<Line> {
+            self.data_mut()
+        }
+    }
+
+    /// Connects each point to the next one, e.g. to plot a function. Each line takes
+    /// the color of the point it starts at.
+    ///
+    /// Fewer than two points give no lines
+    pub fn points_to_lines(points: &[Point], width: f32) -> Vec<Line> {
+        points
+            .windows(2)
+            .map(|pair| Line {
+                color: pair[0].color,
+                start: pair[0].position,
+                end: pair[1].position,
+                width,
+                layer: 0,
+            })
+            .collect()
+    }
+
+    /// Like [points_to_lines], but also connects the last point back to the first.
+    /// Two points are connected only once
+    pub fn points_to_closed_lines(points: &[Point], width: f32) -> Vec<Line> {
+        let mut lines = points_to_lines(points, width);
+        if let [first, .., last] = points {
+            if points.len() > 2 {
+                lines.push(Line {
+                    color: last.color,
+                    start: last.position,
+                    end: first.position,
+                    width,
+                    layer: 0,
+                });
+            }
+        }
+        lines
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn points(positions: &[[f32; 2]]) -> Vec<Point> {
+            positions
+                .iter()
+                .map(|&position| Point {
+                    color: Vector4::new([1., 1., 1., 1.]),
+                    position: Vector2::new(position),
+                })
+                .collect()
+        }
+
+        fn ends(lines: &[Line]) -> Vec<([f32; 2], [f32; 2])> {
+            lines.iter().map(|line| (*line.start, *line.end)).collect()
+        }
+
+        #[test]
+        fn open_and_closed() {
+            let square = points(&[[0., 0.], [1., 0.], [1., 1.], [0., 1.]]);
+            let open = points_to_lines(&square, 2.);
+            assert_eq!(
+                ends(&open),
+                [([0., 0.], [1., 0.]), ([1., 0.], [1., 1.]), ([1., 1.], [0., 1.])]
+            );
+            assert!(open.iter().all(|line| line.width == 2.));
+
+            let closed = points_to_closed_lines(&square, 2.);
+            assert_eq!(closed.len(), 4);
+            assert_eq!(ends(&closed[3..]), [([0., 1.], [0., 0.])]);
+        }
+
+        #[test]
+        fn too_few_points() {
+            assert!(points_to_lines(&[], 1.).is_empty());
+            assert!(points_to_closed_lines(&[], 1.).is_empty());
+            let single = points(&[[1., 1.]]);
+            assert!(points_to_lines(&single, 1.).is_empty());
+            assert!(points_to_closed_lines(&single, 1.).is_empty());
+
+            let pair = points(&[[0., 0.], [1., 0.]]);
+            assert_eq!(points_to_closed_lines(&pair, 1.).len(), 1);
+        }
+    }
+}
+
+mod circle {
+    use crate::shader_manager::*;
+    use crate::vertex_buffer_layout;
+    use crate::wgpu_context::{BufferAndData, BufferData, WGPUContext};
+	use crate::math::{Vector2, Vector4};
+
+    use derive::VertexBufferData;
+    use wgpu::*;
+
+
+    use super::{
+        Primitive, PrimitiveRenderer, Render, RenderStats, RendererConstants, VertexBuffers,
+    };
+
+    use bytemuck::{Pod, Zeroable};
+
+    #[derive(Pod, Zeroable, Clone, Copy, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Circle {
+        pub color: Vector4<f32>,
+        pub position: Vector2<f32>,
+        pub radius: f32,
+        /// Width in pixels over which the edge fades out. Only widens the fade, the
+        /// renderer's [super::Uniform::aa_px] is the minimum
+        pub edge_softness: f32,
+        /// 0 fills the circle. Anything larger draws only an outline this wide, in the
+        /// same units as `radius`, just inside the edge. Ignored by
+        /// [super::PolygonCircleRenderer]
+        #[cfg_attr(feature = "serde", serde(default))]
+        pub stroke_width: f32,
+        /// Orientation in radians, like [super::CenterRect::rotation]. Flat fills look the
+        /// same at any rotation, but the shader gets coordinates in the rotated frame of
+        /// the circle for fills that don't
+        #[cfg_attr(feature = "serde", serde(default))]
+        pub rotation: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+    /// An opaque white circle at the origin with a radius of 0 and a 1 pixel soft edge,
+    /// filled and unrotated. Fill in the fields that matter with
+    /// `Circle { radius: 8., ..Default::default() }`
+    impl Default for Circle {
+        fn default() -> Self {
+            Self {
+                color: Vector4::new([1., 1., 1., 1.]),
+                position: Vector2::new([0., 0.]),
+                radius: 0.,
+                edge_softness: 1.,
+                stroke_width: 0.,
+                rotation: 0.,
+                layer: 0,
+            }
+        }
+    }
+
+	const CIRCLE_SHADER: &str = include_str!("../shaders/circle.wgsl");
+
+    impl Primitive for Circle {
+        const SHADER: &'static str = CIRCLE_SHADER;
+        const SHADER_PATH: &'static str = "circle.wgsl";
+        const LABEL: &'static str = "circle";
+        const ID_ENTRY_POINT: Option<&'static str> = Some("f_main_id");
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![2 => Float32]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            (f32, Instance, &vertex_attr_array![4 => Float32]),
+            (f32, Instance, &vertex_attr_array![5 => Float32]),
+            (u32, Instance, &vertex_attr_array![6 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type CircleRenderer = PrimitiveRenderer<Circle>;
+
+    /// A [Circle] with its color packed by [super::pack_color], so the color takes 4
+    /// bytes of the instance buffer instead of 16. Draws with the same shader, which
+    /// gets the color unpacked by the `Unorm8x4` vertex format
+    #[derive(Pod, Zeroable, Clone, Copy, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PackedCircle {
+        pub color: u32,
+        pub position: Vector2<f32>,
+        pub radius: f32,
+        /// See [Circle::edge_softness]
+        pub edge_softness: f32,
+        /// See [Circle::stroke_width]
+        #[cfg_attr(feature = "serde", serde(default))]
+        pub stroke_width: f32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        pub rotation: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+    impl From<Circle> for PackedCircle {
+        fn from(circle: Circle) -> Self {
+            Self {
+                color: super::pack_color(circle.color),
+                position: circle.position,
+                radius: circle.radius,
+                edge_softness: circle.edge_softness,
+                stroke_width: circle.stroke_width,
+                rotation: circle.rotation,
+                layer: circle.layer,
+            }
+        }
+    }
+
+    impl Primitive for PackedCircle {
+        const SHADER: &'static str = CIRCLE_SHADER;
+        const SHADER_PATH: &'static str = "circle.wgsl";
+        const LABEL: &'static str = "circle_packed";
+        const ID_ENTRY_POINT: Option<&'static str> = Some("f_main_id");
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            (u32, Instance, &vertex_attr_array![0 => Unorm8x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![2 => Float32]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            (f32, Instance, &vertex_attr_array![4 => Float32]),
+            (f32, Instance, &vertex_attr_array![5 => Float32]),
+            (u32, Instance, &vertex_attr_array![6 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type PackedCircleRenderer = PrimitiveRenderer<PackedCircle>;
+
+    impl CircleRenderer {
+        pub fn circles_mut(&mut self) -> &mut Vec<Circle> {
+            self.data_mut()
+        }
+
+        pub fn update_circles(&mut self, context: &WGPUContext) {
+            self.update(context);
+        }
+    }
+
+    /// Accumulates circles from multiple sources into a single instance buffer
+    /// and draws all of them with one draw call.
+    ///
+    /// Intended to be cleared and refilled every frame, e.g. by many entities that
+    /// would otherwise each own a [CircleRenderer]. The instance buffers only grow
+    /// and are reused between frames
+    pub struct CircleBatch {
+        circles: Vec<Circle>,
+        buffers: Option<<Vec<Circle> as BufferData>::Buffers>,
+        capacity: usize,
+        instance_count: u32,
+        constants: RendererConstants,
+        transparent: bool,
+    }
+
+    impl CircleBatch {
+        pub fn new(
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            Circle::register(uniform_bind_group_layout, context, shader_manager);
+
+            Self {
+                circles: Vec::new(),
+                buffers: None,
+                capacity: 0,
+                instance_count: 0,
+                constants: RendererConstants::new(context),
+                transparent: false,
+            }
+        }
+
+        /// Sorts the circles back to front on every [Self::upload] when set.
+        ///
+        /// Without a depth buffer, circles are drawn in the order they were pushed, so
+        /// translucent circles from different sources blend depending on which pushed
+        /// first. Sorting draws lower layers first, keeping the push order within a
+        /// layer, so the result only depends on the layers. This costs a sort of all
+        /// circles per upload, which is noticeable with many thousands of circles, so
+        /// leave it off for opaque circles
+        pub fn set_transparent(&mut self, transparent: bool) {
+            self.transparent = transparent;
+        }
+
+        pub fn transparent(&self) -> bool {
+            self.transparent
+        }
+
+        /// Removes all circles. Nothing is drawn until [Self::upload] is called again
+        pub fn clear(&mut self) {
+            self.circles.clear();
+            self.instance_count = 0;
+        }
+
+        pub fn push(&mut self, circle: Circle) {
+            self.circles.push(circle);
+        }
+
+        pub fn extend<I: IntoIterator<Item = Circle>>(&mut self, circles: I) {
+            self.circles.extend(circles);
+        }
+
+        pub fn circles(&self) -> &[Circle] {
+            &self.circles
+        }
+
+        /// Uploads the accumulated circles, growing the instance buffers if needed. They
+        /// are sorted by layer first if the batch is [Self::set_transparent]
+        pub fn upload(&mut self, context: &WGPUContext) {
+            self.instance_count = self.circles.len() as u32;
+            if self.circles.is_empty() {
+                return;
+            }
+            if self.transparent {
+                self.circles.sort_by_key(Circle::layer);
+            }
+            if self.buffers.is_none() || self.circles.len() > self.capacity {
+                // Every field buffer is re-created, padded to the new capacity
+                self.capacity = self.circles.len().next_power_of_two();
+                let mut padded = self.circles.clone();
+                padded.resize(self.capacity, Circle::default());
+                self.buffers = Some(padded.create_buffers(context));
+            }
+            if let Some(buffers) = &mut self.buffers {
+                self.circles.fill_buffers(buffers, context);
+            }
+        }
+    }
+
+    impl Render for CircleBatch {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            if self.buffers.is_none() || self.instance_count == 0 {
+                return;
+            }
+            render_pass.set_pipeline(&shader_manager.get_render_pipeline(Circle::LABEL, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(Circle::LABEL)
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            let Some(buffers) = &self.buffers else {
+                return;
+            };
+            self.constants.bind(render_pass);
+            buffers.set_vertex_buffers(render_pass);
+            render_pass.draw(0..Circle::VERTEX_COUNT, 0..self.instance_count);
+        }
+
+        fn stats(&self) -> RenderStats {
+            match self.buffers {
+                Some(_) if self.instance_count > 0 => {
+                    RenderStats::draw(Circle::VERTEX_COUNT, self.instance_count)
+                }
+                _ => RenderStats::default(),
+            }
+        }
+    }
+
+	const CIRCLE_POLYGON_SHADER: &str = include_str!("../shaders/circle_polygon.wgsl");
+
+    /// Draws circles as polygons with a configurable number of segments instead of with
+    /// a signed distance field.
+    ///
+    /// Fewer segments cost less fill rate than the quad a [CircleRenderer] shades, at the
+    /// price of visible corners. Edges are not antialiased and
+    /// [Circle::edge_softness] is ignored
+    pub struct PolygonCircleRenderer {
+        data: BufferAndData<Vec<Circle>>,
+        dirty: bool,
+        segments: u32,
+        pipeline_label: String,
+        uniform_bind_group_layout: BindGroupLayout,
+    }
+
+    impl PolygonCircleRenderer {
+        pub fn new(
+            data: Vec<Circle>,
+            segments: u32,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            let segments = segments.max(3);
+            Self {
+                data: BufferAndData::new(data, context),
+                dirty: false,
+                segments,
+                pipeline_label: register_polygon_circle(
+                    segments,
+                    uniform_bind_group_layout,
+                    context,
+                    shader_manager,
+                ),
+                uniform_bind_group_layout: uniform_bind_group_layout.clone(),
+            }
+        }
+
+        /// Switches to a pipeline compiled with `segments` (at least 3) triangles per
+        /// circle. Each count is compiled once and kept in the [ShaderManager]
+        pub fn set_segments(
+            &mut self,
+            segments: u32,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            self.segments = segments.max(3);
+            self.pipeline_label = register_polygon_circle(
+                self.segments,
+                &self.uniform_bind_group_layout,
+                context,
+                shader_manager,
+            );
+        }
+
+        pub fn segments(&self) -> u32 {
+            self.segments
+        }
+
+        /// Marks the circles as changed so the next [Self::sync] uploads them
+        pub fn circles_mut(&mut self) -> &mut Vec<Circle> {
+            self.dirty = true;
+            &mut self.data.data
+        }
+
+        /// Applies `f` to every circle and marks the circles as changed
+        pub fn for_each_mut(&mut self, f: impl FnMut(&mut Circle)) {
+            self.circles_mut().iter_mut().for_each(f);
+        }
+
+        /// Applies `f` to the circle at `index` and marks the circles as changed.
+        /// Returns false without changing anything if `index` is out of range
+        pub fn modify(&mut self, index: usize, f: impl FnOnce(&mut Circle)) -> bool {
+            let Some(circle) = self.data.data.get_mut(index) else {
+                return false;
+            };
+            f(circle);
+            self.dirty = true;
+            true
+        }
+
+        /// Uploads the circles only if they were accessed mutably since the last upload
+        pub fn sync(&mut self, context: &WGPUContext) {
+            if self.dirty {
+                self.data.update_buffer(context);
+                self.dirty = false;
+            }
+        }
+    }
+
+    /// Registers the polygon pipeline for `segments` and returns its label
+    fn register_polygon_circle(
+        segments: u32,
+        uniform_bind_group_layout: &BindGroupLayout,
+        context: &WGPUContext,
+        shader_manager: &ShaderManager,
+    ) -> String {
+        let label = format!("circle_polygon_{segments}");
+        let pipeline_layout = context
+            .device()
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let render_pipeline_template = RenderPipelineDescriptorTemplate {
+            label: Some("circle_polygon"),
+            layout: Some(pipeline_layout),
+            vertex: VertexStateTemplate {
+                module_path: "circle_polygon.wgsl",
+                entry_point: None,
+                buffers: Circle::VERTEX_BUFFERS,
+            },
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(FragmentStateTemplate {
+                module_path: "circle_polygon.wgsl",
+                entry_point: None,
+                targets: Box::new([Some(ColorTargetState {
+                    format: context.config().format,
+                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })]),
+            }),
+            multiview: None,
+            cache: None,
+            push_constant_ranges: Box::new([]),
+            constants: [("SEGMENTS".to_string(), segments as f64)].into(),
+        };
+        shader_manager
+            .register_constant_source("circle_polygon.wgsl", CIRCLE_POLYGON_SHADER.into());
+        shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
+        shader_manager.register_render_pipeline(&label, render_pipeline_template);
+        label
+    }
+
+    impl Render for PolygonCircleRenderer {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline(&self.pipeline_label, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(&self.pipeline_label)
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            self.data.buffers.set_vertex_buffers(render_pass);
+            render_pass.draw(0..self.segments * 3, 0..self.data.data.len() as u32);
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(self.segments * 3, self.data.data.len() as u32)
+        }
+    }
+}
+
+mod ring {
+    use crate::vertex_buffer_layout;
+    use crate::wgpu_context::WGPUContext;
+    use derive::VertexBufferData;
+    use wgpu::*;
+
+    use super::{Primitive, PrimitiveRenderer};
+
+    use crate::math::{Vector2, Vector4};
+    use bytemuck::{Pod, Zeroable};
+
+    #[derive(Pod, Zeroable, Clone, Copy, VertexBufferData)]
+    #[repr(C)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Ring {
+        pub color: Vector4<f32>,
+        pub position: Vector2<f32>,
+        pub outer_radius: f32,
+        pub inner_radius: f32,
+        /// Width in pixels over which both edges fade out. Only widens the fade, the
+        /// renderer's [super::Uniform::aa_px] is the minimum
+        pub edge_softness: f32,
+        /// Draw order relative to other instances, see [super::MAX_LAYER]
+        pub layer: u32,
+    }
+
+	const RING_SHADER: &str = include_str!("../shaders/rings.wgsl");
+
+    impl Primitive for Ring {
+        const SHADER: &'static str = RING_SHADER;
+        const SHADER_PATH: &'static str = "rings.wgsl";
+        const LABEL: &'static str = "Ring";
+        const ID_ENTRY_POINT: Option<&'static str> = Some("f_main_id");
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            ([f32; 2], Instance, &vertex_attr_array![1 => Float32x2]),
+            (f32, Instance, &vertex_attr_array![2 => Float32]),
+            (f32, Instance, &vertex_attr_array![3 => Float32]),
+            (f32, Instance, &vertex_attr_array![4 => Float32]),
+            (u32, Instance, &vertex_attr_array![5 => Uint32]),
+        );
+
+        fn layer(&self) -> u32 {
+            self.layer
+        }
+    }
+
+    pub type RingRenderer = PrimitiveRenderer<Ring>;
+
+    impl RingRenderer {
+        pub fn rings_mut(&mut self) -> &mut Vec<Ring> {
+            self.data_mut()
+        }
+
+        pub fn update_rings(&mut self, context: &WGPUContext) {
+            self.update(context);
+        }
+    }
+}
+
+mod particles {
+    use std::mem::offset_of;
+
+    use bytemuck::{Pod, Zeroable};
+    use wgpu::*;
+
+    use crate::math::{Vector2, Vector4};
+    use crate::shader_manager::ShaderManager;
+    use crate::wgpu_context::{WGPUBuffer, WGPUContext};
+
+    use super::{Primitive, Render, RenderStats, RendererConstants};
+
+    /// A particle of a [GpuParticleSystem], laid out as the step shader reads it
+    #[derive(Clone, Copy, Debug, Pod, Zeroable)]
+    #[repr(C)]
+    pub struct Particle {
+        pub color: Vector4<f32>,
+        pub position: Vector2<f32>,
+        /// World units per second
+        pub velocity: Vector2<f32>,
+        /// Added to the velocity every second, e.g. gravity
+        pub acceleration: Vector2<f32>,
+        /// Width and height of the square drawn for the particle
+        pub size: f32,
+        /// Seconds until the particle disappears
+        pub lifetime: f32,
+    }
+
+    const PARTICLE_SHADER: &str = include_str!("../shaders/particles.wgsl");
+    const PARTICLE_STEP_SHADER: &str = include_str!("../shaders/particle_step.wgsl");
+    const WORKGROUP_SIZE: u32 = 64;
+
+    impl Primitive for Particle {
+        const SHADER: &'static str = PARTICLE_SHADER;
+        const SHADER_PATH: &'static str = "particles.wgsl";
+        const LABEL: &'static str = "particles";
+        // Only what the vertex shader needs. The step shader updates the velocity
+        const VERTEX_BUFFERS: &'static [VertexBufferLayout<'static>] = &[VertexBufferLayout {
+            array_stride: size_of::<Particle>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: &[
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: offset_of!(Particle, color) as u64,
+                    shader_location: 0,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: offset_of!(Particle, position) as u64,
+                    shader_location: 1,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: offset_of!(Particle, size) as u64,
+                    shader_location: 2,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: offset_of!(Particle, lifetime) as u64,
+                    shader_location: 3,
+                },
+            ],
+        }];
+
+        fn layer(&self) -> u32 {
+            0
+        }
+    }
+
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    #[repr(C)]
+    struct Step {
+        dt: f32,
+        count: u32,
+    }
+
+    /// Particles that are simulated and drawn entirely on the GPU.
+    ///
+    /// The particles live in a single buffer that a compute shader integrates on every
+    /// [Self::step] and that is drawn directly as instanced quads, so they are never read
+    /// back. The buffer holds a fixed number of particles and emitting more replaces the
+    /// oldest ones. Unlike the render pipeline, the compute shader is not reloaded with
+    /// the [ShaderManager]
+    pub struct GpuParticleSystem {
+        particles: WGPUBuffer,
+        capacity: u32,
+        // Slot the next emitted particle is written to
+        next: u32,
+        step: WGPUBuffer,
+        step_pipeline: ComputePipeline,
+        step_bind_group: BindGroup,
+        constants: RendererConstants,
+    }
+
+    impl GpuParticleSystem {
+        /// Creates a system with room for `capacity` particles, all initially dead
+        pub fn new(
+            capacity: u32,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            Particle::register(uniform_bind_group_layout, context, shader_manager);
+
+            // New buffers are zeroed, which is a particle without lifetime
+            let particles = WGPUBuffer::new_storage_vertex(
+                size_of::<Particle>() as u64 * capacity.max(1) as u64,
+                context,
+            );
+            let step = WGPUBuffer::new_uniform(size_of::<Step>() as u64, context);
+
+            let device = context.device();
+            let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Particle step bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+            let step_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Particle step bind group"),
+                layout: &layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: particles.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: step.as_entire_binding(),
+                    },
+                ],
+            });
+            let module = device.create_shader_module(ShaderModuleDescriptor {
+                label: Some("particle_step.wgsl"),
+                source: ShaderSource::Wgsl(PARTICLE_STEP_SHADER.into()),
+            });
+            let step_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Particle step pipeline"),
+                layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&layout],
+                    push_constant_ranges: &[],
+                })),
+                module: &module,
+                entry_point: Some("c_main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+            Self {
+                particles,
+                capacity,
+                next: 0,
+                step,
+                step_pipeline,
+                step_bind_group,
+                constants: RendererConstants::new(context),
+            }
+        }
+
+        pub fn capacity(&self) -> u32 {
+            self.capacity
+        }
+
+        /// Adds the particles, replacing the oldest ones once the buffer is full. Only
+        /// the last [Self::capacity] particles are kept if more are emitted at once
+        pub fn emit(&mut self, particles: &[Particle], context: &WGPUContext) {
+            let capacity = self.capacity as usize;
+            if capacity == 0 {
+                return;
+            }
+            let skipped = particles.len().saturating_sub(capacity);
+            let particles = &particles[skipped..];
+            let next = (self.next as usize + skipped) % capacity;
+
+            // Up to the end of the buffer, then wrapping around to the start
+            let (end, start) = particles.split_at(particles.len().min(capacity - next));
+            let offset = (next * size_of::<Particle>()) as u64;
+            context
+                .queue()
+                .write_buffer(&self.particles, offset, bytemuck::cast_slice(end));
+            if !start.is_empty() {
+                context
+                    .queue()
+                    .write_buffer(&self.particles, 0, bytemuck::cast_slice(start));
+            }
+            self.next = ((next + particles.len()) % capacity) as u32;
+        }
+
+        /// Advances every living particle by `dt` seconds on the GPU
+        pub fn step(&mut self, dt: f32, context: &WGPUContext) {
+            if self.capacity == 0 {
+                return;
+            }
+            let step = Step {
+                dt,
+                count: self.capacity,
+            };
+            self.step.write_data(bytemuck::bytes_of(&step), context);
+
+            let mut encoder = context.get_encoder();
+            {
+                let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("Particle step"),
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(&self.step_pipeline);
+                pass.set_bind_group(0, &self.step_bind_group, &[]);
+                pass.dispatch_workgroups(self.capacity.div_ceil(WORKGROUP_SIZE), 1, 1);
+            }
+            context.queue().submit([encoder.finish()]);
+        }
+    }
+
+    impl Render for GpuParticleSystem {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass.set_pipeline(&shader_manager.get_render_pipeline(Particle::LABEL, context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some(Particle::LABEL)
+        }
+
+        // Every slot is drawn. Dead particles are collapsed by the vertex shader
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            self.constants.bind(render_pass);
+            render_pass.set_vertex_buffer(0, self.particles.slice(..));
+            render_pass.draw(0..Particle::VERTEX_COUNT, 0..self.capacity);
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(Particle::VERTEX_COUNT, self.capacity)
+        }
+    }
+}
+
+mod canvas {
+    use wgpu::*;
+
+    use crate::math::{Vector2, Vector4};
+    use crate::shader_manager::ShaderManager;
+    use crate::wgpu_context::WGPUContext;
+
+    use super::{
+        CenterRect, Circle, Line, Render, RenderStats, Renderer2D, SlicePrimitiveRenderer,
+    };
+
+    /// Any shape a [Canvas] can draw
+    #[derive(Clone, Copy)]
+    pub enum Shape {
+        Rect(CenterRect),
+        Circle(Circle),
+        Line(Line),
+    }
+
+    impl From<CenterRect> for Shape {
+        fn from(rect: CenterRect) -> Self {
+            Self::Rect(rect)
+        }
+    }
+
+    impl From<Circle> for Shape {
+        fn from(circle: Circle) -> Self {
+            Self::Circle(circle)
+        }
+    }
+
+    impl From<Line> for Shape {
+        fn from(line: Line) -> Self {
+            Self::Line(line)
+        }
+    }
+
+    /// Immediate mode drawing for quick prototypes.
+    ///
+    /// Shapes are queued with [Self::circle], [Self::rect] and [Self::line] every frame
+    /// and drawn by [Self::present], which starts the queues over. Each kind of shape
+    /// is one batch, drawn rects first, then circles, then lines. Within a batch later
+    /// shapes are drawn on top. The buffers only grow, so queueing a similar number of
+    /// shapes every frame allocates nothing after the first few frames
+    pub struct Canvas {
+        rects: Vec<CenterRect>,
+        circles: Vec<Circle>,
+        lines: Vec<Line>,
+        rect_renderer: SlicePrimitiveRenderer<CenterRect>,
+        circle_renderer: SlicePrimitiveRenderer<Circle>,
+        line_renderer: SlicePrimitiveRenderer<Line>,
+    }
+
+    impl Canvas {
+        pub fn new(
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            Self {
+                rects: Vec::new(),
+                circles: Vec::new(),
+                lines: Vec::new(),
+                rect_renderer: SlicePrimitiveRenderer::from_slice(
+                    &[],
+                    uniform_bind_group_layout,
+                    context,
+                    shader_manager,
+                ),
+                circle_renderer: SlicePrimitiveRenderer::from_slice(
+                    &[],
+                    uniform_bind_group_layout,
+                    context,
+                    shader_manager,
+                ),
+                line_renderer: SlicePrimitiveRenderer::from_slice(
+                    &[],
+                    uniform_bind_group_layout,
+                    context,
+                    shader_manager,
+                ),
+            }
+        }
+
+        pub fn circle(&mut self, position: Vector2<f32>, radius: f32, color: Vector4<f32>) {
+            self.circles.push(Circle {
+                color,
+                position,
+                radius,
+                ..Default::default()
+            });
+        }
+
+        pub fn rect(&mut self, center: Vector2<f32>, size: Vector2<f32>, color: Vector4<f32>) {
+            self.rects.push(CenterRect {
+                color,
+                center,
+                size,
+                rotation: 0.,
+                layer: 0,
+            });
+        }
+
+        pub fn line(
+            &mut self,
+            start: Vector2<f32>,
+            end: Vector2<f32>,
+            width: f32,
+            color: Vector4<f32>,
+        ) {
+            self.lines.push(Line {
+                color,
+                start,
+                end,
+                width,
+                layer: 0,
+            });
+        }
+
+        /// Queues a fully described shape, e.g. a rotated rect or a circle outline
+        pub fn shape(&mut self, shape: impl Into<Shape>) {
+            match shape.into() {
+                Shape::Rect(rect) => self.rects.push(rect),
+                Shape::Circle(circle) => self.circles.push(circle),
+                Shape::Line(line) => self.lines.push(line),
+            }
+        }
+
+        /// Uploads the queued shapes and starts the queues over. The canvas then draws
+        /// them like any other [Render] item until the next flush. Use this instead of
+        /// [Self::present] to draw the canvas along with other items
+        pub fn flush(&mut self, context: &WGPUContext) {
+            self.rect_renderer.update(&self.rects, context);
+            self.circle_renderer.update(&self.circles, context);
+            self.line_renderer.update(&self.lines, context);
+            self.rects.clear();
+            self.circles.clear();
+            self.lines.clear();
+        }
+
+        /// Flushes the canvas and renders a frame with only the canvas in it
+        pub fn present(
+            &mut self,
+            renderer: &mut Renderer2D,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError> {
+            self.flush(context);
+            renderer.render([&*self], context, shader_manager)
+        }
+
+        // Empty batches are skipped since they have no buffer contents to bind
+        fn batches(&self) -> impl Iterator<Item = &dyn Render> {
+            [
+                (&self.rect_renderer as &dyn Render, self.rect_renderer.instance_count()),
+                (&self.circle_renderer, self.circle_renderer.instance_count()),
+                (&self.line_renderer, self.line_renderer.instance_count()),
+            ]
+            .into_iter()
+            .filter(|&(_, instances)| instances > 0)
+            .map(|(batch, _)| batch)
+        }
+    }
+
+    impl Render for Canvas {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            for batch in self.batches() {
+                batch.render(render_pass, context, shader_manager);
+            }
+        }
+
+        fn stats(&self) -> RenderStats {
+            self.batches().map(Render::stats).sum()
+        }
+    }
+}
+
+mod texture {
+    use super::{surface_color_target, Render, RenderStats};
+    use crate::math::{Vector2, Vector4};
+    use crate::rendering::CenterRect;
+    use crate::shader_manager::{
+        FragmentStateTemplate, RenderPipelineDescriptorTemplate, ShaderManager, VertexStateTemplate,
+    };
+    use crate::wgpu_context::{BufferAndData, WGPUContext};
+    use bytemuck::{Pod, Zeroable};
+    use derive::UniformBufferData;
+    use wgpu::*;
+
+    // struct TextureData {
+    // 	data: Vec<[f32; 4]>,
+    // 	rows: usize,
+    // 	columns: usize,
+    // }
+
+    // impl TextureData {
+    // 	pub fn new (data: Vec<[f32;4]>, rows: usize, columns: usize) -> Self {
+    // 		assert!(rows * columns == data.len());
+    // 		Self {
+    // 			data,
+    // 			rows,
+    // 			columns,
+    // 		}
+    // 	}
+
+    // 	pub fn generate_next_mip(&self) -> Option<Self> {
+    // 		todo!();
+    // 		// if self.rows == 1 && self.columns == 1 {
+    // 		// 	return None;
+    // 		// }
+    // 		// let mip_rows = std::cmp::max(self.rows / 2, 1);
+    // 		// let mip_columns = std::cmp::max(self.columns / 2, 1);
+    // 		// let mut output = Vec::new();
+
+    // 		// for y in 0..mip_rows {
+    // 		// 	for x in 0..mip_columns {
+    // 		// 		// uv of next mip
+    // 		// 		let u = (x as f32 + 0.5) / mip_columns;
+    // 		// 		let v = (y as f32 + 0.5) / mip_rows;
+
+    // 		// 		let au = (u * self.rows - 0.5);
+    // 		// 		let av = (v * self.columns - 0.5);
+
+    // 		// 		// compute the src top left texel coord (not texcoord)
+    // 		// 		let tx = au;
+    // 		// 		let ty = av;
+
+    // 		// 		// compute the mix amounts between pixels
+    // 		// 		let t1 = au % 1;
+    // 		// 		let t2 = av % 1;
+    // 		// 	}
+    // 		// }
+    // 	}
+    // }
+
+    // impl std::ops::Index<(f32, f32)> for TextureData {
+    // 	type Output = [[f32;4]];
+    // 	fn index (&self, index: usize) -> &Self::Output {
+    // 		&self.data[(index * self.columns)..((index + 1) * self.columns)]
+    // 	}
+    // }
+
+    // impl std::ops::Index<usize> for TextureData {
+    // 	type Output = [[f32;4]];
+    // 	fn index (&self, index: usize) -> &Self::Output {
+    // 		&self.data[(index * self.columns)..((index + 1) * self.columns)]
+    // 	}
+    // }
+
+	const TEXTURE_SHADER: &str = include_str!("../shaders/texture.wgsl");
+	const TILED_TEXTURE_SHADER: &str = include_str!("../shaders/tiled_texture.wgsl");
+
+    /// Errors that can occur while creating a texture from image data
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum TextureError {
+        /// The image is larger than [Limits::max_texture_dimension_2d] along an axis
+        TooLarge { requested: [u32; 2], max: u32 },
+    }
+
+    impl std::fmt::Display for TextureError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::TooLarge {
+                    requested: [width, height],
+                    max,
+                } => write!(
+                    f,
+                    "Texture of {width}x{height} texels exceeds the maximum size of {max} \
+                     texels per side supported by the device"
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for TextureError {}
+
+    /// Checks that a 2D texture of `size` texels fits in the limits of the device.
+    ///
+    /// wgpu reports oversized textures as a validation error, which goes to the
+    /// uncaptured error handler and ends the process, so check image sizes first
+    pub fn check_texture_size(size: [u32; 2], context: &WGPUContext) -> Result<(), TextureError> {
+        let max = context.limits().max_texture_dimension_2d;
+        if size[0] > max || size[1] > max {
+            return Err(TextureError::TooLarge {
+                requested: size,
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Multiplies the RGB channels of each RGBA8 texel by its alpha.
+    ///
+    /// Every pipeline in this crate blends with premultiplied alpha
+    /// (`src_factor: One`). Image files usually store straight alpha, which
+    /// renders with dark fringes around transparent edges unless converted first
+    pub fn premultiply_alpha(texels: &mut [[u8; 4]]) {
+        for texel in texels {
+            let alpha = texel[3] as u16;
+            for channel in &mut texel[..3] {
+                *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+            }
+        }
+    }
+
+    /// Renders a textured quad.
+    ///
+    /// Texture data is expected to use premultiplied alpha. Pass
+    /// `premultiply_alpha_on_upload` to [Self::from_texels] when the source data uses
+    /// straight alpha (as most PNGs do) to convert it with [premultiply_alpha]
+    pub struct TextureRenderer {
+        rect: BufferAndData<CenterRect>,
+        dirty: bool,
+        // Renderers with the same blending share a pipeline
+        pipeline_label: String,
+        #[allow(dead_code)]
+        texture: Texture,
+        #[allow(dead_code)]
+        view: TextureView,
+        #[allow(dead_code)]
+        sampler: Sampler,
+        bind_group: BindGroup,
+    }
+
+    impl TextureRenderer {
+        /// Draws a small opaque test image. See [Self::from_texels] for `blend` and
+        /// `write_mask`
+        pub fn new(
+            uniform_bind_group_layout: &BindGroupLayout,
+            blend: Option<BlendState>,
+            write_mask: ColorWrites,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Self {
+            let x: [u8; 4] = [255, 0, 0, 255];
+            let y: [u8; 4] = [255, 255, 0, 255];
+            let b: [u8; 4] = [0, 0, 255, 255];
+            let texture_data = [
+                [b, x, x, x, x],
+                [x, y, y, y, x],
+                [x, y, x, x, x],
+                [x, y, y, x, x],
+                [x, y, x, x, x],
+                [x, y, x, x, x],
+                [x, x, x, x, x],
+            ];
+            let mut renderer = Self::from_texels(
+                texture_data.as_flattened(),
+                5,
+                false,
+                uniform_bind_group_layout,
+                blend,
+                write_mask,
+                context,
+                shader_manager,
+            )
+            .expect("The test image fits any device");
+            renderer.rect.data.center = Vector2::new([4.5, 3.5]);
+            renderer.update_rect(context);
+            renderer
+        }
+
+        /// `texels` are the rows of an image `width` texels wide, from the top. Set
+        /// `premultiply_alpha_on_upload` if they use straight alpha, see
+        /// [premultiply_alpha]. The quad starts out centered on the origin, one world
+        /// unit wide and high, and is moved with [Self::rect_mut].
+        ///
+        /// `blend` and `write_mask` control how the texture is composited. Use
+        /// [BlendState::PREMULTIPLIED_ALPHA_BLENDING] and [ColorWrites::ALL] to blend
+        /// like every other renderer, `None` to overwrite the target, or a narrower
+        /// mask to write e.g. only the alpha channel of a mask.
+        ///
+        /// Fails if the image is too large for the device, see [check_texture_size]
+        ///
+        /// # Panics
+        /// If `width` is 0 or `texels` is empty or doesn't make whole rows of `width`
+        #[allow(clippy::too_many_arguments)]
+        pub fn from_texels(
+            texels: &[[u8; 4]],
+            width: u32,
+            premultiply_alpha_on_upload: bool,
+            uniform_bind_group_layout: &BindGroupLayout,
+            blend: Option<BlendState>,
+            write_mask: ColorWrites,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<Self, TextureError> {
+            assert!(
+                width > 0 && !texels.is_empty() && texels.len().is_multiple_of(width as usize),
+                "{} texels don't make rows of {width}",
+                texels.len()
+            );
+            let height = texels.len() as u32 / width;
+            check_texture_size([width, height], context)?;
+
+            let rect = BufferAndData::new(
+                CenterRect {
+                    color: Vector4::new([0., 0., 0., 1.]),
+                    center: Vector2::new([0., 0.]),
+                    size: Vector2::new([1.0, 1.0]),
+                    rotation: 0.,
+                    layer: 0,
+                },
+                context,
+            );
+
+            let mut premultiplied = Vec::new();
+            let texels = if premultiply_alpha_on_upload {
+                premultiplied.extend_from_slice(texels);
+                premultiply_alpha(&mut premultiplied);
+                &premultiplied[..]
+            } else {
+                texels
+            };
+
+            // Create Texture
+            let texture = context.device().create_texture(&TextureDescriptor {
+                label: Some("Texture"),
+                size: Extent3d {
+                    height,
+                    width,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: context.image_texture_format(),
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[context.image_texture_format()],
+            });
+
+            let texture_view = texture.create_view(&TextureViewDescriptor {
+                label: Some("Texture View"),
+                format: None,
+                dimension: None,
+                usage: None,
+                aspect: TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: None,
+                base_array_layer: 0,
+                array_layer_count: None,
+            });
+
+            // Copy data to texture
+            context.queue().write_texture(
+                TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: Origin3d { x: 0, y: 0, z: 0 },
+                    aspect: TextureAspect::All,
+                },
+                bytemuck::cast_slice(texels),
+                TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: Some(height),
+                },
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            // Create Sampler
+            let sampler = context.device().create_sampler(&SamplerDescriptor {
+                label: Some("Test Sampler"),
+                address_mode_u: AddressMode::Repeat,
+                address_mode_v: AddressMode::Repeat,
+                address_mode_w: AddressMode::Repeat,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                lod_min_clamp: 0.,
+                lod_max_clamp: 0.,
+                compare: None,
+                anisotropy_clamp: 1,
+                border_color: None,
+            });
+
+            let bind_group_layout =
+                context
+                    .device()
+                    .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                        label: Some("Texture bind group layout"),
+                        entries: &[
+                            BindGroupLayoutEntry {
+                                binding: 0,
+                                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                                ty: BindingType::Buffer {
+                                    ty: BufferBindingType::Uniform,
+                                    has_dynamic_offset: false,
+                                    min_binding_size: None,
+                                },
+                                count: None,
+                            },
+                            BindGroupLayoutEntry {
+                                binding: 1,
+                                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                                ty: BindingType::Texture {
+                                    sample_type: TextureSampleType::Float { filterable: true },
+                                    view_dimension: TextureViewDimension::D2,
+                                    multisampled: false,
+                                },
+                                count: None,
+                            },
+                            BindGroupLayoutEntry {
+                                binding: 2,
+                                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                                count: None,
+                            },
+                        ],
+                    });
+
+            let pipeline_layout =
+                context
+                    .device()
+                    .create_pipeline_layout(&PipelineLayoutDescriptor {
+                        label: Some("Texture pipeline layout"),
+                        bind_group_layouts: &[uniform_bind_group_layout, &bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+
+            let render_pipeline_template = RenderPipelineDescriptorTemplate {
+                label: Some("Texture quad Pipeline"),
+                layout: Some(pipeline_layout),
+                vertex: VertexStateTemplate {
+                    module_path: "texture.wgsl",
+                    entry_point: None,
+                    buffers: &[],
+                },
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: Default::default(),
+                fragment: Some(FragmentStateTemplate {
+                    module_path: "texture.wgsl",
+                    entry_point: None,
                     targets: Box::new([Some(ColorTargetState {
                         format: context.config().format,
-                        blend: Some(BlendState {
-                            color: BlendComponent {
-                                src_factor: BlendFactor::One,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
+                        blend,
+                        write_mask,
+                    })]),
+                }),
+                multiview: None,
+                cache: None,
+                push_constant_ranges: Box::new([]),
+                constants: Default::default(),
+            };
+
+            shader_manager.register_constant_source("texture.wgsl", TEXTURE_SHADER.into());
+            shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
+            let pipeline_label = if (blend, write_mask)
+                == (Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING), ColorWrites::ALL)
+            {
+                "texture".to_string()
+            } else {
+                format!("texture {blend:?} {write_mask:?}")
+            };
+            shader_manager.register_render_pipeline(&pipeline_label, render_pipeline_template);
+
+            let bind_group = context.device().create_bind_group(&BindGroupDescriptor {
+                label: Some("Texture bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: rect.buffers.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+
+            Ok(Self {
+                rect,
+                dirty: false,
+                pipeline_label,
+                texture,
+                view: texture_view,
+                sampler,
+                bind_group,
+            })
+        }
+
+        /// Marks the rect as changed so the next [Self::sync] uploads it
+        pub fn rect_mut(&mut self) -> &mut CenterRect {
+            self.dirty = true;
+            &mut self.rect.data
+        }
+
+        /// Uploads the rect regardless of whether it changed
+        pub fn update_rect(&mut self, context: &WGPUContext) {
+            self.rect.update_buffer(context);
+            self.dirty = false;
+        }
+
+        /// Uploads the rect only if it was accessed mutably since the last upload
+        pub fn sync(&mut self, context: &WGPUContext) {
+            if self.dirty {
+                self.update_rect(context);
+            }
+        }
+    }
+
+    impl Render for TextureRenderer {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline(&self.pipeline_label, context));
+            render_pass.set_bind_group(1, &self.bind_group, &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(4, 1)
+        }
+    }
+
+    #[derive(Clone, Copy, Pod, Zeroable, UniformBufferData)]
+    #[repr(C)]
+    struct Tiling {
+        tile_size: Vector2<f32>,
+        scroll_speed: Vector2<f32>,
+    }
+
+    /// Fills the screen with a texture repeated across world space, e.g. for an endless
+    /// scrolling background.
+    ///
+    /// The texture is placed relative to the `view_port_origin` of the uniform it is
+    /// drawn with, so it needs no updates when the camera pans. Layers with a
+    /// [Self::set_scroll_speed] below 1 move slower than the world, for parallax.
+    /// Render it before the rest of the scene, since it covers everything. Texels are
+    /// expected to use premultiplied alpha, see [premultiply_alpha]
+    pub struct TiledTextureRenderer {
+        tiling: BufferAndData<Tiling>,
+        #[allow(dead_code)]
+        texture: Texture,
+        bind_group: BindGroup,
+    }
+
+    impl TiledTextureRenderer {
+        /// `texels` are the rows of an image `width` texels wide, from the top. One copy
+        /// of the image covers `tile_size` world units.
+        ///
+        /// Fails if the image is too large for the device, see [check_texture_size]
+        ///
+        /// # Panics
+        /// If `width` is 0 or `texels` is empty or doesn't make whole rows of `width`
+        pub fn new(
+            texels: &[[u8; 4]],
+            width: u32,
+            tile_size: Vector2<f32>,
+            uniform_bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<Self, TextureError> {
+            assert!(
+                width > 0 && !texels.is_empty() && texels.len().is_multiple_of(width as usize),
+                "{} texels don't make rows of {width}",
+                texels.len()
+            );
+            let size = Extent3d {
+                width,
+                height: texels.len() as u32 / width,
+                depth_or_array_layers: 1,
+            };
+            check_texture_size([size.width, size.height], context)?;
+            let texture = context.device().create_texture(&TextureDescriptor {
+                label: Some("Tiled texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: context.image_texture_format(),
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            context.queue().write_texture(
+                texture.as_image_copy(),
+                bytemuck::cast_slice(texels),
+                TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: None,
+                },
+                size,
+            );
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            // Repeating is what tiles the texture
+            let sampler = context.device().create_sampler(&SamplerDescriptor {
+                label: Some("Tiled texture sampler"),
+                address_mode_u: AddressMode::Repeat,
+                address_mode_v: AddressMode::Repeat,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            });
+
+            let tiling = BufferAndData::new(
+                Tiling {
+                    tile_size,
+                    scroll_speed: Vector2::new([1., 1.]),
+                },
+                context,
+            );
+
+            let bind_group_layout = Self::bind_group_layout(context);
+            Self::register(uniform_bind_group_layout, &bind_group_layout, context, shader_manager);
+            let bind_group = context.device().create_bind_group(&BindGroupDescriptor {
+                label: Some("Tiled texture bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: tiling.buffers.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+
+            Ok(Self {
+                tiling,
+                texture,
+                bind_group,
+            })
+        }
+
+        pub fn tile_size(&self) -> Vector2<f32> {
+            self.tiling.data.tile_size
+        }
+
+        pub fn set_tile_size(&mut self, tile_size: Vector2<f32>, context: &WGPUContext) {
+            self.tiling.data.tile_size = tile_size;
+            self.tiling.update_buffer(context);
+        }
+
+        pub fn scroll_speed(&self) -> Vector2<f32> {
+            self.tiling.data.scroll_speed
+        }
+
+        /// How far the texture moves when the camera pans, relative to the world, per
+        /// axis. 1 (the default) moves with the world, 0 stays fixed on the screen and
+        /// values in between suit distant parallax layers
+        pub fn set_scroll_speed(&mut self, scroll_speed: Vector2<f32>, context: &WGPUContext) {
+            self.tiling.data.scroll_speed = scroll_speed;
+            self.tiling.update_buffer(context);
+        }
+
+        fn bind_group_layout(context: &WGPUContext) -> BindGroupLayout {
+            context
+                .device()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("Tiled texture bind group layout"),
+                    entries: &[
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
                             },
-                            alpha: BlendComponent {
-                                src_factor: BlendFactor::One,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Texture {
+                                sample_type: TextureSampleType::Float { filterable: true },
+                                view_dimension: TextureViewDimension::D2,
+                                multisampled: false,
                             },
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })]),
-                }),
-                multiview: None,
-                cache: None,
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                })
+        }
+
+        fn register(
+            uniform_bind_group_layout: &BindGroupLayout,
+            bind_group_layout: &BindGroupLayout,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            shader_manager
+                .register_constant_source("tiled_texture.wgsl", TILED_TEXTURE_SHADER.into());
+            shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
+            let pipeline_layout =
+                context
+                    .device()
+                    .create_pipeline_layout(&PipelineLayoutDescriptor {
+                        label: Some("Tiled texture pipeline layout"),
+                        bind_group_layouts: &[uniform_bind_group_layout, bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+            shader_manager.register_render_pipeline(
+                "tiled_texture",
+                RenderPipelineDescriptorTemplate {
+                    label: Some("tiled_texture"),
+                    layout: Some(pipeline_layout),
+                    vertex: VertexStateTemplate {
+                        module_path: "tiled_texture.wgsl",
+                        entry_point: None,
+                        buffers: &[],
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    fragment: Some(FragmentStateTemplate {
+                        module_path: "tiled_texture.wgsl",
+                        entry_point: None,
+                        targets: Box::new([Some(surface_color_target(context))]),
+                    }),
+                    multiview: None,
+                    cache: None,
+                    push_constant_ranges: Box::new([]),
+                    constants: Default::default(),
+                },
+            );
+        }
+    }
+
+    impl Render for TiledTextureRenderer {
+        fn render(
+            &self,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) {
+            render_pass
+                .set_pipeline(&shader_manager.get_render_pipeline("tiled_texture", context));
+            self.draw(render_pass, context, shader_manager);
+        }
+
+        fn pipeline_label(&self) -> Option<&str> {
+            Some("tiled_texture")
+        }
+
+        fn draw(&self, render_pass: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+            render_pass.set_bind_group(1, &self.bind_group, &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+
+        fn stats(&self) -> RenderStats {
+            RenderStats::draw(4, 1)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn premultiplies_alpha() {
+            let mut texels = [[255, 128, 0, 128], [10, 20, 30, 255], [200, 100, 50, 0]];
+            premultiply_alpha(&mut texels);
+            assert_eq!(texels, [[128, 64, 0, 128], [10, 20, 30, 255], [0, 0, 0, 0]]);
+        }
+    }
+}
+
+use bytemuck::{Pod, Zeroable};
+use derive::UniformBufferData;
+use crate::math::Vector2;
+/// Describes how world space maps to the screen.
+///
+/// World coordinates, including every position and size given to the renderers, are in
+/// logical pixels. A world unit covers `scale_factor` physical pixels when `screen_size`
+/// matches the size of the surface
+#[derive(Pod, Zeroable, Clone, Copy, UniformBufferData)]
+#[repr(C)]
+pub struct Uniform {
+    /// Size of the surface in physical pixels
+    pub screen_size: Vector2<f32>,
+    /// World position shown at the origin of the [CoordinateSystem], e.g. the top left
+    /// of the screen for [CoordinateSystem::TopLeftYDown]
+	pub view_port_origin: Vector2<f32>,
+    /// Physical pixels per logical pixel, as reported by the windowing system
+    pub scale_factor: f32,
+    /// A [CoordinateSystem] as its `u32` value. Set with
+    /// [Renderer2D::set_coordinate_system]
+    pub coordinate_system: u32,
+    /// Width in physical pixels over which the edges of shapes fade out. 1 gives smooth
+    /// edges at any zoom, 0 hard edges. Set with [Renderer2D::set_antialias_pixels]
+    pub aa_px: f32,
+    /// Seconds since the renderer was created or [Renderer2D::reset_time] was called,
+    /// written at the start of every frame for animated shaders. Uniform overrides get
+    /// the same time, whatever they were created with.
+    ///
+    /// This never wraps, but an `f32` loses precision as it grows: steps are about
+    /// 1 ms after two hours and 8 ms after a day, which makes fast animations stutter.
+    /// Long running applications can reset it, e.g. at a scene change
+    pub time: f32,
+}
+
+impl Uniform {
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        match self.coordinate_system {
+            1 => CoordinateSystem::CenterYUp,
+            2 => CoordinateSystem::BottomLeftYUp,
+            _ => CoordinateSystem::TopLeftYDown,
+        }
+    }
+
+    /// Corner of the visible region of world space with the smallest coordinates, and the
+    /// size of the region
+    pub fn visible_region(&self) -> (Vector2<f32>, Vector2<f32>) {
+        let size = self.screen_size / self.scale_factor;
+        let min = match self.coordinate_system() {
+            CoordinateSystem::TopLeftYDown | CoordinateSystem::BottomLeftYUp => {
+                self.view_port_origin
+            }
+            CoordinateSystem::CenterYUp => self.view_port_origin - size / 2.,
+        };
+        (min, size)
+    }
+
+    /// Converts a position in physical pixels from the top left of the surface, like a
+    /// cursor position, to world space. The inverse of [Self::world_to_screen]
+    pub fn screen_to_world(&self, screen: Vector2<f32>) -> Vector2<f32> {
+        let [x, y] = *screen;
+        let [width, height] = *self.screen_size;
+        let offset = match self.coordinate_system() {
+            CoordinateSystem::TopLeftYDown => Vector2::new([x, y]),
+            CoordinateSystem::CenterYUp => Vector2::new([x - width / 2., height / 2. - y]),
+            CoordinateSystem::BottomLeftYUp => Vector2::new([x, height - y]),
+        };
+        offset / self.scale_factor + self.view_port_origin
+    }
+
+    /// Converts a world position to physical pixels from the top left of the surface
+    pub fn world_to_screen(&self, world: Vector2<f32>) -> Vector2<f32> {
+        let [x, y] = *((world - self.view_port_origin) * self.scale_factor);
+        let [width, height] = *self.screen_size;
+        match self.coordinate_system() {
+            CoordinateSystem::TopLeftYDown => Vector2::new([x, y]),
+            CoordinateSystem::CenterYUp => Vector2::new([x + width / 2., height / 2. - y]),
+            CoordinateSystem::BottomLeftYUp => Vector2::new([x, height - y]),
+        }
+    }
+}
+
+/// Where the world origin is placed on the screen and which way y points
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CoordinateSystem {
+    /// The origin is at the top left and y points down, matching window and cursor
+    /// coordinates
+    #[default]
+    TopLeftYDown = 0,
+    /// The origin is at the center of the screen and y points up
+    CenterYUp = 1,
+    /// The origin is at the bottom left and y points up
+    BottomLeftYUp = 2,
+}
+
+pub use canvas::*;
+pub use circle::*;
+pub use color::*;
+pub use debug_overlay::*;
+pub use draw_constants::*;
+pub use line::*;
+pub use particles::*;
+pub use picking::*;
+pub use point::*;
+pub use polyline::*;
+pub use primitive::*;
+pub use rect::*;
+pub use ring::*;
+pub use texture::*;
+pub use triangle::*;
+
+#[cfg(test)]
+mod tests;
+
+#[macro_export]
+macro_rules! vertex_buffer_layout {
+	($(($stridetype: ty, $mode: ident, $attributes: expr)),+ $(,)?) => {
+		[
+		$(::wgpu::VertexBufferLayout {
+			array_stride: ::std::mem::size_of::<$stridetype>() as u64,
+			step_mode: ::wgpu::VertexStepMode::$mode,
+			attributes: $attributes,
+		},)+
+		]
+	}
+}
+
+/// A sub-rectangle of the window in physical pixels, with the origin at the top left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// A viewport covering the entire surface
+    pub fn full(context: &WGPUContext) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: context.config().width,
+            height: context.config().height,
+        }
+    }
+
+    /// Sets the viewport and scissor rect on the render pass.
+    ///
+    /// The scissor rect is clamped to the surface since wgpu rejects scissor
+    /// rects that extend past the render target
+    fn apply(&self, render_pass: &mut RenderPass, context: &WGPUContext) {
+        render_pass.set_viewport(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+            0.,
+            1.,
+        );
+        self.apply_scissor(render_pass, context);
+    }
+
+    /// Sets only the scissor rect, clamped to the surface
+    fn apply_scissor(&self, render_pass: &mut RenderPass, context: &WGPUContext) {
+        let x = self.x.min(context.config().width);
+        let y = self.y.min(context.config().height);
+        render_pass.set_scissor_rect(
+            x,
+            y,
+            self.width.min(context.config().width - x),
+            self.height.min(context.config().height - y),
+        );
+    }
+
+    /// The region covered by both rects. Empty (zero sized) if they don't overlap
+    pub fn intersection(&self, other: &Self) -> Self {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Self {
+            x,
+            y,
+            width: right.saturating_sub(x),
+            height: bottom.saturating_sub(y),
+        }
+    }
+}
+
+/// Nested clip rects in physical pixels. Each pushed rect is intersected with the one
+/// below it, so children never draw outside their parents
+#[derive(Clone, Debug, Default)]
+pub struct ClipStack {
+    rects: Vec<Viewport>,
+}
+
+impl ClipStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, rect: Viewport) {
+        let rect = match self.current() {
+            Some(current) => current.intersection(&rect),
+            None => rect,
+        };
+        self.rects.push(rect);
+    }
+
+    /// Removes the most recently pushed rect and returns it as it was clipped
+    pub fn pop(&mut self) -> Option<Viewport> {
+        self.rects.pop()
+    }
+
+    /// The region drawing is currently limited to. [None] when nothing is pushed
+    pub fn current(&self) -> Option<Viewport> {
+        self.rects.last().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+}
+
+pub use renderer::*;
+mod renderer {
+    use super::*;
+    use crate::shader_manager::{ShaderError, ShaderManager};
+    use crate::timer::Timer;
+    use crate::wgpu_context::{BufferAndData, FrameTexture, WGPUContext};
+
+    use smallvec::SmallVec;
+    use std::ops::Range;
+    use std::sync::mpsc::{channel, Receiver, TryRecvError};
+    use wgpu::*;
+
+    /// Render calls with up to this many items keep them on the stack. Documented on
+    /// [Renderer2D]
+    const INLINE_ITEMS: usize = 32;
+    type Items<R> = SmallVec<[R; INLINE_ITEMS]>;
+
+    /// Renders items implementing [Render] to the window.
+    ///
+    /// Ordering and batching the items of a render call reuses a buffer owned by the
+    /// renderer, so once it has grown to the largest call the renderer itself doesn't
+    /// allocate per frame, as long as calls stay within 32 items. wgpu may still
+    /// allocate when recording and submitting commands
+    pub struct Renderer2D {
+        uniform: BufferAndData<Uniform>,
+        uniform_bind_group: BindGroup,
+        uniform_bind_group_layout: BindGroupLayout,
+        // Uniforms used by viewports that override the main one. Kept between frames so
+        // that rendering the same views every frame doesn't allocate
+        uniform_pool: Vec<(BufferAndData<Uniform>, BindGroup)>,
+        clip_stack: ClipStack,
+        gpu_timer: Option<GpuTimer>,
+        last_stats: RenderStats,
+        // Draw order of the items of the current render call, see [DrawOrder]
+        draw_order: DrawOrder,
+        // Source of [Uniform::time]
+        timer: Timer,
+    }
+
+    impl Renderer2D {
+        pub fn new(context: &WGPUContext) -> Self {
+            let uniform = BufferAndData::new(
+                Uniform {
+                    screen_size: Vector2::new([
+                        context.config().width as f32,
+                        context.config().height as f32,
+                    ]),
+					view_port_origin: Vector2::new([0., 0.]),
+                    scale_factor: 1.,
+                    coordinate_system: CoordinateSystem::default() as u32,
+                    aa_px: 1.,
+                    time: 0.,
+                },
+                context,
+            );
+
+            let _2d_uniform_bind_group_descriptor = BindGroupLayoutDescriptor {
+                label: Some("Texture bind group layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            };
+
+            let uniform_bind_group_layout = context
+                .device()
+                .create_bind_group_layout(&_2d_uniform_bind_group_descriptor);
+
+            let uniform_bind_group =
+                create_uniform_bind_group(&uniform, &uniform_bind_group_layout, context);
+
+            Self {
+                uniform,
+                uniform_bind_group,
+                uniform_bind_group_layout,
+                uniform_pool: Vec::new(),
+                clip_stack: ClipStack::new(),
+                gpu_timer: None,
+                last_stats: RenderStats::default(),
+                draw_order: Vec::new(),
+                timer: Timer::new(),
+            }
+        }
+
+        /// Renders the items to the whole window, ordered by [Render::z_index].
+        ///
+        /// Frames that can't be acquired because the surface is lost, outdated or
+        /// timed out are skipped. Only unrecoverable errors are returned
+        pub fn render<I>(
+            &mut self,
+            items: I,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            let items = items.into_iter().collect::<Items<_>>();
+            order_by_z_index(&items, &mut self.draw_order);
+            let stats = items.iter().map(Render::stats).sum();
+            self.render_frame(stats, context, |render_pass, renderer| {
+                for &(_, index) in &renderer.draw_order {
+                    items[index].render(render_pass, context, shader_manager);
+                }
+            })
+        }
+
+        /// Renders the items like [Self::render] and paints `ui` over them at the end
+        /// of the frame
+        pub fn render_with_ui<I>(
+            &mut self,
+            items: I,
+            ui: &mut dyn UiPass,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            let Some(mut frame) = self.begin_frame(context)? else {
+                return Ok(());
+            };
+            frame.draw(items, shader_manager);
+            frame.paint_ui(ui);
+            frame.end_frame();
+            Ok(())
+        }
+
+        /// Renders the items to the whole window like [Self::render], but draws all
+        /// items that share a [Render::pipeline_label] together so each pipeline is
+        /// set once per frame.
+        ///
+        /// Items are first ordered by [Render::z_index] and only items with the same z
+        /// index are batched, so higher z indices still draw on top. Within a z index
+        /// batches are drawn in the order their label first appears and items keep
+        /// their order within a batch. This changes the draw order, so overlapping
+        /// translucent items may blend differently than with `render`. Items without a
+        /// label are drawn on their own at their position.
+        ///
+        /// The returned stats describe the batching even if the frame was skipped
+        pub fn render_batched<I>(
+            &mut self,
+            items: I,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<BatchStats, SurfaceError>
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            let items = items.into_iter().collect::<Items<_>>();
+            order_by_z_index(&items, &mut self.draw_order);
+            let stats = BatchStats {
+                items: items.len(),
+                pipeline_switches: batch_by_pipeline(&items, &mut self.draw_order),
+            };
+
+            let frame_stats = items.iter().map(Render::stats).sum();
+            self.render_frame(frame_stats, context, |render_pass, renderer| {
+                let batches = renderer.draw_order.chunk_by(|a, b| a.0 == b.0);
+                for batch in batches {
+                    let first = &items[batch[0].1];
+                    match first.pipeline_label() {
+                        Some(label) => {
+                            render_pass
+                                .set_pipeline(&shader_manager.get_render_pipeline(label, context));
+                            for &(_, index) in batch {
+                                items[index].draw(render_pass, context, shader_manager);
+                            }
+                        }
+                        None => first.render(render_pass, context, shader_manager),
+                    }
+                }
+            })?;
+            Ok(stats)
+        }
+
+        /// Renders the items into a single sub-rectangle of the window.
+        ///
+        /// See [Self::render_viewports]
+        pub fn render_viewport<I>(
+            &mut self,
+            items: I,
+            viewport: Viewport,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            self.render_viewports([(viewport, items)], context, shader_manager)
+        }
+
+        /// Renders each group of items into its own sub-rectangle of the window
+        /// within a single frame (e.g. split-screen or a minimap).
+        ///
+        /// The uniform (`screen_size` and `view_port_origin`) applies per viewport:
+        /// the region of world space it describes is scaled to fit each viewport
+        /// rather than to the whole window. Drawing is clipped to the viewport.
+        pub fn render_viewports<V, I>(
+            &mut self,
+            viewports: V,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            V: IntoIterator<Item = (Viewport, I)>,
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            self.render_viewports_with_uniforms(
+                viewports
+                    .into_iter()
+                    .map(|(viewport, items)| (viewport, None, items)),
+                context,
+                shader_manager,
+            )
+        }
+
+        /// Renders the items into a sub-rectangle of the window using `uniform` instead
+        /// of the renderer's own, e.g. to show the world zoomed out.
+        ///
+        /// See [Self::render_viewports_with_uniforms] to combine this with other views
+        /// in the same frame
+        pub fn render_with_uniform<I>(
+            &mut self,
+            items: I,
+            uniform: Uniform,
+            viewport: Viewport,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            self.render_viewports_with_uniforms(
+                [(viewport, Some(uniform), items)],
+                context,
+                shader_manager,
+            )
+        }
+
+        /// Like [Self::render_viewports], but each viewport can bring its own [Uniform]
+        /// (e.g. a minimap over the main view). Viewports without one use the
+        /// renderer's uniform.
+        ///
+        /// Each override is written to a separate uniform buffer. These buffers are
+        /// pooled, so a frame with as many overrides as the last one allocates nothing
+        pub fn render_viewports_with_uniforms<V, I>(
+            &mut self,
+            viewports: V,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            V: IntoIterator<Item = (Viewport, Option<Uniform>, I)>,
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            // The items of all viewports are gathered in one list and each viewport
+            // draws its own range of it
+            let mut items = Items::new();
+            let mut views = SmallVec::<[(Viewport, Option<usize>, Range<usize>); 4]>::new();
+            let mut overrides = 0;
+            for (viewport, uniform, group) in viewports {
+                // Upload every override to its own buffer since writes only land on submit
+                let pooled = uniform.map(|uniform| {
+                    self.write_pooled_uniform(overrides, uniform, context);
+                    overrides += 1;
+                    overrides - 1
+                });
+                let start = items.len();
+                items.extend(group);
+                views.push((viewport, pooled, start..items.len()));
+            }
+
+            self.draw_order.clear();
+            for (group, (_, _, range)) in views.iter().enumerate() {
+                self.draw_order
+                    .extend(range.clone().map(|index| (group, index)));
+            }
+            sort_by_z_index(&items, &mut self.draw_order);
+
+            let stats = items.iter().map(Render::stats).sum();
+            self.render_frame(stats, context, |render_pass, renderer| {
+                for (viewport, pooled, range) in views {
+                    viewport.apply(render_pass, context);
+                    renderer.apply_clip(viewport, render_pass, context);
+                    let bind_group = match pooled {
+                        Some(index) => &renderer.uniform_pool[index].1,
+                        None => &renderer.uniform_bind_group,
+                    };
+                    render_pass.set_bind_group(0, bind_group, &[]);
+                    for &(_, index) in &renderer.draw_order[range] {
+                        items[index].render(render_pass, context, shader_manager);
+                    }
+                }
+            })
+        }
+
+        /// Starts a frame that several draw calls can add to before it is presented
+        /// with [FrameContext::end_frame]. This lets independent systems (e.g. the
+        /// world and the UI) draw into the same frame without knowing about each other.
+        ///
+        /// Returns `None` if the frame is skipped, see [Self::render]
+        pub fn begin_frame<'a>(
+            &'a mut self,
+            context: &'a WGPUContext,
+        ) -> Result<Option<FrameContext<'a>>, SurfaceError> {
+            self.begin_frame_with_targets(&[], context)
+        }
+
+        /// Like [Self::begin_frame], but every pass also draws to `targets`, bound to
+        /// `@location(1)` onwards. They are cleared to zero by the first pass.
+        ///
+        /// Every item drawn in the frame must write to all targets, e.g. through
+        /// [PrimitiveRenderer::with_ids]
+        pub fn begin_frame_with_targets<'a>(
+            &'a mut self,
+            targets: &'a [&'a TextureView],
+            context: &'a WGPUContext,
+        ) -> Result<Option<FrameContext<'a>>, SurfaceError> {
+            if let Some(timer) = &mut self.gpu_timer {
+                timer.collect(context);
+            }
+            self.uniform.data.time = self.timer.elapsed_start();
+            self.uniform.update_buffer(context);
+            let Some(frame) = context.acquire_frame()? else {
+                return Ok(None);
+            };
+
+            let view = frame.texture().create_view(&TextureViewDescriptor {
+                label: Some("Render Texture"),
+                format: Some(frame.texture().format()),
+                dimension: Some(TextureViewDimension::D2),
+                usage: Some(TextureUsages::RENDER_ATTACHMENT),
+                aspect: TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: None,
+                base_array_layer: 0,
+                array_layer_count: None,
+            });
+
+            Ok(Some(FrameContext {
+                encoder: context.get_encoder(),
+                renderer: self,
+                context,
+                frame,
+                view,
+                targets,
+                cleared: false,
+                overrides: 0,
+                stats: RenderStats::default(),
+            }))
+        }
+
+        /// Renders the items like [Self::render] while also drawing to `targets`, e.g.
+        /// an [IdBuffer] for picking. See [Self::begin_frame_with_targets]
+        pub fn render_with_targets<I>(
+            &mut self,
+            items: I,
+            targets: &[&TextureView],
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Result<(), SurfaceError>
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            let Some(mut frame) = self.begin_frame_with_targets(targets, context)? else {
+                return Ok(());
+            };
+            frame.draw(items, shader_manager);
+            frame.end_frame();
+            Ok(())
+        }
+
+        /// Renders a whole frame with a single pass that is handed to `draw`, which
+        /// submits `stats`. `draw` is not called if the frame is skipped
+        fn render_frame(
+            &mut self,
+            stats: RenderStats,
+            context: &WGPUContext,
+            draw: impl FnOnce(&mut RenderPass, &Self),
+        ) -> Result<(), SurfaceError> {
+            let Some(mut frame) = self.begin_frame(context)? else {
+                return Ok(());
+            };
+            frame.stats = stats;
+            frame.pass(draw);
+            frame.end_frame();
+            Ok(())
+        }
+
+        /// Turns measuring the GPU time of each frame on or off, see
+        /// [Self::last_gpu_time].
+        ///
+        /// Returns whether timing is on. It stays off if the device doesn't support
+        /// [Features::TIMESTAMP_QUERY]
+        pub fn set_gpu_timing(&mut self, enabled: bool, context: &WGPUContext) -> bool {
+            if !enabled {
+                self.gpu_timer = None;
+            } else if self.gpu_timer.is_none() {
+                self.gpu_timer = GpuTimer::new(context);
+                if self.gpu_timer.is_none() {
+                    log::warn!("Timestamp queries are not supported, GPU timing stays off");
+                }
+            }
+            self.gpu_timer.is_some()
+        }
+
+        /// Milliseconds the GPU spent in the render passes of a recent frame.
+        ///
+        /// Timestamps are read back without waiting for the GPU, so this lags a frame
+        /// or two behind. [None] until the first measurement arrives or if GPU timing
+        /// is off
+        pub fn last_gpu_time(&self) -> Option<f32> {
+            self.gpu_timer.as_ref().and_then(|timer| timer.last)
+        }
+
+        /// Registers the shaders and pipelines of every built-in renderer up front, so
+        /// that creating the first renderer of a kind doesn't have to.
+        ///
+        /// [TextureRenderer] and [PolygonCircleRenderer] are left out since their
+        /// pipelines depend on the blend mode and segment count they are created with
+        pub fn preregister_all(&self, context: &WGPUContext, shader_manager: &ShaderManager) {
+            let layout = &self.uniform_bind_group_layout;
+            PointRenderer::register(layout, context, shader_manager);
+            TriangleListRenderer::register(layout, context, shader_manager);
+            PolylineRenderer::register(layout, context, shader_manager);
+            CenterRect::register(layout, context, shader_manager);
+            BorderedRect::register(layout, context, shader_manager);
+            PivotRect::register(layout, context, shader_manager);
+            Line::register(layout, context, shader_manager);
+            Circle::register(layout, context, shader_manager);
+            Ring::register(layout, context, shader_manager);
+            Particle::register(layout, context, shader_manager);
+        }
+
+        /// Calls [Self::preregister_all] and then compiles every registered pipeline with
+        /// [ShaderManager::warm_up], so the first frame doesn't stall on compilation.
+        /// Returns the result for each pipeline
+        pub fn warm_up(
+            &self,
+            context: &WGPUContext,
+            shader_manager: &ShaderManager,
+        ) -> Vec<(String, Result<(), ShaderError>)> {
+            self.preregister_all(context, shader_manager);
+            shader_manager.warm_up(context)
+        }
+
+        /// Draw calls, instances and vertices of the last presented frame, as reported
+        /// by [Render::stats] of the items drawn in it
+        pub fn last_stats(&self) -> RenderStats {
+            self.last_stats
+        }
+
+        /// Writes `uniform` to the pooled buffer at `index`, growing the pool if needed
+        fn write_pooled_uniform(&mut self, index: usize, uniform: Uniform, context: &WGPUContext) {
+            // Overrides share the frame's time, so animations stay in step
+            let uniform = Uniform {
+                time: self.timer.elapsed_start(),
+                ..uniform
+            };
+            if index == self.uniform_pool.len() {
+                let buffer = BufferAndData::new(uniform, context);
+                let bind_group =
+                    create_uniform_bind_group(&buffer, &self.uniform_bind_group_layout, context);
+                self.uniform_pool.push((buffer, bind_group));
+            } else {
+                let buffer = &mut self.uniform_pool[index].0;
+                buffer.data = uniform;
+                buffer.update_buffer(context);
+            }
+        }
+
+        /// Limits drawing to the current clip rect within `viewport`, if one is pushed
+        fn apply_clip(
+            &self,
+            viewport: Viewport,
+            render_pass: &mut RenderPass,
+            context: &WGPUContext,
+        ) {
+            if let Some(clip) = self.clip_stack.current() {
+                clip.intersection(&viewport).apply_scissor(render_pass, context);
+            }
+        }
+
+        /// Clips everything rendered until the matching [Self::pop_clip] to a region of
+        /// world space, given by its corner with the smallest coordinates and its size.
+        /// Nested clips are intersected with the enclosing ones.
+        ///
+        /// The region is converted to whole pixels with the renderer's uniform, covering
+        /// every pixel it touches. Unlike a [Viewport] it doesn't change how the world is
+        /// mapped to the screen
+        pub fn push_clip(&mut self, min: Vector2<f32>, size: Vector2<f32>) {
+            let a = self.uniform.data.world_to_screen(min);
+            let b = self.uniform.data.world_to_screen(min + size);
+            let [left, top] = [a[0].min(b[0]).max(0.), a[1].min(b[1]).max(0.)];
+            let [right, bottom] = [a[0].max(b[0]).max(0.), a[1].max(b[1]).max(0.)];
+            self.push_clip_screen(Viewport {
+                x: left.floor() as u32,
+                y: top.floor() as u32,
+                width: (right.ceil() - left.floor()) as u32,
+                height: (bottom.ceil() - top.floor()) as u32,
+            });
+        }
+
+        /// Like [Self::push_clip] with a rect in physical pixels
+        pub fn push_clip_screen(&mut self, rect: Viewport) {
+            self.clip_stack.push(rect);
+        }
+
+        pub fn pop_clip(&mut self) -> Option<Viewport> {
+            self.clip_stack.pop()
+        }
+
+        pub fn clip_stack(&self) -> &ClipStack {
+            &self.clip_stack
+        }
+
+        pub fn uniform_bind_group_layout(&self) -> &BindGroupLayout {
+            &self.uniform_bind_group_layout
+        }
+
+        /// The uniform bound to group 0 of every pass, for drawing this crate's
+        /// renderers in a [UiPass], which starts without any bind groups
+        pub fn uniform_bind_group(&self) -> &BindGroup {
+            &self.uniform_bind_group
+        }
+
+        pub fn update_uniform(&mut self, context: &WGPUContext) {
+            self.uniform.update_buffer(context);
+        }
+
+        pub fn uniform(&self) -> &Uniform {
+            &self.uniform.data
+        }
+
+        /// Sets the number of physical pixels per logical pixel and uploads the uniform.
+        /// Call this when the window is created and whenever its scale factor changes
+        pub fn set_scale_factor(&mut self, scale_factor: f32, context: &WGPUContext) {
+            self.uniform.data.scale_factor = scale_factor;
+            self.update_uniform(context);
+        }
+
+        /// Sets how world positions map to the screen and uploads the uniform
+        pub fn set_coordinate_system(
+            &mut self,
+            coordinate_system: CoordinateSystem,
+            context: &WGPUContext,
+        ) {
+            self.uniform.data.coordinate_system = coordinate_system as u32;
+            self.update_uniform(context);
+        }
+
+        /// Sets the width of the antialiased edge shared by every SDF primitive and
+        /// uploads the uniform. Negative values are treated as 0
+        pub fn set_antialias_pixels(&mut self, pixels: f32, context: &WGPUContext) {
+            self.uniform.data.aa_px = pixels.max(0.);
+            self.update_uniform(context);
+        }
+
+        /// Restarts [Uniform::time] from 0 on the next frame
+        pub fn reset_time(&mut self) {
+            self.timer = Timer::new();
+        }
+
+        /// See [Uniform::screen_to_world]
+        pub fn screen_to_world(&self, screen: Vector2<f32>) -> Vector2<f32> {
+            self.uniform.data.screen_to_world(screen)
+        }
+
+        /// See [Uniform::world_to_screen]
+        pub fn world_to_screen(&self, world: Vector2<f32>) -> Vector2<f32> {
+            self.uniform.data.world_to_screen(world)
+        }
+
+		pub fn get_uniform(&mut self) -> &mut Uniform {
+			&mut self.uniform.data
+		}
+    }
+
+    /// A frame started with [Renderer2D::begin_frame].
+    ///
+    /// Every draw call records its own render pass into a shared encoder. The first
+    /// one clears the frame and later ones draw over it. Nothing reaches the screen
+    /// until [Self::end_frame] submits and presents. A frame that is dropped
+    /// without calling it is discarded.
+    pub struct FrameContext<'a> {
+        renderer: &'a mut Renderer2D,
+        context: &'a WGPUContext,
+        frame: FrameTexture,
+        view: TextureView,
+        // Extra color attachments after the frame
+        targets: &'a [&'a TextureView],
+        encoder: CommandEncoder,
+        cleared: bool,
+        // Number of pooled uniforms used so far this frame
+        overrides: usize,
+        stats: RenderStats,
+    }
+
+    impl FrameContext<'_> {
+        /// Draws the items over what is already in the frame, ordered by
+        /// [Render::z_index]
+        pub fn draw<I>(&mut self, items: I, shader_manager: &ShaderManager)
+        where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            let context = self.context;
+            let items = items.into_iter().collect::<Items<_>>();
+            order_by_z_index(&items, &mut self.renderer.draw_order);
+            self.stats += items.iter().map(Render::stats).sum();
+            self.pass(|render_pass, renderer| {
+                for &(_, index) in &renderer.draw_order {
+                    items[index].render(render_pass, context, shader_manager);
+                }
+            });
+        }
+
+        /// Like [Self::draw], but with `uniform` instead of the renderer's own.
+        ///
+        /// Each call takes its own buffer from the renderer's uniform pool, so
+        /// earlier draws in the frame keep the uniform they were recorded with
+        pub fn draw_with_uniform<I>(
+            &mut self,
+            items: I,
+            uniform: Uniform,
+            shader_manager: &ShaderManager,
+        ) where
+            I: IntoIterator,
+            <I as IntoIterator>::Item: Render,
+        {
+            let context = self.context;
+            let index = self.overrides;
+            self.overrides += 1;
+            self.renderer.write_pooled_uniform(index, uniform, context);
+
+            let items = items.into_iter().collect::<Items<_>>();
+            order_by_z_index(&items, &mut self.renderer.draw_order);
+            self.stats += items.iter().map(Render::stats).sum();
+            self.pass(|render_pass, renderer| {
+                render_pass.set_bind_group(0, &renderer.uniform_pool[index].1, &[]);
+                for &(_, item) in &renderer.draw_order {
+                    items[item].render(render_pass, context, shader_manager);
+                }
+            });
+        }
+
+        /// See [Renderer2D::push_clip]. Applies to draws made after this call
+        pub fn push_clip(&mut self, min: Vector2<f32>, size: Vector2<f32>) {
+            self.renderer.push_clip(min, size);
+        }
+
+        /// See [Renderer2D::pop_clip]
+        pub fn pop_clip(&mut self) -> Option<Viewport> {
+            self.renderer.pop_clip()
+        }
+
+        /// Paints `ui` over everything drawn so far in its own pass. Call it after the
+        /// scene so the UI ends up on top.
+        ///
+        /// The pass only draws to the frame, not to the targets of
+        /// [Renderer2D::begin_frame_with_targets], and isn't clipped
+        pub fn paint_ui(&mut self, ui: &mut dyn UiPass) {
+            let context = self.context;
+            ui.prepare(&mut self.encoder, context);
+            let load = if self.cleared {
+                LoadOp::Load
+            } else {
+                LoadOp::Clear(CLEAR_COLOR)
+            };
+            self.cleared = true;
+
+            let timed_pass = self.renderer.gpu_timer.as_mut().and_then(GpuTimer::next_pass);
+            let mut render_pass = self
+                .encoder
+                .begin_render_pass(&RenderPassDescriptor {
+                    label: Some("UI pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &self.view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load,
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    timestamp_writes: timed_pass
+                        .zip(self.renderer.gpu_timer.as_ref())
+                        .map(|(index, timer)| timer.pass_writes(index)),
+                    ..Default::default()
+                })
+                .forget_lifetime();
+            ui.paint(&mut render_pass, context);
+        }
+
+        /// Submits everything drawn this frame and presents it. A frame with no
+        /// draws is still cleared
+        pub fn end_frame(mut self) {
+            if !self.cleared {
+                self.pass(|_, _| {});
+            }
+            if let Some(timer) = &self.renderer.gpu_timer {
+                timer.resolve(&mut self.encoder);
+            }
+            self.context.queue().submit([self.encoder.finish()]);
+            if let Some(timer) = &mut self.renderer.gpu_timer {
+                timer.read_back();
+            }
+            self.frame.present();
+            self.renderer.last_stats = self.stats;
+        }
+
+        /// Begins a render pass with the uniform bound and hands it to `draw`
+        fn pass(&mut self, draw: impl FnOnce(&mut RenderPass, &Renderer2D)) {
+            let (load, target_load) = if self.cleared {
+                (LoadOp::Load, LoadOp::Load)
+            } else {
+                (LoadOp::Clear(CLEAR_COLOR), LoadOp::Clear(wgpu::Color::TRANSPARENT))
+            };
+            self.cleared = true;
+
+            let attachment = |view, load| {
+                Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load,
+                        store: StoreOp::Store,
+                    },
+                })
+            };
+            let color_attachments = std::iter::once(attachment(&self.view, load))
+                .chain(self.targets.iter().map(|view| attachment(view, target_load)))
+                .collect::<SmallVec<[_; 4]>>();
+
+            let timed_pass = self.renderer.gpu_timer.as_mut().and_then(GpuTimer::next_pass);
+            let mut render_pass = self.encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &color_attachments,
+                timestamp_writes: timed_pass
+                    .zip(self.renderer.gpu_timer.as_ref())
+                    .map(|(index, timer)| timer.pass_writes(index)),
+                ..Default::default()
+            });
+
+            render_pass.set_bind_group(0, &self.renderer.uniform_bind_group, &[]);
+            self.renderer
+                .apply_clip(Viewport::full(self.context), &mut render_pass, self.context);
+            draw(&mut render_pass, self.renderer);
+        }
+    }
+
+    /// What the first pass of a frame clears the frame to
+    const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+        r: 0.05,
+        g: 0.05,
+        b: 0.05,
+        a: 1.0,
+    };
+
+    /// Most render passes per frame that get timestamps. Later passes are not timed
+    const MAX_TIMED_PASSES: u32 = 16;
+
+    /// Times the render passes of a frame with timestamp queries.
+    ///
+    /// The resolved timestamps are copied to a buffer that is mapped after submitting.
+    /// Frames recorded while it is still mapped are not timed, so reading back never
+    /// stalls rendering
+    struct GpuTimer {
+        query_set: QuerySet,
+        resolve_buffer: Buffer,
+        readback_buffer: Buffer,
+        // Nanoseconds per timestamp tick
+        period: f32,
+        // Passes given timestamps in the frame being recorded
+        timed_passes: u32,
+        // Timed passes in the readback buffer and the result of mapping it
+        pending: Option<(u32, Receiver<Result<(), BufferAsyncError>>)>,
+        last: Option<f32>,
+    }
+
+    impl GpuTimer {
+        fn new(context: &WGPUContext) -> Option<Self> {
+            let query_set =
+                context.create_timestamp_query_set(Some("GPU timer"), MAX_TIMED_PASSES * 2)?;
+            let size = (MAX_TIMED_PASSES * 2 * QUERY_SIZE) as BufferAddress;
+            let resolve_buffer = context.device().create_buffer(&BufferDescriptor {
+                label: Some("GPU timer resolve buffer"),
+                size,
+                usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+            let readback_buffer = context.device().create_buffer(&BufferDescriptor {
+                label: Some("GPU timer readback buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            Some(Self {
+                query_set,
+                resolve_buffer,
+                readback_buffer,
+                period: context.timestamp_period(),
+                timed_passes: 0,
+                pending: None,
+                last: None,
+            })
+        }
+
+        /// Reads the timestamps of an earlier frame if they have been mapped and
+        /// starts timing a new frame
+        fn collect(&mut self, context: &WGPUContext) {
+            self.timed_passes = 0;
+            let Some((passes, receiver)) = &self.pending else {
+                return;
             };
+            let _ = context.device().poll(Maintain::Poll);
+            match receiver.try_recv() {
+                Ok(Ok(())) => {
+                    let slice = self.readback_buffer.slice(..);
+                    let ticks = {
+                        let data = slice.get_mapped_range();
+                        let timestamps: &[u64] =
+                            bytemuck::cast_slice(&data[..(passes * 2 * QUERY_SIZE) as usize]);
+                        timestamps
+                            .chunks_exact(2)
+                            .map(|pass| pass[1].saturating_sub(pass[0]))
+                            .sum::<u64>()
+                    };
+                    self.readback_buffer.unmap();
+                    self.last = Some(ticks as f32 * self.period / 1_000_000.);
+                    self.pending = None;
+                }
+                Ok(Err(err)) => {
+                    log::warn!("Could not read GPU timestamps: {err}");
+                    self.pending = None;
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        /// The index of the next pass to time, if it can be timed
+        fn next_pass(&mut self) -> Option<u32> {
+            if self.pending.is_some() || self.timed_passes == MAX_TIMED_PASSES {
+                return None;
+            }
+            self.timed_passes += 1;
+            Some(self.timed_passes - 1)
+        }
 
-            shader_manager.register_constant_source("texture.wgsl", TEXTURE_SHADER.into());
-            shader_manager.register_constant_source("common.wgsl", super::COMMON_INCLUDE.into());
-            shader_manager.register_render_pipeline("texture", render_pipeline_template);
+        fn pass_writes(&self, index: u32) -> RenderPassTimestampWrites<'_> {
+            RenderPassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: Some(index * 2),
+                end_of_pass_write_index: Some(index * 2 + 1),
+            }
+        }
 
-            let bind_group = context.device().create_bind_group(&BindGroupDescriptor {
-                label: Some("Texture bind group"),
-                layout: &bind_group_layout,
-                entries: &[
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: rect.buffers.as_entire_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: BindingResource::TextureView(&texture_view),
-                    },
-                    BindGroupEntry {
-                        binding: 2,
-                        resource: BindingResource::Sampler(&sampler),
-                    },
-                ],
-            });
+        /// Copies the timestamps of this frame to the readback buffer
+        fn resolve(&self, encoder: &mut CommandEncoder) {
+            if self.timed_passes == 0 {
+                return;
+            }
+            let count = self.timed_passes * 2;
+            encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(
+                &self.resolve_buffer,
+                0,
+                &self.readback_buffer,
+                0,
+                (count * QUERY_SIZE) as BufferAddress,
+            );
+        }
+
+        /// Starts mapping the readback buffer once the frame has been submitted
+        fn read_back(&mut self) {
+            if self.timed_passes == 0 {
+                return;
+            }
+            let (sender, receiver) = channel();
+            self.readback_buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    let _ = sender.send(result);
+                });
+            self.pending = Some((self.timed_passes, receiver));
+        }
+    }
+
+    fn create_uniform_bind_group(
+        uniform: &BufferAndData<Uniform>,
+        layout: &BindGroupLayout,
+        context: &WGPUContext,
+    ) -> BindGroup {
+        context.device().create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform.buffers.as_entire_binding(),
+            }],
+        })
+    }
+
+    /// Work submitted by a frame, see [Renderer2D::last_stats] and [Render::stats].
+    /// Counts saturate at `u32::MAX` instead of overflowing
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct RenderStats {
+        pub draw_calls: u32,
+        pub instances: u32,
+        /// Vertices processed across all instances
+        pub vertices: u32,
+    }
 
+    impl RenderStats {
+        /// Stats of a single draw call of `instances` instances with `vertices` vertices each
+        pub fn draw(vertices: u32, instances: u32) -> Self {
             Self {
-                rect,
-                texture,
-                view: texture_view,
-                sampler,
-                bind_group,
+                draw_calls: 1,
+                instances,
+                vertices: vertices.saturating_mul(instances),
             }
         }
+    }
 
-        pub fn rect_mut(&mut self) -> &mut CenterRect {
-            &mut self.rect.data
+    impl std::ops::Add for RenderStats {
+        type Output = Self;
+
+        fn add(self, other: Self) -> Self {
+            Self {
+                draw_calls: self.draw_calls.saturating_add(other.draw_calls),
+                instances: self.instances.saturating_add(other.instances),
+                vertices: self.vertices.saturating_add(other.vertices),
+            }
         }
+    }
 
-        pub fn update_rect(&mut self, context: &WGPUContext) {
-            self.rect.update_buffer(context);
+    impl std::ops::AddAssign for RenderStats {
+        fn add_assign(&mut self, other: Self) {
+            *self = *self + other;
         }
     }
 
-    impl Render for TextureRenderer {
-        fn render(
-            &self,
-            render_pass: &mut RenderPass,
-            context: &WGPUContext,
-            shader_manager: &ShaderManager,
-        ) {
-            render_pass.set_pipeline(shader_manager.get_render_pipeline("texture", context));
-            render_pass.set_bind_group(1, &self.bind_group, &[]);
-            render_pass.draw(0..4, 0..1);
+    impl std::iter::Sum for RenderStats {
+        fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+            iter.fold(Self::default(), std::ops::Add::add)
         }
     }
-}
 
-use bytemuck::{Pod, Zeroable};
-use derive::UniformBufferData;
-use crate::math::Vector2;
-#[derive(Pod, Zeroable, Clone, Copy, UniformBufferData)]
-#[repr(C)]
-pub struct Uniform {
-    pub screen_size: Vector2<f32>,
-	pub view_port_origin: Vector2<f32>,
-}
+    /// How much [Renderer2D::render_batched] grouped a frame
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct BatchStats {
+        /// Number of items drawn
+        pub items: usize,
+        /// Number of times a pipeline was set, one per batch
+        pub pipeline_switches: usize,
+    }
 
-pub use circle::*;
-pub use point::*;
-pub use rect::*;
-pub use ring::*;
-pub use texture::*;
-pub use triangle::*;
-#[macro_export]
-macro_rules! vertex_buffer_layout {
-	($(($stridetype: ty, $mode: ident, $attributes: expr)),+ $(,)?) => {
-		[
-		$(::wgpu::VertexBufferLayout {
-			array_stride: ::std::mem::size_of::<$stridetype>() as u64,
-			step_mode: ::wgpu::VertexStepMode::$mode,
-			attributes: $attributes,
-		},)+
-		]
-	}
-}
+    /// Indices into the items of a render call in the order they are drawn. Each index
+    /// comes with the group it belongs to, e.g. its viewport or batch, and groups are
+    /// drawn one after the other
+    type DrawOrder = Vec<(usize, usize)>;
 
-pub use renderer::*;
-mod renderer {
-    use super::*;
-    use crate::shader_manager::ShaderManager;
-    use crate::wgpu_context::{BufferAndData, WGPUContext};
+    /// Fills `order` with all `items` in a single group, ordered by [Render::z_index]
+    fn order_by_z_index<R: Render>(items: &[R], order: &mut DrawOrder) {
+        order.clear();
+        order.extend((0..items.len()).map(|index| (0, index)));
+        sort_by_z_index(items, order);
+    }
 
-    use wgpu::*;
+    /// Sorts by group and then by z index. Indices are unique, so the unstable sort
+    /// still keeps items with equal z indices in order, and unlike a stable sort it
+    /// never allocates
+    fn sort_by_z_index<R: Render>(items: &[R], order: &mut DrawOrder) {
+        order.sort_unstable_by_key(|&(group, index)| (group, items[index].z_index(), index));
+    }
 
-    pub struct Renderer2D {
-        uniform: BufferAndData<Uniform>,
-        uniform_bind_group: BindGroup,
-        uniform_bind_group_layout: BindGroupLayout,
+    /// Regroups an `order` of a single group, sorted by z index, by pipeline label within
+    /// each z index, keeping the order in which labels first appear. Items without a
+    /// label each get a batch of their own.
+    ///
+    /// Returns the number of batches
+    fn batch_by_pipeline<R: Render>(items: &[R], order: &mut DrawOrder) -> usize {
+        // Brings items with the same z index and label together, in the order of their
+        // positions
+        for (position, entry) in order.iter_mut().enumerate() {
+            entry.0 = position;
+        }
+        let key = |&(position, index): &(usize, usize)| {
+            let item = &items[index];
+            (item.z_index(), item.pipeline_label(), position)
+        };
+        order.sort_unstable_by(|a, b| key(a).cmp(&key(b)));
+
+        // Each batch is numbered by the position of its first item
+        let mut batches = 0;
+        let mut first: Option<(i32, &str, usize)> = None;
+        for entry in order.iter_mut() {
+            let item = &items[entry.1];
+            match (item.pipeline_label(), first) {
+                (Some(label), Some((z_index, first_label, position)))
+                    if (item.z_index(), label) == (z_index, first_label) =>
+                {
+                    entry.0 = position;
+                }
+                (label, _) => {
+                    batches += 1;
+                    first = label.map(|label| (item.z_index(), label, entry.0));
+                }
+            }
+        }
+        sort_by_z_index(items, order);
+        batches
     }
 
-    impl Renderer2D {
-        pub fn new(context: &WGPUContext) -> Self {
-            let uniform = BufferAndData::new(
-                Uniform {
-                    screen_size: Vector2::new([
-                        context.config().width as f32,
-                        context.config().height as f32,
-                    ]),
-					view_port_origin: Vector2::new([0., 0.]),
-                },
-                context,
-            );
+    /// Collects, orders and batches `items` like [Renderer2D::render_batched] without
+    /// drawing them and returns the number of batches. Only public for the allocation
+    /// test in `tests/`
+    #[cfg(feature = "test_internals")]
+    #[doc(hidden)]
+    pub fn batch_items<I>(items: I, order: &mut Vec<(usize, usize)>) -> usize
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: Render,
+    {
+        let items = items.into_iter().collect::<Items<_>>();
+        order_by_z_index(&items, order);
+        batch_by_pipeline(&items, order)
+    }
 
-            let _2d_uniform_bind_group_descriptor = BindGroupLayoutDescriptor {
-                label: Some("Texture bind group layout"),
-                entries: &[BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            };
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::math::Vector4;
+        use crate::test_utils::{headless, read_texture};
 
-            let uniform_bind_group_layout = context
-                .device()
-                .create_bind_group_layout(&_2d_uniform_bind_group_descriptor);
+        struct Labeled(Option<&'static str>, usize, i32);
 
-            let uniform_bind_group = context.device().create_bind_group(&BindGroupDescriptor {
-                label: Some("Texture bind group"),
-                layout: &uniform_bind_group_layout,
-                entries: &[BindGroupEntry {
-                    binding: 0,
-                    resource: uniform.buffers.as_entire_binding(),
-                }],
-            });
+        impl Render for Labeled {
+            fn render(&self, _: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+                unreachable!()
+            }
 
-            Self {
-                uniform,
-                uniform_bind_group,
-                uniform_bind_group_layout,
+            fn pipeline_label(&self) -> Option<&str> {
+                self.0
+            }
+
+            fn z_index(&self) -> i32 {
+                self.2
             }
         }
 
-        pub fn render<I>(&mut self, items: I, context: &WGPUContext, shader_manager: &ShaderManager)
-        where
-            I: IntoIterator,
-            <I as IntoIterator>::Item: Render,
-        {
-            // log::trace!("Frame Delta: {}", self.timer.elapsed_reset());
-            // self.timer.reset();
+        fn batches(items: &[Labeled]) -> Vec<Vec<usize>> {
+            let mut order = Vec::new();
+            order_by_z_index(items, &mut order);
+            let count = batch_by_pipeline(items, &mut order);
+            let batches = order
+                .chunk_by(|a, b| a.0 == b.0)
+                .map(|batch| batch.iter().map(|&(_, index)| items[index].1).collect())
+                .collect::<Vec<_>>();
+            assert_eq!(batches.len(), count);
+            batches
+        }
 
-            let surface_texture = context
-                .surface()
-                .get_current_texture()
-                .expect("Could not get current texture");
+        #[test]
+        fn batches_interleaved_items() {
+            let items = (0..1000)
+                .map(|i| {
+                    let label = if i % 2 == 0 { "circle" } else { "rects" };
+                    Labeled(Some(label), i, 0)
+                })
+                .collect::<Vec<_>>();
+            let batches = batches(&items);
+            assert_eq!(batches.len(), 2);
+            assert!(batches[0].iter().copied().eq((0..1000).step_by(2)));
+            assert!(batches[1].iter().copied().eq((1..1000).step_by(2)));
+        }
 
-            let texture_view = surface_texture.texture.create_view(&TextureViewDescriptor {
-                label: Some("Render Texture"),
-                format: Some(surface_texture.texture.format()),
-                dimension: Some(TextureViewDimension::D2),
-                usage: Some(TextureUsages::RENDER_ATTACHMENT),
-                aspect: TextureAspect::All,
-                base_mip_level: 0,
-                mip_level_count: None,
-                base_array_layer: 0,
-                array_layer_count: None,
-            });
+        #[test]
+        fn render_stats_saturate() {
+            let huge = RenderStats::draw(u32::MAX, 2);
+            assert_eq!(huge.vertices, u32::MAX);
+            let total: RenderStats = [huge, huge].into_iter().sum();
+            assert_eq!(total.instances, 4);
+            assert_eq!(total.vertices, u32::MAX);
+        }
 
-            let mut encoder = context.get_encoder();
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &texture_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color {
-                            r: 0.05,
-                            g: 0.05,
-                            b: 0.05,
-                            a: 1.0,
-                        }),
-                        store: StoreOp::Store,
-                    },
-                })],
-                ..Default::default()
-            });
+        #[test]
+        fn batches_within_z_index() {
+            let items = [
+                Labeled(Some("circle"), 0, 0),
+                Labeled(Some("rects"), 1, 1),
+                Labeled(Some("circle"), 2, 2),
+                Labeled(Some("circle"), 3, 0),
+            ];
+            // The circle above the rects must not join the batch below them
+            assert_eq!(batches(&items), [vec![0, 3], vec![1], vec![2]]);
+        }
+
+        #[test]
+        fn uniform_overrides_are_pooled() {
+            let Some(context) = headless() else { return };
+            let shader_manager = ShaderManager::new("");
+            let mut renderer = Renderer2D::new(&context);
+            let zoomed_out = Uniform {
+                scale_factor: 0.5,
+                ..*renderer.uniform()
+            };
+            let full = Viewport::full(&context);
 
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-            for item in items {
-                item.render(&mut render_pass, &context, &shader_manager);
+            for _ in 0..2 {
+                let no_items: [&dyn Render; 0] = [];
+                renderer
+                    .render_viewports_with_uniforms(
+                        [
+                            (full, None, no_items),
+                            (full, Some(zoomed_out), no_items),
+                            (full, Some(zoomed_out), no_items),
+                        ],
+                        &context,
+                        &shader_manager,
+                    )
+                    .expect("Could not render frame");
             }
+            assert_eq!(renderer.uniform_pool.len(), 2);
+        }
+
+        #[test]
+        fn uniform_overrides_share_frame_time() {
+            let Some(context) = headless() else { return };
+            let shader_manager = ShaderManager::new("");
+            let mut renderer = Renderer2D::new(&context);
+            let stale = Uniform {
+                time: -1.,
+                ..*renderer.uniform()
+            };
+            let full = Viewport::full(&context);
+
+            let no_items: [&dyn Render; 0] = [];
+            renderer
+                .render_viewports_with_uniforms(
+                    [(full, Some(stale), no_items)],
+                    &context,
+                    &shader_manager,
+                )
+                .expect("Could not render frame");
+            // The override is written just before the frame starts
+            let time = renderer.uniform_pool[0].0.data.time;
+            assert!((0. ..=renderer.uniform().time).contains(&time), "{time}");
+        }
+
+        #[test]
+        fn time_advances_every_frame() {
+            let Some(context) = headless() else { return };
+            let shader_manager = ShaderManager::new("");
+            let mut renderer = Renderer2D::new(&context);
+            let no_items: [&dyn Render; 0] = [];
+
+            renderer
+                .render(no_items, &context, &shader_manager)
+                .expect("Could not render frame");
+            let first = renderer.uniform().time;
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            renderer
+                .render(no_items, &context, &shader_manager)
+                .expect("Could not render frame");
+            let second = renderer.uniform().time;
+            assert!(second > first);
 
-            std::mem::drop(render_pass);
+            renderer.reset_time();
+            renderer
+                .render(no_items, &context, &shader_manager)
+                .expect("Could not render frame");
+            assert!(renderer.uniform().time < second);
+        }
+
+        #[test]
+        fn draws_into_float_target() {
+            let Some(context) = headless() else { return };
+            let shader_manager = ShaderManager::new("");
+            let renderer = Renderer2D::new(&context);
+            let format = TextureFormat::Rgba16Float;
+            let rect = CenterRect {
+                color: Vector4::new([2., 1., 0.5, 1.]),
+                center: Vector2::new([32., 32.]),
+                size: Vector2::new([64., 64.]),
+                rotation: 0.,
+                layer: 0,
+            };
+            let rects = RectangleRenderer::with_format(
+                vec![rect],
+                format,
+                renderer.uniform_bind_group_layout(),
+                &context,
+                &shader_manager,
+            );
+            assert_eq!(rects.pipeline_label(), Some("rects_Rgba16Float"));
+
+            let texture = context.device().create_texture(&TextureDescriptor {
+                label: None,
+                size: Extent3d {
+                    width: 64,
+                    height: 64,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&Default::default());
+            let mut encoder = context.get_encoder();
+            {
+                let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: Operations::default(),
+                    })],
+                    ..Default::default()
+                });
+                render_pass.set_bind_group(0, &renderer.uniform_bind_group, &[]);
+                rects.render(&mut render_pass, &context, &shader_manager);
+            }
             context.queue().submit([encoder.finish()]);
-            surface_texture.present();
+
+            // Half floats keep values above 1: 2, 1, 0.5 and 1
+            let pixels = read_texture(&texture, &context);
+            let texel: [u16; 4] = bytemuck::pod_read_unaligned(&pixels[(32 * 64 + 32) * 8..][..8]);
+            assert_eq!(texel, [0x4000, 0x3c00, 0x3800, 0x3c00]);
         }
 
-        pub fn uniform_bind_group_layout(&self) -> &BindGroupLayout {
-            &self.uniform_bind_group_layout
+        #[test]
+        fn warm_up_built_in_pipelines() {
+            let Some(context) = headless() else { return };
+            let shader_manager = ShaderManager::new("");
+            let renderer = Renderer2D::new(&context);
+
+            let results = renderer.warm_up(&context, &shader_manager);
+            for label in ["Point Renderer Pipeline", "triangles", "polyline", "circle", "rects"] {
+                assert!(results.iter().any(|(result, _)| result == label));
+            }
+            for (label, result) in results {
+                assert_eq!(result, Ok(()), "{label} did not compile");
+            }
         }
 
-        pub fn update_uniform(&mut self, context: &WGPUContext) {
-            self.uniform.update_buffer(context);
+        #[test]
+        fn z_index_orders_items() {
+            struct Layered(i32, usize);
+
+            impl Render for Layered {
+                fn render(&self, _: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+                    unreachable!()
+                }
+
+                fn z_index(&self) -> i32 {
+                    self.0
+                }
+            }
+
+            let items = [Layered(1, 0), Layered(-1, 1), Layered(0, 2), Layered(1, 3)];
+            let mut order = Vec::new();
+            order_by_z_index(&items, &mut order);
+            let order = order
+                .iter()
+                .map(|&(_, index)| items[index].1)
+                .collect::<Vec<_>>();
+            assert_eq!(order, [1, 2, 0, 3]);
         }
 
-		pub fn get_uniform(&mut self) -> &mut Uniform {
-			&mut self.uniform.data
-		}
+        #[test]
+        fn clip_stack_intersects() {
+            let mut stack = ClipStack::new();
+            assert_eq!(stack.current(), None);
+            stack.push(Viewport {
+                x: 10,
+                y: 10,
+                width: 100,
+                height: 100,
+            });
+            stack.push(Viewport {
+                x: 50,
+                y: 0,
+                width: 100,
+                height: 20,
+            });
+            assert_eq!(
+                stack.current(),
+                Some(Viewport {
+                    x: 50,
+                    y: 10,
+                    width: 60,
+                    height: 10,
+                })
+            );
+            // Disjoint rects clip everything
+            stack.push(Viewport {
+                x: 0,
+                y: 0,
+                width: 5,
+                height: 5,
+            });
+            let empty = stack.pop().unwrap();
+            assert_eq!((empty.width, empty.height), (0, 0));
+            stack.pop();
+            assert_eq!(stack.current().map(|rect| rect.x), Some(10));
+        }
+
+        #[test]
+        fn unlabeled_items_are_not_merged() {
+            let items = [
+                Labeled(None, 0, 0),
+                Labeled(Some("circle"), 1, 0),
+                Labeled(None, 2, 0),
+                Labeled(Some("circle"), 3, 0),
+            ];
+            assert_eq!(batches(&items), [vec![0], vec![1, 3], vec![2]]);
+        }
     }
 }
 
@@ -1114,6 +5755,37 @@ pub trait Render {
         context: &WGPUContext,
         shader_manager: &ShaderManager,
     );
+
+    /// Label of the single pipeline `render` draws with, if there is one. Items that
+    /// share a label are drawn back to back with [Render::draw] by
+    /// [Renderer2D::render_batched]
+    fn pipeline_label(&self) -> Option<&str> {
+        None
+    }
+
+    /// Same as `render` but assumes the pipeline named by [Render::pipeline_label] is
+    /// already set. Only called for items that return a label
+    fn draw(
+        &self,
+        render_pass: &mut RenderPass,
+        context: &WGPUContext,
+        shader_manager: &ShaderManager,
+    ) {
+        self.render(render_pass, context, shader_manager);
+    }
+
+    /// Items with a higher z index are drawn later, on top of lower ones. Items with the
+    /// same z index keep the order they were passed in. Unlike [Primitive::layer] this
+    /// orders whole renderers rather than instances within one
+    fn z_index(&self) -> i32 {
+        0
+    }
+
+    /// Draw calls, instances and vertices one call to `render` submits, added up in
+    /// [Renderer2D::last_stats]. Reports nothing by default
+    fn stats(&self) -> RenderStats {
+        RenderStats::default()
+    }
 }
 
 impl<'a, R: Render + ?Sized> Render for &'a R {
@@ -1125,4 +5797,40 @@ impl<'a, R: Render + ?Sized> Render for &'a R {
     ) {
         <R as Render>::render(self, render_pass, context, shader_manager);
     }
+
+    fn pipeline_label(&self) -> Option<&str> {
+        <R as Render>::pipeline_label(self)
+    }
+
+    fn draw(
+        &self,
+        render_pass: &mut RenderPass,
+        context: &WGPUContext,
+        shader_manager: &ShaderManager,
+    ) {
+        <R as Render>::draw(self, render_pass, context, shader_manager);
+    }
+
+    fn z_index(&self) -> i32 {
+        <R as Render>::z_index(self)
+    }
+
+    fn stats(&self) -> RenderStats {
+        <R as Render>::stats(self)
+    }
+}
+
+/// Paints on top of everything else drawn in a frame, e.g. a debug UI from an external
+/// renderer like `egui-wgpu`. See [FrameContext::paint_ui] and [Renderer2D::render_with_ui]
+pub trait UiPass {
+    /// Records uploads and other commands that have to happen before the pass begins.
+    /// Does nothing by default
+    fn prepare(&mut self, encoder: &mut CommandEncoder, context: &WGPUContext) {
+        let _ = (encoder, context);
+    }
+
+    /// Draws into a pass over the frame, which only has the frame itself as a color
+    /// attachment and no bind groups set. The pass has its lifetime forgotten so that
+    /// renderers which need `RenderPass<'static>`, like `egui-wgpu`, can use it
+    fn paint(&mut self, render_pass: &mut RenderPass<'static>, context: &WGPUContext);
 }
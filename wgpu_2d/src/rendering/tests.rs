@@ -0,0 +1,984 @@
+//! Tests drawing with the renderers into a headless context
+
+use wgpu::*;
+
+use super::*;
+use crate::math::{Vector2, Vector4};
+use crate::shader_manager::ShaderManager;
+use crate::test_utils::*;
+use crate::wgpu_context::{WGPUContext, WGPUContextDescriptor};
+
+#[test]
+fn sort_by_layer_is_stable() {
+    let Some(context) = headless() else { return };
+
+    let layered = |radius, layer| Circle {
+        layer,
+        ..circle([32., 32.], radius)
+    };
+    let renderer = Renderer2D::new(&context);
+    let mut circles = CircleRenderer::new(
+        vec![
+            layered(1., 2),
+            layered(2., 0),
+            layered(3., 2),
+            layered(4., 1),
+        ],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &ShaderManager::new(""),
+    );
+    circles.sort_by_layer();
+    let order = circles
+        .circles_mut()
+        .iter()
+        .map(|x| x.radius)
+        .collect::<Vec<_>>();
+    assert_eq!(order, [2., 4., 1., 3.]);
+}
+
+#[test]
+fn circle_stroke() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let circles = CircleRenderer::new(
+        vec![Circle {
+            stroke_width: 4.,
+            ..circle([32., 32.], 24.)
+        }],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    renderer
+        .render([&circles], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+
+    // Only the band just inside the edge is drawn
+    assert!(pixel(&pixels, 54, 32)[0] > 200);
+    assert!(pixel(&pixels, 32, 32)[0] < 100);
+    assert!(pixel(&pixels, 42, 32)[0] < 100);
+}
+
+#[test]
+fn transparent_batch_sorts_by_layer() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut batch = CircleBatch::new(
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    batch.set_transparent(true);
+    let translucent = |color: [f32; 4], x: f32, layer: u32| Circle {
+        color: Vector4::new(color),
+        layer,
+        ..circle([x, 32.], 16.)
+    };
+    // Half transparent red on top of blue, but pushed first
+    batch.push(translucent([0.5, 0., 0., 0.5], 28., 1));
+    batch.push(translucent([0., 0., 0.5, 0.5], 36., 0));
+    batch.upload(&context);
+    assert_eq!(batch.circles()[0].layer, 0);
+    renderer
+        .render([&batch], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+    let center = pixel(&pixels, 32, 32);
+    assert!(center[0] > center[2], "{center:?}");
+}
+
+#[test]
+fn batch_grows_every_buffer() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut batch = CircleBatch::new(
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    batch.push(circle([32., 32.], 4.));
+    batch.upload(&context);
+
+    batch.clear();
+    batch.extend((0..100).map(|index| Circle {
+        color: Vector4::new([1., 0., 0., 1.]),
+        layer: 1,
+        rotation: 0.5,
+        ..circle(
+            [(index % 10) as f32 * 6. + 5., (index / 10) as f32 * 6. + 5.],
+            2.,
+        )
+    }));
+    batch.upload(&context);
+    renderer
+        .render([&batch], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+    assert!(pixel(&pixels, 5, 5)[0] > 200);
+    assert!(pixel(&pixels, 59, 59)[0] > 200);
+}
+
+#[test]
+fn modify_instances() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut circles = CircleRenderer::new(
+        vec![circle([16., 32.], 8.), circle([48., 32.], 8.)],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+
+    circles.for_each_mut(|circle| circle.color = Vector4::new([0., 0., 1., 1.]));
+    assert!(circles.modify(1, |circle| circle.color = Vector4::new([1., 0., 0., 1.])));
+    assert!(!circles.modify(2, |_| panic!("Out of range instance was modified")));
+    circles.sync(&context);
+    renderer
+        .render([&circles], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+    assert!(pixel(&pixels, 16, 32)[0] < 100 && pixel(&pixels, 16, 32)[2] > 200);
+    assert!(pixel(&pixels, 48, 32)[0] > 200 && pixel(&pixels, 48, 32)[2] < 100);
+}
+
+#[test]
+fn id_buffer_picks() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let circles = CircleRenderer::new(
+        vec![circle([16., 32.], 8.), circle([48., 32.], 8.)],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let ids = IdBuffer::new(&context);
+    renderer
+        .render_with_targets(
+            [circles.with_ids()],
+            &[ids.view()],
+            &context,
+            &shader_manager,
+        )
+        .expect("Could not render frame");
+
+    assert_eq!(ids.read([16, 32], &context), Some(0));
+    assert_eq!(ids.read([48, 32], &context), Some(1));
+    assert_eq!(ids.read([32, 32], &context), None);
+    assert_eq!(ids.read([64, 0], &context), None);
+}
+
+#[test]
+fn line_renderer() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let points = [[8., 8.], [56., 8.], [32., 56.]].map(|position| Point {
+        color: Vector4::new([1., 1., 1., 1.]),
+        position: Vector2::new(position),
+    });
+    let lines = LineRenderer::new(
+        points_to_closed_lines(&points, 2.),
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    renderer
+        .render([&lines], &context, &shader_manager)
+        .expect("Could not render frame");
+}
+
+#[test]
+fn rect_border() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let rect = BorderedRect {
+        color: Vector4::new([0., 0., 1., 1.]),
+        center: Vector2::new([32., 32.]),
+        size: Vector2::new([40., 40.]),
+        rotation: 0.,
+        border_color: Vector4::new([1., 0., 0., 1.]),
+        border_width: 4.,
+        layer: 0,
+    };
+    let mut rects = BorderedRectRenderer::new(
+        vec![rect],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    renderer
+        .render([&rects], &context, &shader_manager)
+        .expect("Could not render frame");
+    let pixels = read_pixels(&context);
+    // Border from 12 to 16, fill inside
+    assert!(pixel(&pixels, 14, 32)[0] > 200 && pixel(&pixels, 14, 32)[2] < 100);
+    assert!(pixel(&pixels, 32, 14)[0] > 200 && pixel(&pixels, 32, 14)[2] < 100);
+    assert!(pixel(&pixels, 32, 32)[0] < 100 && pixel(&pixels, 32, 32)[2] > 200);
+
+    // Without a border the rect is filled up to its edges, and the outermost pixels fade
+    rects.rects_mut()[0].border_width = 0.;
+    rects.sync(&context);
+    renderer
+        .render([&rects], &context, &shader_manager)
+        .expect("Could not render frame");
+    let pixels = read_pixels(&context);
+    assert!(pixel(&pixels, 13, 32)[0] < 100 && pixel(&pixels, 13, 32)[2] > 200);
+    let edge = pixel(&pixels, 12, 32)[2];
+    assert!((60..200).contains(&edge), "{edge}");
+}
+
+#[test]
+fn particles_step_on_gpu() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut particles = GpuParticleSystem::new(
+        2,
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let particle = Particle {
+        color: Vector4::new([1., 1., 1., 1.]),
+        position: Vector2::new([16., 16.]),
+        velocity: Vector2::new([32., 0.]),
+        acceleration: Vector2::new([0., 32.]),
+        size: 8.,
+        lifetime: 2.,
+    };
+    // The third overwrites the oldest particle in slot 0 and has already died, so only
+    // the second one is drawn
+    particles.emit(&[particle, particle], &context);
+    particles.emit(
+        &[Particle {
+            lifetime: 0.,
+            ..particle
+        }],
+        &context,
+    );
+    particles.step(1., &context);
+    renderer
+        .render([&particles], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+    // Moved by the velocity after the acceleration was applied
+    assert!(pixel(&pixels, 48, 48)[0] > 200);
+    assert!(pixel(&pixels, 16, 16)[0] < 100);
+    let lit = pixels.chunks(4).filter(|pixel| pixel[0] > 200).count();
+    assert_eq!(lit, 8 * 8);
+}
+
+#[test]
+fn rect_pivot() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    // Rotated a quarter turn about its top left corner, so it hangs down to the left
+    let rect = PivotRect {
+        color: Vector4::new([1., 1., 1., 1.]),
+        position: Vector2::new([32., 16.]),
+        size: Vector2::new([32., 16.]),
+        rotation: std::f32::consts::FRAC_PI_2,
+        pivot: Vector2::new([0., 0.]),
+        layer: 0,
+    };
+    let rects = PivotRectRenderer::new(
+        vec![rect],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    renderer
+        .render([&rects], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+    assert!(pixel(&pixels, 24, 40)[0] > 200);
+    assert!(pixel(&pixels, 40, 20)[0] < 100);
+}
+
+#[test]
+fn renderer_tint() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let rect = rect([32., 32.], [64., 64.]);
+    let mut rects = RectangleRenderer::new(
+        vec![rect],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    assert_eq!(*rects.constants().tint(), [1., 1., 1., 1.]);
+    rects.set_tint(Vector4::new([0., 1., 0., 1.]), &context);
+    renderer
+        .render([&rects], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let pixels = read_pixels(&context);
+    let center = pixel(&pixels, 32, 32);
+    assert!(
+        center[0] < 50 && center[1] > 200 && center[2] < 50,
+        "{center:?}"
+    );
+}
+
+#[test]
+fn polygon_mode_outlines() {
+    let descriptor = WGPUContextDescriptor {
+        optional_features: Features::POLYGON_MODE_LINE,
+        ..Default::default()
+    };
+    let Some(context) = headless_with(&descriptor) else {
+        return;
+    };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let rects = RectangleRenderer::new(
+        vec![rect([32., 32.], [40., 40.])],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    // Inside the rect but away from the diagonal of its quad
+    let inside_lit = |renderer: &mut Renderer2D| {
+        renderer
+            .render([&rects], &context, &shader_manager)
+            .expect("Could not render frame");
+        pixel(&read_pixels(&context), 40, 28)[0] > 200
+    };
+    assert!(inside_lit(&mut renderer));
+
+    // Points are never requested
+    assert!(!shader_manager.set_polygon_mode(PolygonMode::Point, &context));
+    assert_eq!(shader_manager.polygon_mode(), PolygonMode::Fill);
+    if !shader_manager.set_polygon_mode(PolygonMode::Line, &context) {
+        eprintln!("POLYGON_MODE_LINE not supported, skipping polygon_mode_outlines");
+        return;
+    }
+    assert!(!inside_lit(&mut renderer));
+    assert!(shader_manager.set_polygon_mode(PolygonMode::Fill, &context));
+    assert!(inside_lit(&mut renderer));
+}
+
+#[test]
+fn canvas_draws_queued_shapes() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut canvas = Canvas::new(
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let red = Vector4::new([1., 0., 0., 1.]);
+    let blue = Vector4::new([0., 0., 1., 1.]);
+
+    // Nothing queued yet
+    canvas
+        .present(&mut renderer, &context, &shader_manager)
+        .expect("Could not render frame");
+    assert_eq!(renderer.last_stats().draw_calls, 0);
+
+    canvas.rect(Vector2::new([16., 16.]), Vector2::new([16., 16.]), red);
+    canvas.circle(Vector2::new([48., 48.]), 8., blue);
+    canvas.line(Vector2::new([0., 48.]), Vector2::new([24., 48.]), 4., red);
+    canvas
+        .present(&mut renderer, &context, &shader_manager)
+        .expect("Could not render frame");
+    assert_eq!(renderer.last_stats().draw_calls, 3);
+    let pixels = read_pixels(&context);
+    assert!(pixel(&pixels, 16, 16)[0] > 200);
+    assert!(pixel(&pixels, 48, 48)[2] > 200);
+    assert!(pixel(&pixels, 12, 48)[0] > 200);
+
+    // Shapes are only drawn for the frame they were queued in, and more than fit
+    // before can be queued
+    for x in [8., 24., 40., 56.] {
+        canvas.circle(Vector2::new([x, 16.]), 4., blue);
+    }
+    canvas
+        .present(&mut renderer, &context, &shader_manager)
+        .expect("Could not render frame");
+    assert_eq!(renderer.last_stats().draw_calls, 1);
+    assert_eq!(renderer.last_stats().instances, 4);
+    let pixels = read_pixels(&context);
+    assert!(pixel(&pixels, 56, 16)[2] > 200);
+    assert!(pixel(&pixels, 16, 16)[0] < 100);
+    assert!(pixel(&pixels, 48, 48)[2] < 100);
+}
+
+#[test]
+fn circle_rotation_keeps_flat_fill() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut draw = |rotation| {
+        let circles = CircleRenderer::new(
+            vec![Circle {
+                edge_softness: 2.,
+                stroke_width: 4.,
+                rotation,
+                ..circle([32., 32.], 20.)
+            }],
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        );
+        renderer
+            .render([&circles], &context, &shader_manager)
+            .expect("Could not render frame");
+        read_pixels(&context)
+    };
+    let upright = draw(0.);
+    let rotated = draw(1.2);
+    assert!(upright
+        .iter()
+        .zip(&rotated)
+        .all(|(a, b)| a.abs_diff(*b) <= 2));
+    assert!(pixel(&upright, 50, 32)[0] > 200);
+}
+
+#[test]
+fn active_range_draws_part() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut circles = CircleRenderer::new(
+        [16., 32., 48.].map(|x| circle([x, 32.], 6.)).to_vec(),
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let mut lit = |circles: &CircleRenderer| {
+        renderer
+            .render([circles], &context, &shader_manager)
+            .expect("Could not render frame");
+        let pixels = read_pixels(&context);
+        [16, 32, 48].map(|x| pixel(&pixels, x, 32)[0] > 200)
+    };
+    assert_eq!(lit(&circles), [true, true, true]);
+
+    circles.set_active_range(Some(1..2));
+    assert_eq!(circles.stats().instances, 1);
+    assert_eq!(lit(&circles), [false, true, false]);
+    // Instances past the end are ignored
+    circles.set_active_range(Some(2..10));
+    assert_eq!(circles.active_range(), 2..3);
+    assert_eq!(lit(&circles), [false, false, true]);
+}
+
+#[test]
+fn ui_pass_paints_over_scene() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let scene = RectangleRenderer::new(
+        vec![CenterRect {
+            color: Vector4::new([0., 0., 1., 1.]),
+            ..rect([32., 32.], [64., 64.])
+        }],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+
+    // A red panel over the left half of the window
+    struct Panel<'a> {
+        rects: RectangleRenderer,
+        bind_group: BindGroup,
+        shader_manager: &'a ShaderManager,
+        prepared: bool,
+    }
+
+    impl UiPass for Panel<'_> {
+        fn prepare(&mut self, _: &mut CommandEncoder, _: &WGPUContext) {
+            self.prepared = true;
+        }
+
+        fn paint(&mut self, render_pass: &mut RenderPass<'static>, context: &WGPUContext) {
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            self.rects.render(render_pass, context, self.shader_manager);
+        }
+    }
+
+    let mut panel = Panel {
+        rects: RectangleRenderer::new(
+            vec![CenterRect {
+                color: Vector4::new([1., 0., 0., 1.]),
+                ..rect([16., 32.], [32., 64.])
+            }],
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        ),
+        bind_group: renderer.uniform_bind_group().clone(),
+        shader_manager: &shader_manager,
+        prepared: false,
+    };
+    renderer
+        .render_with_ui([&scene], &mut panel, &context, &shader_manager)
+        .expect("Could not render frame");
+    assert!(panel.prepared);
+
+    let pixels = read_pixels(&context);
+    let [left, right] = [16, 48].map(|x| pixel(&pixels, x, 32));
+    assert!(left[0] > 200 && left[2] < 100, "{left:?}");
+    assert!(right[0] < 100 && right[2] > 200, "{right:?}");
+}
+
+#[test]
+fn packed_colors_draw_like_full_ones() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let layout = renderer.uniform_bind_group_layout().clone();
+    let rects = [0., 16., 32., 48.].map(|x| CenterRect {
+        color: Vector4::new([x / 64., 0.2, 1., 1.]),
+        ..rect([x + 8., 32.], [16., 64.])
+    });
+    let circle = Circle {
+        color: Vector4::new([1., 0.6, 0., 0.8]),
+        stroke_width: 4.,
+        ..circle([32., 32.], 20.)
+    };
+    let mut draw = |items: [&dyn Render; 2]| {
+        renderer
+            .render(items, &context, &shader_manager)
+            .expect("Could not render frame");
+        read_pixels(&context)
+    };
+
+    let full = draw([
+        &RectangleRenderer::new(rects.to_vec(), &layout, &context, &shader_manager),
+        &CircleRenderer::new(vec![circle], &layout, &context, &shader_manager),
+    ]);
+    let packed = draw([
+        &PackedRectRenderer::new(
+            rects.map(PackedRect::from).to_vec(),
+            &layout,
+            &context,
+            &shader_manager,
+        ),
+        &PackedCircleRenderer::new(vec![circle.into()], &layout, &context, &shader_manager),
+    ]);
+    // Channels are rounded to 8 bits either way, once when packing and once when
+    // writing the frame
+    assert!(full.iter().zip(&packed).all(|(a, b)| a.abs_diff(*b) <= 1));
+}
+
+#[test]
+fn packed_structs_match_full_ones() {
+    // Both sides are destructured and built without `..`, so adding a field to only
+    // one of the structs stops this from compiling
+    let unpack_circle = |packed: PackedCircle| {
+        let PackedCircle {
+            color,
+            position,
+            radius,
+            edge_softness,
+            stroke_width,
+            rotation,
+            layer,
+        } = packed;
+        Circle {
+            color: unpack_color(color),
+            position,
+            radius,
+            edge_softness,
+            stroke_width,
+            rotation,
+            layer,
+        }
+    };
+    let unpack_rect = |packed: PackedRect| {
+        let PackedRect {
+            color,
+            center,
+            size,
+            rotation,
+            layer,
+        } = packed;
+        CenterRect {
+            color: unpack_color(color),
+            center,
+            size,
+            rotation,
+            layer,
+        }
+    };
+
+    let circle = Circle {
+        color: Vector4::new([1., 0., 0.2, 0.6]),
+        edge_softness: 2.,
+        stroke_width: 3.,
+        rotation: 0.5,
+        layer: 7,
+        ..circle([4., 5.], 6.)
+    };
+    let rect = CenterRect {
+        rotation: 1.5,
+        layer: 3,
+        ..rect([1., 2.], [3., 4.])
+    };
+    assert_eq!(
+        bytemuck::bytes_of(&unpack_circle(circle.into())),
+        bytemuck::bytes_of(&circle)
+    );
+    assert_eq!(
+        bytemuck::bytes_of(&unpack_rect(rect.into())),
+        bytemuck::bytes_of(&rect)
+    );
+
+    // Only the color buffer differs
+    for (packed, full) in [
+        (PackedCircle::VERTEX_BUFFERS, Circle::VERTEX_BUFFERS),
+        (PackedRect::VERTEX_BUFFERS, CenterRect::VERTEX_BUFFERS),
+    ] {
+        assert_eq!(packed.len(), full.len());
+        assert_eq!(packed[0].array_stride, 4);
+        assert_eq!(packed[0].attributes[0].format, VertexFormat::Unorm8x4);
+        assert_eq!(packed[1..], full[1..]);
+    }
+}
+
+#[test]
+fn slice_renderer_grows() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let circle = circle([32., 32.], 16.);
+    let mut circles = SlicePrimitiveRenderer::from_slice(
+        &[circle; 2],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    circles.update(&[circle; 8], &context);
+    assert_eq!(circles.instance_count(), 8);
+    circles.update(&[circle], &context);
+    assert_eq!(circles.instance_count(), 1);
+    renderer
+        .render([&circles], &context, &shader_manager)
+        .expect("Could not render frame");
+}
+
+#[test]
+fn polygon_circle_segments() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let mut circles = PolygonCircleRenderer::new(
+        vec![circle([32., 32.], 16.)],
+        6,
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    assert_eq!(circles.pipeline_label(), Some("circle_polygon_6"));
+    renderer
+        .render([&circles], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    circles.set_segments(64, &context, &shader_manager);
+    assert_eq!(circles.pipeline_label(), Some("circle_polygon_64"));
+    renderer
+        .render([&circles], &context, &shader_manager)
+        .expect("Could not render frame");
+}
+
+#[test]
+fn frame_shares_draws() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let circles = CircleRenderer::new(
+        vec![circle([32., 32.], 16.)],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let mut zoomed = *renderer.uniform();
+    zoomed.scale_factor = 2.;
+
+    let mut frame = renderer
+        .begin_frame(&context)
+        .expect("Could not begin frame")
+        .expect("Headless frames are never skipped");
+    frame.draw([&circles], &shader_manager);
+    frame.push_clip(Vector2::new([0., 0.]), Vector2::new([16., 16.]));
+    frame.draw_with_uniform([&circles], zoomed, &shader_manager);
+    frame.draw_with_uniform([&circles], zoomed, &shader_manager);
+    assert!(frame.pop_clip().is_some());
+    frame.end_frame();
+    assert_eq!(renderer.last_stats().draw_calls, 3);
+    assert_eq!(renderer.last_stats().instances, 3);
+    // Later passes load the frame instead of clearing it, so the first draw survives
+    // outside of their clip
+    assert!(pixel(&read_pixels(&context), 32, 32)[0] > 200);
+
+    // An empty frame is still cleared and presented
+    renderer
+        .begin_frame(&context)
+        .expect("Could not begin frame")
+        .expect("Headless frames are never skipped")
+        .end_frame();
+    assert_eq!(renderer.last_stats(), RenderStats::default());
+}
+
+#[test]
+fn render_stats() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let circle = circle([32., 32.], 16.);
+    let circles = CircleRenderer::new(
+        vec![circle; 2],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    let rects = RectangleRenderer::new(
+        vec![rect([32., 32.], [8., 8.])],
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    renderer
+        .render([&circles as &dyn Render, &rects], &context, &shader_manager)
+        .expect("Could not render frame");
+
+    let expected = RenderStats {
+        draw_calls: 2,
+        instances: 3,
+        vertices: 2 * Circle::VERTEX_COUNT + CenterRect::VERTEX_COUNT,
+    };
+    assert_eq!(renderer.last_stats(), expected);
+    assert_eq!(circles.stats() + rects.stats(), expected);
+}
+
+#[test]
+fn gpu_timing() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let supported = context.features().contains(Features::TIMESTAMP_QUERY);
+    assert_eq!(renderer.set_gpu_timing(true, &context), supported);
+    assert_eq!(renderer.last_gpu_time(), None);
+
+    // The first frame is timed, the second one collects its timestamps
+    let empty: [&CircleRenderer; 0] = [];
+    renderer
+        .render(empty, &context, &shader_manager)
+        .expect("Could not render frame");
+    context.device().poll(Maintain::Wait);
+    renderer
+        .render(empty, &context, &shader_manager)
+        .expect("Could not render frame");
+    assert_eq!(renderer.last_gpu_time().is_some(), supported);
+
+    assert!(!renderer.set_gpu_timing(false, &context));
+    assert_eq!(renderer.last_gpu_time(), None);
+}
+
+#[test]
+fn texture_blend_modes() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    let blended = TextureRenderer::new(
+        renderer.uniform_bind_group_layout(),
+        Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        ColorWrites::ALL,
+        &context,
+        &shader_manager,
+    );
+    let mask = TextureRenderer::new(
+        renderer.uniform_bind_group_layout(),
+        None,
+        ColorWrites::ALPHA,
+        &context,
+        &shader_manager,
+    );
+    renderer
+        .render([&blended, &mask], &context, &shader_manager)
+        .expect("Could not render frame");
+}
+
+#[test]
+fn texture_premultiplies_on_upload() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    // Half transparent white with straight alpha
+    let draw = |renderer: &mut Renderer2D, premultiply: bool| {
+        let mut texture = TextureRenderer::from_texels(
+            &[[255, 255, 255, 128]],
+            1,
+            premultiply,
+            renderer.uniform_bind_group_layout(),
+            Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            ColorWrites::ALL,
+            &context,
+            &shader_manager,
+        )
+        .unwrap();
+        texture.rect_mut().center = Vector2::new([32., 32.]);
+        texture.rect_mut().size = Vector2::new([64., 64.]);
+        texture.sync(&context);
+        renderer
+            .render([&texture], &context, &shader_manager)
+            .expect("Could not render frame");
+        pixel(&read_pixels(&context), 32, 32)
+    };
+
+    // Straight alpha added as if premultiplied saturates, premultiplied blends halfway
+    assert!(draw(&mut renderer, false)[0] > 250);
+    let premultiplied = draw(&mut renderer, true)[0];
+    assert!((120..150).contains(&premultiplied), "{premultiplied}");
+}
+
+#[test]
+fn tiled_texture_scrolls() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let mut renderer = Renderer2D::new(&context);
+    // Red then blue, each covering 16 units
+    let mut tiled = TiledTextureRenderer::new(
+        &[[255, 0, 0, 255], [0, 0, 255, 255]],
+        2,
+        Vector2::new([32., 32.]),
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    )
+    .unwrap();
+    let red_at = |renderer: &mut Renderer2D, tiled: &TiledTextureRenderer, x: usize| {
+        renderer
+            .render([tiled], &context, &shader_manager)
+            .expect("Could not render frame");
+        let pixel = pixel(&read_pixels(&context), x, 8);
+        // Linear filtering blends in a little of the other texel
+        assert!(pixel[0].abs_diff(pixel[2]) > 150, "{pixel:?} at {x}");
+        pixel[0] > pixel[2]
+    };
+    assert!(red_at(&mut renderer, &tiled, 8));
+    assert!(!red_at(&mut renderer, &tiled, 24));
+    assert!(red_at(&mut renderer, &tiled, 40));
+
+    // Panning half a tile moves the texture with the world
+    renderer.get_uniform().view_port_origin = Vector2::new([16., 0.]);
+    renderer.update_uniform(&context);
+    assert!(!red_at(&mut renderer, &tiled, 8));
+    assert!(red_at(&mut renderer, &tiled, 24));
+
+    // Unless it's fixed to the screen
+    tiled.set_scroll_speed(Vector2::new([0., 0.]), &context);
+    assert!(red_at(&mut renderer, &tiled, 8));
+    assert!(!red_at(&mut renderer, &tiled, 24));
+}
+
+#[test]
+fn oversized_texture_is_an_error() {
+    let Some(context) = headless() else { return };
+    let shader_manager = ShaderManager::new("");
+    let renderer = Renderer2D::new(&context);
+    let max = context.limits().max_texture_dimension_2d;
+    let result = TiledTextureRenderer::new(
+        &vec![[0; 4]; max as usize + 1],
+        1,
+        Vector2::new([32., 32.]),
+        renderer.uniform_bind_group_layout(),
+        &context,
+        &shader_manager,
+    );
+    assert_eq!(
+        result.err(),
+        Some(TextureError::TooLarge {
+            requested: [1, max + 1],
+            max
+        })
+    );
+    assert!(check_texture_size([max, max], &context).is_ok());
+}
+
+#[test]
+fn draw_constants_fall_back_to_uniform() {
+    let descriptor = WGPUContextDescriptor {
+        optional_features: Features::empty(),
+        ..Default::default()
+    };
+    let Some(context) = headless_with(&descriptor) else {
+        return;
+    };
+    assert!(!context.supports_push_constants());
+
+    let mut tint = DrawConstants::new([1f32, 0., 0., 1.], ShaderStages::FRAGMENT, &context);
+    assert!(!tint.uses_push_constants());
+    assert_eq!(tint.push_constant_range().range, 0..16);
+    tint.set([0., 1., 0., 1.], &context);
+    assert_eq!(tint.data(), &[0., 1., 0., 1.]);
+}
+
+#[test]
+fn visible_region_follows_coordinate_system() {
+    let mut uniform = Uniform {
+        screen_size: Vector2::new([200., 100.]),
+        view_port_origin: Vector2::new([10., 10.]),
+        scale_factor: 2.,
+        coordinate_system: CoordinateSystem::TopLeftYDown as u32,
+        aa_px: 1.,
+        time: 0.,
+    };
+    let (min, size) = uniform.visible_region();
+    assert_eq!((*min, *size), ([10., 10.], [100., 50.]));
+
+    uniform.coordinate_system = CoordinateSystem::CenterYUp as u32;
+    let (min, size) = uniform.visible_region();
+    assert_eq!((*min, *size), ([-40., -15.], [100., 50.]));
+}
+
+#[test]
+fn screen_to_world_inverts_world_to_screen() {
+    let mut uniform = Uniform {
+        screen_size: Vector2::new([200., 100.]),
+        view_port_origin: Vector2::new([10., 10.]),
+        scale_factor: 2.,
+        coordinate_system: CoordinateSystem::TopLeftYDown as u32,
+        aa_px: 1.,
+        time: 0.,
+    };
+    assert_eq!(*uniform.screen_to_world(Vector2::new([0., 0.])), [10., 10.]);
+    assert_eq!(*uniform.screen_to_world(Vector2::new([200., 100.])), [110., 60.]);
+
+    uniform.coordinate_system = CoordinateSystem::CenterYUp as u32;
+    assert_eq!(*uniform.screen_to_world(Vector2::new([100., 50.])), [10., 10.]);
+    assert_eq!(*uniform.screen_to_world(Vector2::new([200., 0.])), [60., 35.]);
+
+    uniform.coordinate_system = CoordinateSystem::BottomLeftYUp as u32;
+    assert_eq!(*uniform.screen_to_world(Vector2::new([0., 100.])), [10., 10.]);
+
+    for coordinate_system in [
+        CoordinateSystem::TopLeftYDown,
+        CoordinateSystem::CenterYUp,
+        CoordinateSystem::BottomLeftYUp,
+    ] {
+        uniform.coordinate_system = coordinate_system as u32;
+        let screen = Vector2::new([30., 70.]);
+        let round_trip = uniform.world_to_screen(uniform.screen_to_world(screen));
+        assert_eq!(*round_trip, *screen);
+    }
+}
@@ -0,0 +1,198 @@
+//! Shapes grouped into a tree, each group placed relative to its parent, e.g. a turret
+//! on a tank.
+//!
+//! Every [SceneNode] has a [Transform] relative to its parent and shapes in its own space.
+//! [SceneNode::draw] walks the tree every frame and queues the shapes on a [Canvas] in
+//! world space, so moving a node moves everything below it
+
+use crate::math::Vector2;
+use crate::rendering::{Canvas, Shape};
+
+/// Translation, rotation and uniform scale, applied in reverse order.
+///
+/// Shear and non-uniform scale are left out on purpose, since they would turn circles
+/// into ellipses and rects into parallelograms, which the primitives can't draw
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub translation: Vector2<f32>,
+    /// Radians, in the same direction as [crate::rendering::CenterRect::rotation]
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::from_translation(Vector2::new([0., 0.]))
+    }
+}
+
+impl Transform {
+    pub fn new(translation: Vector2<f32>, rotation: f32, scale: f32) -> Self {
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn from_translation(translation: Vector2<f32>) -> Self {
+        Self::new(translation, 0., 1.)
+    }
+
+    /// Moves a point from the space this transform describes to the space of its parent
+    pub fn apply(&self, point: Vector2<f32>) -> Vector2<f32> {
+        (point * self.scale).rotate(self.rotation) + self.translation
+    }
+
+    /// The transform of a child placed at `local` in the space of `self`
+    pub fn then(&self, local: &Transform) -> Transform {
+        Self {
+            translation: self.apply(local.translation),
+            rotation: self.rotation + local.rotation,
+            scale: self.scale * local.scale,
+        }
+    }
+
+    /// `shape` moved from the space of this transform to the space of its parent
+    pub fn apply_to_shape(&self, shape: Shape) -> Shape {
+        match shape {
+            Shape::Rect(mut rect) => {
+                rect.center = self.apply(rect.center);
+                rect.size = rect.size * self.scale;
+                rect.rotation += self.rotation;
+                Shape::Rect(rect)
+            }
+            Shape::Circle(mut circle) => {
+                circle.position = self.apply(circle.position);
+                circle.radius *= self.scale;
+                circle.stroke_width *= self.scale;
+                circle.rotation += self.rotation;
+                Shape::Circle(circle)
+            }
+            Shape::Line(mut line) => {
+                line.start = self.apply(line.start);
+                line.end = self.apply(line.end);
+                line.width *= self.scale;
+                Shape::Line(line)
+            }
+        }
+    }
+}
+
+/// A group of shapes and child nodes, placed relative to the parent node
+#[derive(Clone, Default)]
+pub struct SceneNode {
+    local: Transform,
+    shapes: Vec<Shape>,
+    children: Vec<SceneNode>,
+}
+
+impl SceneNode {
+    pub fn new(local: Transform) -> Self {
+        Self {
+            local,
+            shapes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn local_transform(&self) -> &Transform {
+        &self.local
+    }
+
+    /// Moves this node and everything below it
+    pub fn set_local_transform(&mut self, local: Transform) {
+        self.local = local;
+    }
+
+    /// Adds a shape described in the space of this node
+    pub fn add_shape(&mut self, shape: impl Into<Shape>) {
+        self.shapes.push(shape.into());
+    }
+
+    pub fn shapes_mut(&mut self) -> &mut Vec<Shape> {
+        &mut self.shapes
+    }
+
+    /// Adds `child` below this node and returns it, e.g. to keep building the tree
+    pub fn add_child(&mut self, child: SceneNode) -> &mut SceneNode {
+        self.children.push(child);
+        self.children.last_mut().unwrap()
+    }
+
+    pub fn children(&self) -> &[SceneNode] {
+        &self.children
+    }
+
+    pub fn children_mut(&mut self) -> &mut [SceneNode] {
+        &mut self.children
+    }
+
+    /// Queues every shape of the tree on `canvas` in world space. Parents are queued
+    /// before their children
+    pub fn draw(&self, canvas: &mut Canvas) {
+        self.visit(&Transform::default(), &mut |shape| canvas.shape(shape));
+    }
+
+    /// Every shape of the tree in world space, in the order [Self::draw] queues them
+    pub fn world_shapes(&self) -> Vec<Shape> {
+        let mut shapes = Vec::new();
+        self.visit(&Transform::default(), &mut |shape| shapes.push(shape));
+        shapes
+    }
+
+    fn visit(&self, parent: &Transform, f: &mut impl FnMut(Shape)) {
+        let world = parent.then(&self.local);
+        for &shape in &self.shapes {
+            f(world.apply_to_shape(shape));
+        }
+        for child in &self.children {
+            child.visit(&world, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::rendering::Circle;
+
+    fn assert_close(a: Vector2<f32>, b: [f32; 2]) {
+        assert!((a - Vector2::new(b)).mag() < 1e-4, "{:?} != {b:?}", *a);
+    }
+
+    fn circle(shape: Shape) -> Circle {
+        match shape {
+            Shape::Circle(circle) => circle,
+            _ => panic!("Expected a circle"),
+        }
+    }
+
+    #[test]
+    fn children_follow_parents() {
+        let shape = |x: f32| Circle {
+            position: Vector2::new([x, 0.]),
+            radius: 2.,
+            ..Default::default()
+        };
+        let hull = Transform::new(Vector2::new([100., 50.]), FRAC_PI_2, 2.);
+        let turret = Transform::from_translation(Vector2::new([10., 0.]));
+        let mut tank = SceneNode::new(hull);
+        tank.add_shape(shape(0.));
+        tank.add_child(SceneNode::new(turret)).add_shape(shape(5.));
+
+        let shapes = tank.world_shapes();
+        assert_close(circle(shapes[0]).position, [100., 50.]);
+        assert_eq!(circle(shapes[0]).radius, 4.);
+        // 15 units along the rotated x axis, doubled by the scale
+        assert_close(circle(shapes[1]).position, [100., 80.]);
+        assert_eq!(circle(shapes[1]).radius, 4.);
+        assert_eq!(circle(shapes[1]).rotation, FRAC_PI_2);
+
+        // Moving the parent moves the child
+        tank.set_local_transform(Transform::default());
+        assert_close(circle(tank.world_shapes()[1]).position, [15., 0.]);
+    }
+}
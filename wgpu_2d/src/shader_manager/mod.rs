@@ -6,37 +6,15 @@ use wgpu::*;
 
 use std::borrow::Cow;
 use std::num::NonZeroU32;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 /// Manages loading and compilation of shaders from disk
 ///
-/// Uses unsafe code to allow taking shared references into the data while
-/// mutating the HashMaps. This use of unsafe has been thought through but
-/// it has not been fully verified. Specific safety comments can be found
-/// at the site of the unsafe blocks
-///
-/// Shared references to the shader modules and render pipelines can be obtained
-/// with the same lifetime as the shared reference to self instead of to the MutexGuard.
-/// This should be okay as long as
-/// 	a) The actual heap allocation of the box is never moved or modified
-/// 	b) The hashmap never removes or replaces a shader module or render pipeline through
-/// 	   a shared reference
-///
-/// Mutable references are allowed to modify the data in any way they want. This is used to
-/// clear the data to allow for hot-reloading
-///
-/// Unanswered questions about safety
-///	- The code below creates mutable references to the box (`&mut Box<T>`). Care is taken to
-///   avoid a shared reference to the actual box (i.e. `&Box<T>`) as that would immediately be UB.
-///	  However, the returned reference (`&'a T`) is derived from such a shared reference. It is
-/// 	  unclear if this is also UB
-///
-///	- A simplified version of the shader manager which requires no OS code (file opening)
-///	  was tested with miri in the test module below. It detected no UB in the current configuration
-///		- A sanity check was performed by returning a static refernce from get_module and it
-/// 		  correctly identified the UB
-/// - This seems to suggest that this use of unsafe is indeed sound, but further research is needed
-///
+/// Sources, shader modules and render pipelines are cached behind [Arc]s and handed out
+/// by value, so callers hold on to what they got even if the caches are cleared by
+/// [Self::reload] in the meantime. Cloning an [Arc] is cheap, and wgpu keeps anything a
+/// render pass uses alive until it is submitted, so the pipeline returned by
+/// [Self::get_render_pipeline] can be dropped as soon as it is set
 
 /// # TODO: 
 /// - Change all these panics to return a result instead
@@ -54,7 +32,7 @@ pub struct ShaderManager {
 	/// [Self::constant_source_files]
 	///
 	/// These are removed by [Self::reload] 
-    source_files: RwLock<HashMap<Box<str>, Box<str>>>,
+    source_files: RwLock<HashMap<Box<str>, Arc<str>>>,
 	/// Stores Shader source files that are not stored on the disk
 	/// but are stored within the final binary
 	///
@@ -66,11 +44,11 @@ pub struct ShaderManager {
 	/// [Self::source_files]
 	///
 	/// These are not removed when [Self::reload] is called
-	constant_source_files: RwLock<HashMap<Box<str>, Box<str>>>,
+	constant_source_files: RwLock<HashMap<Box<str>, Arc<str>>>,
 	/// Cached [ShaderModule]s
 	///
 	/// [ShaderModule]s are returned from here if available
-    shader_modules: RwLock<HashMap<Box<str>, Box<ShaderModule>>>,
+    shader_modules: RwLock<HashMap<Box<str>, Arc<ShaderModule>>>,
 	/// Cached [RenderPipeline]s 
 	///
 	/// [RenderPipeline]s are returned from here if available
@@ -79,38 +57,31 @@ pub struct ShaderManager {
             Box<str>,
             (
                 RenderPipelineDescriptorTemplate,
-                Option<Box<RenderPipeline>>,
+                Option<Arc<RenderPipeline>>,
             ),
         >,
     >,
+	/// Used when compiling pipelines whose template has no [RenderPipelineDescriptorTemplate::cache]
+	pipeline_cache: RwLock<Option<PipelineCache>>,
+	/// Overrides [PrimitiveState::polygon_mode] of every template, see [Self::set_polygon_mode]
+	// Locked after render_pipelines whenever both are held
+	polygon_mode: RwLock<PolygonMode>,
 }
 
 /// Internal Implementations
 impl ShaderManager {
 	/// Searches [Self::source_files] for the given path and returns it if present
 	/// or tries to read it from disk and if found, caches and returns it
-	fn get_file_from_disk<'a>(&'a self, path: &str) -> Option<&'a str> {
-		match self.source_files.read().unwrap().get(path) {
-			// SAFETY: The only thing that can invalidate the lifetime of the returned reference
-			// is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-			//
-			// The returned reference's lifetime is tied to the shared borrow of self and we do not
-			// allow any operations with a shared reference to self to drop or remove any element
-			// from the map
-			Some(file) => return Some(unsafe{extend_lifetime(&**file)}),
-			None => (),
+	fn get_file_from_disk(&self, path: &str) -> Option<Arc<str>> {
+		if let Some(file) = self.source_files.read().unwrap().get(path) {
+			return Some(Arc::clone(file));
 		}
-		match read_to_string(self.directory_path.to_string() + &*path) {
+		match read_to_string(self.directory_path.to_string() + path) {
 			Ok(file) => {
-				// SAFETY: The only thing that can invalidate the lifetime of the returned reference
-				// is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-				//
-				// The returned reference's lifetime is tied to the shared borrow of self and we do not
-				// allow any operations with a shared reference to self to drop or remove any element
-				// from the map 
-				//
-				// This insert uses entry.or_insert which does not insert an element if it already exists
-				Some(unsafe{extend_lifetime(self.source_files.write().unwrap().entry(path.into()).or_insert(file.into()))})
+				// Another thread may have read the file in the meantime, keep whichever came first
+				Some(Arc::clone(
+					self.source_files.write().unwrap().entry(path.into()).or_insert(file.into())
+				))
 			}
 			Err(err) if err.kind() == ErrorKind::NotFound => {
 				None
@@ -122,21 +93,17 @@ impl ShaderManager {
 	}
 
 	/// Searches [Self::constant_source_files] for the given path and returns it if present
-	fn get_file_from_constant_source<'a>(&'a self, path: &str) -> Option<&'a str> {
-		match self.constant_source_files.read().unwrap().get(path) {
-			// SAFETY: The only thing that can invalidate the lifetime of the returned reference
-			// is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-			//
-			// The returned reference's lifetime is tied to the shared borrow of self and we do not
-			// allow any operations with a shared reference to self to drop or remove any element
-			// from the map
-			Some(file) => return Some(unsafe{extend_lifetime(&**file)}),
-			None => None,
-		}
+	fn get_file_from_constant_source(&self, path: &str) -> Option<Arc<str>> {
+		self.constant_source_files.read().unwrap().get(path).cloned()
+	}
+
+	/// Calls [Self::try_get_source] and panics on error
+	fn get_source_new(&self, path: &str) -> String {
+		self.try_get_source(path).unwrap_or_else(|err| panic!("{err}"))
 	}
 
 	/// Gets the source file and then iteratively expands each of the include statements
-	fn get_source_new<'a>(&'a self, path: &str) -> String {
+	fn try_get_source(&self, path: &str) -> Result<String, ShaderError> {
 		// At this point, we know the shader source is not cached
         log::debug!("source file not already loaded: {:?}", path);
 
@@ -146,13 +113,9 @@ impl ShaderManager {
 
 		let mut source = match (disk_source_file, const_source_file) {
 			(Some(source), None) | (None, Some(source)) => source,
-			// If both return a source file or neither return one, then panic
-			(Some(_), Some(_)) => {
-				panic!("Requested shader path {} is available on disk and in constant shaders", path);
-			}
-			(None, None) => {
-				panic!("Requested shader path {} not found on disk or in constant shaders", path);
-			}
+			// If both return a source file or neither return one, then return an error
+			(Some(_), Some(_)) => return Err(ShaderError::AmbiguousSource(path.into())),
+			(None, None) => return Err(ShaderError::MissingSource(path.into())),
 		}.to_string();
 
 		let mut includes: HashSet<Box<str>> = HashSet::new();
@@ -165,7 +128,7 @@ impl ShaderManager {
 
 		while let Some((line, include)) = find_next_include(&source) {
 			if !includes.insert(include.into()) {
-				panic!("Include path {} already seen when processing file {}", include, path);
+				return Err(ShaderError::RepeatedInclude { include: include.into(), path: path.into() });
 			}
 			// create string slice from start of string to beginning of line with include
 			//
@@ -179,7 +142,7 @@ impl ShaderManager {
 				// SAFETY: line is derived from source and is guaranteed by safe code to be within 
 				// valid range for source
 				let offset = unsafe {
-					source.as_ptr().offset_from(line.as_ptr())
+					line.as_ptr().offset_from(source.as_ptr())
 				}.try_into().expect("line must be after or equal to source start");
 				// SAFETY: 
 				// - start is the guaranteed to be the start of a utf-8 char it is equal to the start of source
@@ -199,13 +162,9 @@ impl ShaderManager {
 
 				match (disk_source_file, const_source_file) {
 					(Some(source), None) | (None, Some(source)) => source,
-					// If both return a source file or neither return one, then panic
-					(Some(_), Some(_)) => {
-						panic!("Requested shader path {} is available on disk and in constant shaders", path);
-					}
-					(None, None) => {
-						panic!("Requested shader path {} not found on disk or in constant shaders", path);
-					}
+					// If both return a source file or neither return one, then return an error
+					(Some(_), Some(_)) => return Err(ShaderError::AmbiguousSource(include.into())),
+					(None, None) => return Err(ShaderError::MissingSource(include.into())),
 				}
 			};
 			let last = {
@@ -220,7 +179,7 @@ impl ShaderManager {
 				// create new string slice
 				
 				let start_offset: usize = usize::try_from(unsafe {
-					source.as_ptr().offset_from(line.as_ptr())
+					line.as_ptr().offset_from(source.as_ptr())
 				}).expect("line must be after or equal to source start")
 				+ line.len();
 
@@ -242,10 +201,10 @@ impl ShaderManager {
 				}
 			};
 
-			source = first.to_string() + middle + last;
+			source = first.to_string() + &middle + last;
 		}
 
-		return source;
+		return Ok(source);
 
 		// Go line by line and find the first line that contains an include directive
 		// if its present
@@ -275,30 +234,16 @@ impl ShaderManager {
 
 	/// Internal API for resolving a [ShaderModule] or returning an existing
 	/// [ShaderModule]
-    fn get_module<'a>(&'a self, path: &str, context: &WGPUContext) -> &'a ShaderModule {
-        // SAFETY: The only thing that can invalidate the lifetime of the returned reference
-        // is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-        //
-        // The returned reference's lifetime is tied to the shared borrow of self and we do not
-        // allow any operations with a shared reference to self to drop or remove any element
-        // from the map
-		match self.shader_modules.read().unwrap().get(path) {
-			Some(value) => return unsafe{extend_lifetime(value)},
-			None => (),
-		}
-        // SAFETY: The only thing that can invalidate the lifetime of the returned reference
-        // is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-        //
-        // The returned reference's lifetime is tied to the shared borrow of self and we do not
-        // allow any operations with a shared reference to self to drop or remove any element
-        // from the map
-		//
-		// This insert uses entry.or_insert which does not insert an element if it already exists
-		unsafe {
-			extend_lifetime(&**self.shader_modules.write().unwrap()
-			.entry(path.into())
-			.or_insert(Box::new(self.read_and_get_module(path, context))))
+    fn get_module(&self, path: &str, context: &WGPUContext) -> Arc<ShaderModule> {
+		if let Some(module) = self.shader_modules.read().unwrap().get(path) {
+			return Arc::clone(module);
 		}
+		let module = self.read_and_get_module(path, context);
+		Arc::clone(
+			self.shader_modules.write().unwrap()
+				.entry(path.into())
+				.or_insert_with(|| Arc::new(module))
+		)
     }
 
 	/// Called the first time a [RenderPipeline] with a specific label is requested after 
@@ -312,17 +257,97 @@ impl ShaderManager {
 		// - Get the modules
 		// - Create the pipeline descriptor
 		// - Compile it
+		if let Err(err) = template.check_push_constants(context) {
+			panic!("{err}");
+		}
         let paths = template.get_module_paths();
         let modules = (
             self.get_module(paths.0, context),
             paths.1.map(|x| self.get_module(x, context)),
         );
-        let descriptor = template.resolve(modules.0, modules.1);
+        let pipeline_cache = self.pipeline_cache.read().unwrap().clone();
+        let mut descriptor = template.resolve(&modules.0, modules.1.as_deref());
+        descriptor.cache = descriptor.cache.or(pipeline_cache.as_ref());
+        descriptor.primitive.polygon_mode = *self.polygon_mode.read().unwrap();
 
         context.device().create_render_pipeline(&descriptor)
     }
+
+	/// Compiles and caches the pipeline registered with the given label if it isn't already,
+	/// returning any error instead of panicking
+	fn try_compile_pipeline(&self, label: &str, context: &WGPUContext) -> Result<(), ShaderError> {
+		let template = match self.render_pipelines.read().unwrap().get(label) {
+			Some((_, Some(_))) => return Ok(()),
+			Some((template, None)) => template.clone(),
+			None => return Err(ShaderError::MissingPipeline(label.into())),
+		};
+
+		// Make sure every source resolves so that compiling does not panic
+		template.check_push_constants(context)?;
+		let paths = template.get_module_paths();
+		self.try_get_source(paths.0)?;
+		if let Some(path) = paths.1 {
+			self.try_get_source(path)?;
+		}
+
+		context.device().push_error_scope(ErrorFilter::Validation);
+		let pipeline = self.compile_pipeline(&template, context);
+		if let Some(error) = pollster::block_on(context.device().pop_error_scope()) {
+			return Err(ShaderError::Compilation { label: label.into(), error: error.to_string() });
+		}
+
+		if let Some((_, x)) = self.render_pipelines.write().unwrap().get_mut(label) {
+			x.get_or_insert_with(|| Arc::new(pipeline));
+		}
+		Ok(())
+	}
+}
+
+/// Errors that can occur while resolving shader sources and compiling pipelines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShaderError {
+	/// No source file was found on disk or registered as a constant source at this path
+	MissingSource(String),
+	/// A source file exists both on disk and as a constant source at this path
+	AmbiguousSource(String),
+	/// The same file was included more than once while expanding `path`
+	RepeatedInclude { include: String, path: String },
+	/// No pipeline was registered with this label
+	MissingPipeline(String),
+	/// wgpu rejected the shader module or pipeline
+	Compilation { label: String, error: String },
+	/// The pipeline declares push constants but the device does not support them
+	/// or they exceed [Limits::max_push_constant_size]
+	UnsupportedPushConstants(String),
 }
 
+impl std::fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::MissingSource(path) => {
+				write!(f, "Requested shader path {path} not found on disk or in constant shaders")
+			}
+			Self::AmbiguousSource(path) => {
+				write!(f, "Requested shader path {path} is available on disk and in constant shaders")
+			}
+			Self::RepeatedInclude { include, path } => {
+				write!(f, "Include path {include} already seen when processing file {path}")
+			}
+			Self::MissingPipeline(label) => {
+				write!(f, "No render pipeline registered with label {label}")
+			}
+			Self::Compilation { label, error } => {
+				write!(f, "Could not compile render pipeline {label}: {error}")
+			}
+			Self::UnsupportedPushConstants(label) => {
+				write!(f, "Render pipeline {label} uses push constants which the device does not support")
+			}
+		}
+	}
+}
+
+impl std::error::Error for ShaderError {}
+
 /// Public Interface
 impl ShaderManager {
 	/// Creates a new [ShaderManager]
@@ -333,47 +358,46 @@ impl ShaderManager {
 			constant_source_files: RwLock::new(HashMap::new()),
             shader_modules: RwLock::new(HashMap::new()),
             render_pipelines: RwLock::new(HashMap::new()),
+            pipeline_cache: RwLock::new(None),
+            polygon_mode: RwLock::new(PolygonMode::Fill),
         }
     }
 
+	/// Sets the cache used for compiling pipelines from now on, see
+	/// [WGPUContext::create_pipeline_cache]. Already compiled pipelines are not affected,
+	/// so set this before requesting any
+	pub fn set_pipeline_cache(&self, cache: Option<PipelineCache>) {
+		*self.pipeline_cache.write().unwrap() = cache;
+	}
+
+	/// Contents of the pipeline cache, to be saved to disk and passed to
+	/// [WGPUContext::create_pipeline_cache] on the next run. Empty if there is no cache
+	/// or the backend has nothing to store
+	pub fn pipeline_cache_data(&self) -> Vec<u8> {
+		self.pipeline_cache
+			.read()
+			.unwrap()
+			.as_ref()
+			.and_then(PipelineCache::get_data)
+			.unwrap_or_default()
+	}
+
 	/// Returns an already compiled pipeline with the [RenderPipelineDescriptor] template 
 	/// registered with the given label.
 	///
 	/// If such a pipeline does not exist yet, compile one using the given template
-    pub fn get_render_pipeline<'a>(
-        &'a self,
-        label: &str,
-        context: &WGPUContext,
-    ) -> &'a RenderPipeline {
+    pub fn get_render_pipeline(&self, label: &str, context: &WGPUContext) -> Arc<RenderPipeline> {
 		match self.render_pipelines.read().unwrap().get(label) {
-			// SAFETY: The only thing that can invalidate the lifetime of the returned reference
-			// is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-			//
-			// The returned reference's lifetime is tied to the shared borrow of self and we do not
-			// allow any operations with a shared reference to self to drop or remove any element
-			// from the map
-			Some((_, Some(pipeline))) => return unsafe{extend_lifetime(pipeline)},
+			Some((_, Some(pipeline))) => return Arc::clone(pipeline),
 			Some((_, None)) => (),
 			None => {
 				panic!("Attempted to obtain render pipeline with label that wasn't registered: {}", label);
 			}
 		}
 
-		match self.render_pipelines.write().unwrap().get_mut(label).unwrap() {
-			(template, x) => {
-				// SAFETY: The only thing that can invalidate the lifetime of the returned reference
-				// is if the backing Box is deallocated (moving a box does not invalidate pointers into it)
-				//
-				// The returned reference's lifetime is tied to the shared borrow of self and we do not
-				// allow any operations with a shared reference to self to drop or remove any element
-				// from the map
-				//
-				// This insert uses Option.get_or_insert_with which does not insert an element if it already exists
-				unsafe{extend_lifetime(
-					x.get_or_insert_with(|| Box::new(self.compile_pipeline(template, context)))
-				)}
-			}
-		}
+		let mut pipelines = self.render_pipelines.write().unwrap();
+		let (template, x) = pipelines.get_mut(label).unwrap();
+		Arc::clone(x.get_or_insert_with(|| Arc::new(self.compile_pipeline(template, context))))
     }
 
 	/// Registers a specific [RenderPipelineDescriptorTemplate] with a label.
@@ -387,11 +411,7 @@ impl ShaderManager {
 			Some(_) => return,
 			None => (),
 		}
-		// we only have shared access to self here so there may be borrows into
-		// any existing pipeline here.
-		// we must take care not to remove any existing render pipelines
-		//
-		// entry.or_insert ensures any existign render pipelines are left alone
+		// entry.or_insert leaves a pipeline registered by another thread in the meantime alone
         self.render_pipelines.write().unwrap().entry(label.into())
 			.or_insert((template, None));
     }
@@ -412,7 +432,7 @@ impl ShaderManager {
 		let mut lock = self.constant_source_files
 			.write().unwrap();
 		match lock.get(path) {
-			Some(old_source) if *old_source == source => (),
+			Some(old_source) if **old_source == *source => (),
 			Some(old_source) => {
 				panic!("Conflicting source files registered at path {}: \n\n Old Source : {} \n\n New Source: {} \n\n",
 					path,
@@ -420,15 +440,22 @@ impl ShaderManager {
 					source,
 				);
 			}
-			None => {lock.insert(path.into(), source);},
+			None => {lock.insert(path.into(), source.into());},
 		}
 	}
 
+	/// Returns the source at `path` with all includes expanded, exactly as it would be
+	/// compiled. Sources are looked up on disk and among constant sources like they are
+	/// for pipelines, but nothing is compiled.
+	///
+	/// Useful for inspecting the final source when a shader fails to compile
+	pub fn resolved_source(&self, path: &str) -> Result<String, ShaderError> {
+		self.try_get_source(path)
+	}
+
 	/// Remove all resolved shaders and pipelines
     pub fn reload(&mut self) {
-        // These mutable operations are fine because we have mutable access to self
-        // so there are no borrows of this data
-		// TODO: Change these locks to get_mut
+		// Anything handed out before stays alive through its own Arc
         self.source_files.get_mut().unwrap().clear();
         self.shader_modules.get_mut().unwrap().clear();
         self.render_pipelines
@@ -437,16 +464,79 @@ impl ShaderManager {
             .iter_mut()
             .for_each(|(_, (_, x))| *x = None);
     }
-}
 
-/// This is intended for use in ShaderManager to extend the lifetimes of the shader 
-/// source and shader modules to the lifetime of the ShaderModule reference instead 
-/// of the [Mutex] lock obtained within each function.
-///
-/// Safety comments at each call location will refer here instead of providing 
-/// justification at callsite
-unsafe fn extend_lifetime<'a, 'b, T: ?Sized>(input: &'a T) -> &'b T {
-	unsafe {&*(input as *const T)}
+	/// Compiles every registered pipeline that isn't compiled yet and reports the result
+	/// for each label, sorted by label.
+	///
+	/// Unlike [Self::get_render_pipeline] this does not panic on missing sources, and
+	/// validation errors from wgpu are returned instead of going to the device's error
+	/// handler. Pipelines that fail are left uncompiled. Intended to be called right
+	/// after [Self::reload] to validate all shaders at once.
+	///
+	/// Validation errors are caught with a device error scope, so other threads should
+	/// not use the device while this runs
+	pub fn warm_up(&self, context: &WGPUContext) -> Vec<(String, Result<(), ShaderError>)> {
+		let mut labels = self.render_pipelines.read().unwrap()
+			.keys()
+			.map(|label| label.to_string())
+			.collect::<Vec<_>>();
+		labels.sort();
+		labels.into_iter()
+			.map(|label| {
+				let result = self.try_compile_pipeline(&label, context);
+				(label, result)
+			})
+			.collect()
+	}
+
+	/// Changes the directory dynamic shaders are read from.
+	///
+	/// Like [Self::reload], this drops all files read from disk, compiled shader modules
+	/// and compiled pipelines, since they may have come from the old directory. Constant
+	/// sources and pipeline templates are kept, so nothing has to be registered again.
+	pub fn set_directory(&mut self, directory_path: &str) {
+		self.directory_path = directory_path.into();
+		self.reload();
+	}
+
+	/// Directory dynamic shaders are read from
+	pub fn directory(&self) -> &str {
+		&self.directory_path
+	}
+
+	/// Draws the triangles of every pipeline as outlines ([PolygonMode::Line]) or corner
+	/// points ([PolygonMode::Point]) instead of filling them, to see the geometry behind
+	/// a shape. Meant for debugging only, it replaces the polygon mode of every template.
+	///
+	/// Compiled pipelines are dropped so they get recompiled with the new mode. Line and
+	/// point modes need [Features::POLYGON_MODE_LINE] and [Features::POLYGON_MODE_POINT].
+	/// If the device lacks the feature a warning is logged, nothing changes and false is
+	/// returned
+	pub fn set_polygon_mode(&self, mode: PolygonMode, context: &WGPUContext) -> bool {
+		let feature = match mode {
+			PolygonMode::Fill => Features::empty(),
+			PolygonMode::Line => Features::POLYGON_MODE_LINE,
+			PolygonMode::Point => Features::POLYGON_MODE_POINT,
+		};
+		if !context.features().contains(feature) {
+			log::warn!("Polygon mode {mode:?} needs {feature:?}, which the device doesn't have");
+			return false;
+		}
+		// Locked in the same order as get_render_pipeline, which reads the mode while
+		// holding the pipelines
+		let mut pipelines = self.render_pipelines.write().unwrap();
+		let mut polygon_mode = self.polygon_mode.write().unwrap();
+		if *polygon_mode != mode {
+			*polygon_mode = mode;
+			pipelines.values_mut().for_each(|(_, pipeline)| *pipeline = None);
+		}
+		true
+	}
+
+	/// See [Self::set_polygon_mode]
+	pub fn polygon_mode(&self) -> PolygonMode {
+		*self.polygon_mode.read().unwrap()
+	}
 }
 
 /// A template that can be used to instantiate a [`RenderPipelineDescriptor`]
@@ -468,8 +558,16 @@ pub struct RenderPipelineDescriptorTemplate {
     pub fragment: Option<FragmentStateTemplate>,
 	/// Corresponds to [`RenderPipelineDescriptor::multiview`]
     pub multiview: Option<NonZeroU32>,
-	/// Corresponds to [`RenderPipelineDescriptor::cache`]
+	/// Corresponds to [`RenderPipelineDescriptor::cache`]. When `None` the cache set with
+	/// [ShaderManager::set_pipeline_cache] is used
     pub cache: Option<&'static PipelineCache>,
+	/// The push constant ranges declared in [Self::layout]. Checked against the device
+	/// before compiling so that a missing [Features::PUSH_CONSTANTS] is reported as
+	/// [ShaderError::UnsupportedPushConstants] instead of a validation error
+	pub push_constant_ranges: Box<[PushConstantRange]>,
+	/// Values of pipeline-overridable constants, by name or `@id`. Used by both the
+	/// vertex and fragment stage. Corresponds to [PipelineCompilationOptions::constants]
+	pub constants: HashMap<String, f64>,
 }
 
 impl RenderPipelineDescriptorTemplate {
@@ -486,11 +584,14 @@ impl RenderPipelineDescriptorTemplate {
         RenderPipelineDescriptor {
             label: self.label,
             layout: self.layout.as_ref(),
-            vertex: self.vertex.resolve(v_module),
+            vertex: self.vertex.resolve(v_module, &self.constants),
             primitive: self.primitive,
             depth_stencil: self.depth_stencil.clone(),
             multisample: self.multisample,
-            fragment: self.fragment.as_ref().map(|x| x.resolve(f_module.unwrap())),
+            fragment: self
+                .fragment
+                .as_ref()
+                .map(|x| x.resolve(f_module.unwrap(), &self.constants)),
             multiview: self.multiview,
             cache: self.cache,
         }
@@ -507,12 +608,22 @@ impl RenderPipelineDescriptorTemplate {
             self.fragment.as_ref().map(|x| x.get_module_path()),
         )
     }
+
+	/// Checks that the device can run a pipeline with [Self::push_constant_ranges]
+	fn check_push_constants(&self, context: &WGPUContext) -> Result<(), ShaderError> {
+		let size = self.push_constant_ranges.iter().map(|x| x.range.end).max().unwrap_or(0);
+		if size == 0 || (context.supports_push_constants() && size <= context.limits().max_push_constant_size) {
+			Ok(())
+		} else {
+			Err(ShaderError::UnsupportedPushConstants(self.label.unwrap_or_default().into()))
+		}
+	}
 }
 
 /// A template that can be used to instantiate a [VertexState]
 ///
-/// This does not support overridable constants so [VertexState::compilation_options] does 
-/// not have an equivalent here
+/// Overridable constants are shared between stages and set with
+/// [RenderPipelineDescriptorTemplate::constants]
 #[derive(Debug, Clone, PartialEq)]
 pub struct VertexStateTemplate {
 	/// The path of the shader file relative to the shader source of the [ShaderManager] this gets passed to
@@ -531,12 +642,18 @@ impl VertexStateTemplate {
 	/// The template module path is replaced with the module parameter.
 	///
 	/// The caller is responsible for ensuring the correct module is passed
-    fn resolve<'a>(&self, module: &'a ShaderModule) -> VertexState<'a> {
+    fn resolve<'a>(
+        &self,
+        module: &'a ShaderModule,
+        constants: &'a HashMap<String, f64>,
+    ) -> VertexState<'a> {
         VertexState {
             module,
             entry_point: self.entry_point,
-            // We do not support overridable constants here
-            compilation_options: Default::default(),
+            compilation_options: PipelineCompilationOptions {
+                constants,
+                ..Default::default()
+            },
             buffers: self.buffers,
         }
     }
@@ -549,8 +666,8 @@ impl VertexStateTemplate {
 
 /// A template that can be used to instantiate a [FragmentState]
 ///
-/// This does not support overridable constants so [FragmentState::compilation_options] does 
-/// not have an equivalent here
+/// Overridable constants are shared between stages and set with
+/// [RenderPipelineDescriptorTemplate::constants]
 #[derive(Debug, Clone, PartialEq)]
 pub struct FragmentStateTemplate {
 	/// The path of the shader file relative to the shader source of the [ShaderManager] this gets passed to
@@ -569,12 +686,18 @@ impl FragmentStateTemplate {
 	/// The template module path is replaced with the module parameter.
 	///
 	/// The caller is responsible for ensuring the correct module is passed
-    fn resolve<'a>(&'a self, module: &'a ShaderModule) -> FragmentState<'a> {
+    fn resolve<'a>(
+        &'a self,
+        module: &'a ShaderModule,
+        constants: &'a HashMap<String, f64>,
+    ) -> FragmentState<'a> {
         FragmentState {
             module,
             entry_point: self.entry_point,
-            // We do not support overridable constants here
-            compilation_options: Default::default(),
+            compilation_options: PipelineCompilationOptions {
+                constants,
+                ..Default::default()
+            },
             targets: &self.targets,
         }
     }
@@ -584,3 +707,224 @@ impl FragmentStateTemplate {
         self.module_path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{headless, headless_with};
+
+    const GOOD_SHADER: &str = "
+@vertex
+fn v_main(@builtin(vertex_index) v_id: u32) -> @builtin(position) vec4<f32> {
+	return vec4<f32>(f32(v_id), 0., 0., 1.);
+}
+
+@fragment
+fn f_main() -> @location(0) vec4<f32> {
+	return vec4<f32>(1., 1., 1., 1.);
+}
+";
+
+    // Several pipelines in one file, picked by entry point
+    const SHARED_SHADER: &str = "
+@vertex
+fn left_vs(@builtin(vertex_index) v_id: u32) -> @builtin(position) vec4<f32> {
+	return vec4<f32>(-f32(v_id), 0., 0., 1.);
+}
+
+@vertex
+fn right_vs(@builtin(vertex_index) v_id: u32) -> @builtin(position) vec4<f32> {
+	return vec4<f32>(f32(v_id), 0., 0., 1.);
+}
+
+@fragment
+fn red_fs() -> @location(0) vec4<f32> {
+	return vec4<f32>(1., 0., 0., 1.);
+}
+
+@fragment
+fn blue_fs() -> @location(0) vec4<f32> {
+	return vec4<f32>(0., 0., 1., 1.);
+}
+";
+
+    fn template(
+        module_path: &'static str,
+        context: &WGPUContext,
+    ) -> RenderPipelineDescriptorTemplate {
+        RenderPipelineDescriptorTemplate {
+            label: None,
+            layout: None,
+            vertex: VertexStateTemplate {
+                module_path,
+                entry_point: None,
+                buffers: &[],
+            },
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(FragmentStateTemplate {
+                module_path,
+                entry_point: None,
+                targets: Box::new([Some(context.config().format.into())]),
+            }),
+            multiview: None,
+            cache: None,
+            push_constant_ranges: Box::new([]),
+            constants: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn warm_up_reports_each_pipeline() {
+        let Some(context) = headless() else { return };
+
+        let shader_manager = ShaderManager::new("");
+        shader_manager.register_constant_source("good.wgsl", GOOD_SHADER.into());
+        shader_manager.register_constant_source("bad.wgsl", "fn v_main( {".into());
+        shader_manager.register_render_pipeline("good", template("good.wgsl", &context));
+        shader_manager.register_render_pipeline("bad", template("bad.wgsl", &context));
+        shader_manager.register_render_pipeline("missing", template("missing.wgsl", &context));
+
+        let results = shader_manager.warm_up(&context);
+        let labels = results
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["bad", "good", "missing"]);
+        assert!(matches!(results[0].1, Err(ShaderError::Compilation { .. })));
+        assert_eq!(results[1].1, Ok(()));
+        assert_eq!(
+            results[2].1,
+            Err(ShaderError::MissingSource("missing.wgsl".into()))
+        );
+    }
+
+    #[test]
+    fn named_entry_points_share_module() {
+        let Some(context) = headless() else { return };
+
+        let shader_manager = ShaderManager::new("");
+        shader_manager.register_constant_source("shared.wgsl", SHARED_SHADER.into());
+        let named = |vertex, fragment| {
+            let mut template = template("shared.wgsl", &context);
+            template.vertex.entry_point = Some(vertex);
+            template.fragment.as_mut().unwrap().entry_point = Some(fragment);
+            template
+        };
+        shader_manager.register_render_pipeline("left", named("left_vs", "red_fs"));
+        shader_manager.register_render_pipeline("right", named("right_vs", "blue_fs"));
+        shader_manager.register_render_pipeline("unknown", named("missing_vs", "red_fs"));
+        // Without a name the entry point is ambiguous
+        shader_manager.register_render_pipeline("unnamed", template("shared.wgsl", &context));
+
+        let results = shader_manager.warm_up(&context);
+        assert_eq!(results[0], ("left".into(), Ok(())));
+        assert_eq!(results[1], ("right".into(), Ok(())));
+        assert!(matches!(results[2].1, Err(ShaderError::Compilation { .. })));
+        assert!(matches!(results[3].1, Err(ShaderError::Compilation { .. })));
+        // Both stages of every pipeline use the same module
+        assert_eq!(shader_manager.shader_modules.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn resolved_source_expands_includes() {
+        let shader_manager = ShaderManager::new("");
+        shader_manager.register_constant_source("common.wgsl", "const TWO: f32 = 2.;".into());
+        shader_manager.register_constant_source(
+            "main.wgsl",
+            "#include<common.wgsl>\nconst FOUR: f32 = TWO * TWO;".into(),
+        );
+
+        assert_eq!(
+            shader_manager.resolved_source("main.wgsl"),
+            Ok("const TWO: f32 = 2.;\nconst FOUR: f32 = TWO * TWO;".into())
+        );
+        assert_eq!(
+            shader_manager.resolved_source("missing.wgsl"),
+            Err(ShaderError::MissingSource("missing.wgsl".into()))
+        );
+    }
+
+    #[test]
+    fn resolves_includes_after_first_line() {
+        let shader_manager = ShaderManager::new("");
+        shader_manager.register_constant_source("one.wgsl", "const ONE: f32 = 1.;".into());
+        shader_manager.register_constant_source("two.wgsl", "const TWO: f32 = 2.;".into());
+        shader_manager.register_constant_source(
+            "main.wgsl",
+            "#include<one.wgsl>\n#include<two.wgsl>\nconst THREE: f32 = ONE + TWO;".into(),
+        );
+
+        assert_eq!(
+            shader_manager.resolved_source("main.wgsl"),
+            Ok("const ONE: f32 = 1.;\nconst TWO: f32 = 2.;\nconst THREE: f32 = ONE + TWO;".into())
+        );
+    }
+
+    #[test]
+    fn pipeline_cache() {
+        let descriptor = crate::wgpu_context::WGPUContextDescriptor {
+            optional_features: Features::PIPELINE_CACHE,
+            ..Default::default()
+        };
+        let Some(context) = headless_with(&descriptor) else { return };
+
+        let shader_manager = ShaderManager::new("");
+        assert!(shader_manager.pipeline_cache_data().is_empty());
+        // SAFETY: No data is passed
+        let cache = unsafe { context.create_pipeline_cache(None) };
+        assert_eq!(cache.is_some(), context.features().contains(Features::PIPELINE_CACHE));
+        shader_manager.set_pipeline_cache(cache);
+
+        shader_manager.register_constant_source("good.wgsl", GOOD_SHADER.into());
+        shader_manager.register_render_pipeline("good", template("good.wgsl", &context));
+        shader_manager.get_render_pipeline("good", &context);
+        let data = shader_manager.pipeline_cache_data();
+
+        // Data from a previous run is accepted
+        // SAFETY: The data comes from a cache of the same device
+        let cache = unsafe { context.create_pipeline_cache(Some(&data)) };
+        assert_eq!(cache.is_some(), context.features().contains(Features::PIPELINE_CACHE));
+    }
+
+    #[test]
+    fn pipelines_outlive_reload() {
+        let Some(context) = headless() else { return };
+
+        let mut shader_manager = ShaderManager::new("");
+        shader_manager.register_constant_source("good.wgsl", GOOD_SHADER.into());
+        shader_manager.register_render_pipeline("good", template("good.wgsl", &context));
+        let first = shader_manager.get_render_pipeline("good", &context);
+        assert!(Arc::ptr_eq(&first, &shader_manager.get_render_pipeline("good", &context)));
+
+        shader_manager.reload();
+        let second = shader_manager.get_render_pipeline("good", &context);
+        assert!(!Arc::ptr_eq(&first, &second));
+        // The old pipeline is still ours to use
+        assert_eq!(Arc::strong_count(&first), 1);
+        assert_eq!(Arc::strong_count(&second), 2);
+    }
+
+    #[test]
+    fn push_constants_require_support() {
+        let Some(context) = headless() else { return };
+
+        let mut template = template("good.wgsl", &context);
+        assert_eq!(template.check_push_constants(&context), Ok(()));
+
+        template.label = Some("tinted");
+        template.push_constant_ranges = Box::new([PushConstantRange {
+            stages: ShaderStages::FRAGMENT,
+            range: 0..16,
+        }]);
+        if context.supports_push_constants() {
+            assert_eq!(template.check_push_constants(&context), Ok(()));
+        } else {
+            assert_eq!(
+                template.check_push_constants(&context),
+                Err(ShaderError::UnsupportedPushConstants("tinted".into()))
+            );
+        }
+    }
+}
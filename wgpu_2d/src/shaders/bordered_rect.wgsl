@@ -0,0 +1,52 @@
+#include<common.wgsl>
+#include<renderer_constants.wgsl>
+struct Rect {
+	@location(0) color: vec4<f32>,
+	@location(1) center: vec2<f32>,
+	@location(2) size: vec2<f32>,
+	@location(3) rotation: f32,
+	@location(4) border_color: vec4<f32>,
+	@location(5) border_width: f32,
+	@location(6) layer: u32,
+}
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+	@location(1) @interpolate(flat) border_color: vec4<f32>,
+	// Position relative to the center before rotating, in world units
+	@location(2) local: vec2<f32>,
+	@location(3) @interpolate(flat) half_size: vec2<f32>,
+	@location(4) @interpolate(flat) border_width: f32,
+}
+
+@vertex 
+fn v_main (rect: Rect, @builtin(vertex_index) v_id: u32) -> V2F {
+	let rotation_matrix = mat2x2<f32> (
+		vec2<f32>(cos(rect.rotation), -sin(rect.rotation)),
+		vec2<f32>(sin(rect.rotation), cos(rect.rotation)),
+	);
+	let local = quad_strip[v_id] * rect.size / 2.;
+	let pos = local * rotation_matrix + rect.center;
+
+	var output: V2F;
+	output.color = rect.color * renderer_constants.tint;
+	output.border_color = rect.border_color * renderer_constants.tint;
+	output.local = local;
+	output.half_size = rect.size / 2.;
+	output.border_width = rect.border_width;
+	output.position = vec4<f32>(worldspace_to_clipspace(pos), layer_depth(rect.layer), 1.);
+	return output;
+}
+
+// The border covers everything closer to an edge than its width
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	let edges = v2f.half_size - abs(v2f.local);
+	let edge_distance = min(edges.x, edges.y);
+	// Signed distance to the inner edge of the border, negative in the fill
+	let fill = aa_alpha(v2f.border_width - edge_distance);
+	// The outer edge fades like every other shape
+	let outer = aa_alpha(-edge_distance);
+	return mix(v2f.border_color, v2f.color, select(fill, 1., v2f.border_width <= 0.)) * outer;
+}
@@ -1,39 +1,82 @@
 #include<common.wgsl>
+#include<renderer_constants.wgsl>
 
 struct Circle {
 	@location(0) color: vec4<f32>,
 	@location(1) center: vec2<f32>,
 	@location(2) radius: f32,
+	@location(3) edge_softness: f32,
+	@location(4) stroke_width: f32,
+	@location(5) rotation: f32,
+	@location(6) layer: u32,
 }
 
 struct V2F {
 	@builtin(position) position: vec4<f32>,
 	@location(0) color: vec4<f32>,
+	// Position in the rotated frame of the circle, scaled so the edge is at length 1
 	@location(1) uv: vec2<f32>,
+	@location(2) @interpolate(flat) edge_softness: f32,
+	// Radius of the hole as a fraction of the radius. Negative for filled circles
+	@location(3) @interpolate(flat) inner_ratio: f32,
+	@location(4) @interpolate(flat) id: u32,
 }
 
 // Vertex Shader outputs a quad along with the UV coordinates
 @vertex 
-fn v_main (circle: Circle, @builtin(vertex_index) v_id: u32) -> V2F {
+fn v_main (
+	circle: Circle,
+	@builtin(vertex_index) v_id: u32,
+	@builtin(instance_index) i_id: u32,
+) -> V2F {
 	let pos = quad_strip[v_id] * circle.radius + circle.center;
 
 	let clip_space = worldspace_to_clipspace(pos);
 
 	var output: V2F;
-	output.color = circle.color;
-	output.uv = quad_strip[v_id];
-	output.position = vec4<f32>(clip_space, 0., 1.);
+	output.color = circle.color * renderer_constants.tint;
+	// The quad doesn't have to turn to cover the circle, only its coordinates do
+	let corner = quad_strip[v_id];
+	let c = cos(circle.rotation);
+	let s = sin(circle.rotation);
+	output.uv = vec2<f32>(c * corner.x + s * corner.y, c * corner.y - s * corner.x);
+	output.position = vec4<f32>(clip_space, layer_depth(circle.layer), 1.);
+	output.edge_softness = circle.edge_softness;
+	output.id = i_id + 1u;
+	output.inner_ratio = -1.;
+	if circle.stroke_width > 0. {
+		output.inner_ratio = 1. - circle.stroke_width / circle.radius;
+	}
 	/* output.position = vec4<f32>(0., 0., 0., 1.); */
 	return output;
 }
 
 // Actual Circle is rendered in the fragment shader
+fn coverage(v2f: V2F) -> f32 {
+	let radius = length(v2f.uv);
+	let distance = radius - 1.;
+	// The wider of the two fades wins
+	let outer = min(aa_alpha(distance), edge_coverage(distance, v2f.edge_softness));
+	// Outlines fade on the inside of the band as well. Filled circles are far from it
+	let inner_distance = v2f.inner_ratio - radius;
+	let inner = min(aa_alpha(inner_distance), edge_coverage(inner_distance, v2f.edge_softness));
+	return min(outer, inner);
+}
+
 @fragment
 fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
-	let mag = clamp(dot(v2f.uv, v2f.uv) * (-50.) + 50, 0., 1.);
-
+	let mag = coverage(v2f);
 	if mag == 0. {
 		discard;
 	}
 	return v2f.color * mag;
 }
+
+@fragment
+fn f_main_id(v2f: V2F) -> ColorAndId {
+	let mag = coverage(v2f);
+	if mag == 0. {
+		discard;
+	}
+	return ColorAndId(v2f.color * mag, v2f.id);
+}
@@ -0,0 +1,45 @@
+#include<common.wgsl>
+
+// Number of triangles around the center of each circle
+override SEGMENTS: u32 = 32u;
+
+struct Circle {
+	@location(0) color: vec4<f32>,
+	@location(1) center: vec2<f32>,
+	@location(2) radius: f32,
+	@location(3) edge_softness: f32,
+	@location(6) layer: u32,
+}
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+}
+
+// Every 3 vertices form a triangle between the center and two neighbouring points on the
+// edge. Edges are not antialiased
+@vertex
+fn v_main (circle: Circle, @builtin(vertex_index) v_id: u32) -> V2F {
+	let segment = v_id / 3u;
+	let corner = v_id % 3u;
+
+	var offset = vec2<f32>(0., 0.);
+	if corner != 0u {
+		let angle = f32(segment + corner - 1u) * 6.28318530718 / f32(SEGMENTS);
+		offset = vec2<f32>(cos(angle), sin(angle)) * circle.radius;
+	}
+
+	var output: V2F;
+	output.color = circle.color;
+	output.position = vec4<f32>(
+		worldspace_to_clipspace(circle.center + offset),
+		layer_depth(circle.layer),
+		1.,
+	);
+	return output;
+}
+
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	return v2f.color;
+}
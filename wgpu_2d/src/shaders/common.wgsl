@@ -3,17 +3,88 @@
 // TODO: Move bindings to separate files
 
 struct Uniform {
+	// Size of the surface in physical pixels
 	screen_size: vec2<f32>,
 	view_port_origin: vec2<f32>,
+	// Physical pixels per logical pixel. World space is in logical pixels
+	scale_factor: f32,
+	// 0: origin top left, y down. 1: origin at the center, y up. 2: origin bottom left, y up
+	coordinate_system: u32,
+	// Width of antialiased edges in physical pixels
+	aa_px: f32,
+	// Seconds since the renderer started. Not wrapped, so precision drops in long sessions
+	time: f32,
 }
 
 // Requires uniform binding
 fn worldspace_to_clipspace (input: vec2<f32>) -> vec2<f32> {
-	return (input - uni.view_port_origin) / uni.screen_size * vec2<f32>(2., -2) + vec2<f32>(-1, 1.);
+	let scaled = (input - uni.view_port_origin) * uni.scale_factor / uni.screen_size * 2.;
+	switch uni.coordinate_system {
+		case 1u: {
+			return scaled;
+		}
+		case 2u: {
+			return scaled - vec2<f32>(1., 1.);
+		}
+		default: {
+			return scaled * vec2<f32>(1., -1.) + vec2<f32>(-1., 1.);
+		}
+	}
+}
+
+// Inverse of worldspace_to_clipspace. Requires uniform binding
+fn clipspace_to_worldspace (input: vec2<f32>) -> vec2<f32> {
+	var scaled: vec2<f32>;
+	switch uni.coordinate_system {
+		case 1u: {
+			scaled = input;
+		}
+		case 2u: {
+			scaled = input + vec2<f32>(1., 1.);
+		}
+		default: {
+			scaled = (input - vec2<f32>(-1., 1.)) * vec2<f32>(1., -1.);
+		}
+	}
+	return scaled / 2. * uni.screen_size / uni.scale_factor + uni.view_port_origin;
 }
 
 @group(0) @binding(0) var<uniform> uni: Uniform;
 
+// Antialiased coverage of a shape near one of its edges. `distance` is the signed distance
+// to the edge in any unit, negative inside the shape. The fade covers `softness` pixels
+// just inside the edge. fwidth converts pixels to the unit of `distance`, so the fade has
+// the same width at any zoom. Must be called from uniform control flow
+fn edge_coverage(distance: f32, softness: f32) -> f32 {
+	let width = max(fwidth(distance) * softness, 1e-6);
+	return 1. - smoothstep(-width, 0., distance);
+}
+
+// Edge coverage with the antialias width from the uniform. Every SDF primitive should use
+// this so edges look the same across shapes. Requires uniform binding
+fn aa_alpha(signed_distance: f32) -> f32 {
+	return edge_coverage(signed_distance, uni.aa_px);
+}
+
+// Depth of an instance on the given layer, closer for higher layers. Stays strictly
+// inside (0, 1) so nothing is clipped. Layers above 65535 are clamped
+fn layer_depth(layer: u32) -> f32 {
+	return 1. - (f32(min(layer, 65535u)) + 1.) / 65537.;
+}
+
+// Output of the `f_main_id` entry points, which also write the ID of the instance for
+// picking. IDs are the instance index plus one, so 0 is left for the background
+struct ColorAndId {
+	@location(0) color: vec4<f32>,
+	@location(1) id: u32,
+}
+
+// Inverse of pack_color on the CPU, for packed colors read from storage buffers.
+// Vertex attributes with the Unorm8x4 format arrive unpacked already
+fn unpack_color(packed: u32) -> vec4<f32> {
+	return unpack4x8unorm(packed);
+}
+
 const quad_strip = array(
 	vec2<f32>(-1., -1.),
 	vec2<f32>( 1., -1.),
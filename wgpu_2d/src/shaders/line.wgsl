@@ -0,0 +1,38 @@
+#include<common.wgsl>
+#include<renderer_constants.wgsl>
+
+struct Line {
+	@location(0) color: vec4<f32>,
+	@location(1) start: vec2<f32>,
+	@location(2) end: vec2<f32>,
+	@location(3) width: f32,
+	@location(4) layer: u32,
+}
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+}
+
+// The quad spans from start to end along x and the width of the line along y
+@vertex 
+fn v_main (line: Line, @builtin(vertex_index) v_id: u32) -> V2F {
+	let direction = line.end - line.start;
+	let length = length(direction);
+	var normal = vec2<f32>(0., 1.);
+	if length > 0. {
+		normal = vec2<f32>(-direction.y, direction.x) / length;
+	}
+	let corner = quad_strip[v_id];
+	let pos = mix(line.start, line.end, corner.x * 0.5 + 0.5) + normal * corner.y * line.width / 2.;
+
+	var output: V2F;
+	output.color = line.color * renderer_constants.tint;
+	output.position = vec4<f32>(worldspace_to_clipspace(pos), layer_depth(line.layer), 1.);
+	return output;
+}
+
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	return v2f.color;
+}
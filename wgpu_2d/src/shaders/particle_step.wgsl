@@ -0,0 +1,31 @@
+// Integrates the particles of a `GpuParticleSystem` over one time step
+
+struct Particle {
+	color: vec4<f32>,
+	position: vec2<f32>,
+	velocity: vec2<f32>,
+	acceleration: vec2<f32>,
+	size: f32,
+	lifetime: f32,
+}
+
+struct Step {
+	// Seconds since the last step
+	dt: f32,
+	count: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(1) var<uniform> step: Step;
+
+@compute @workgroup_size(64)
+fn c_main(@builtin(global_invocation_id) id: vec3<u32>) {
+	if id.x >= step.count || particles[id.x].lifetime <= 0. {
+		return;
+	}
+	var particle = particles[id.x];
+	particle.velocity += particle.acceleration * step.dt;
+	particle.position += particle.velocity * step.dt;
+	particle.lifetime -= step.dt;
+	particles[id.x] = particle;
+}
@@ -0,0 +1,34 @@
+#include<common.wgsl>
+#include<renderer_constants.wgsl>
+// Only the fields of `Particle` needed for drawing. The step shader updates the rest
+struct Particle {
+	@location(0) color: vec4<f32>,
+	@location(1) position: vec2<f32>,
+	@location(2) size: f32,
+	@location(3) lifetime: f32,
+}
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+}
+
+@vertex 
+fn v_main (
+	particle: Particle,
+	@builtin(vertex_index) v_id: u32,
+) -> V2F {
+	// Dead particles collapse to a point, which covers no pixels
+	let size = select(0., particle.size, particle.lifetime > 0.);
+	let pos = quad_strip[v_id] * size / 2. + particle.position;
+
+	var output: V2F;
+	output.color = particle.color * renderer_constants.tint;
+	output.position = vec4<f32>(worldspace_to_clipspace(pos), layer_depth(0u), 1.);
+	return output;
+}
+
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	return v2f.color;
+}
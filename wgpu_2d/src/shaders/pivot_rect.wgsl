@@ -0,0 +1,47 @@
+#include<common.wgsl>
+#include<renderer_constants.wgsl>
+struct Rect {
+	@location(0) color: vec4<f32>,
+	@location(1) position: vec2<f32>,
+	@location(2) size: vec2<f32>,
+	@location(3) rotation: f32,
+	@location(4) pivot: vec2<f32>,
+	@location(5) layer: u32,
+}
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+	@location(1) @interpolate(flat) id: u32,
+}
+
+@vertex 
+fn v_main (
+	rect: Rect,
+	@builtin(vertex_index) v_id: u32,
+	@builtin(instance_index) i_id: u32,
+) -> V2F {
+	let rotation_matrix = mat2x2<f32> (
+		vec2<f32>(cos(rect.rotation), -sin(rect.rotation)),
+		vec2<f32>(sin(rect.rotation), cos(rect.rotation)),
+	);
+	// Corner relative to the pivot, so the rotation happens about it
+	let local = (quad_strip[v_id] * 0.5 + 0.5 - rect.pivot) * rect.size;
+	let pos = local * rotation_matrix + rect.position;
+
+	var output: V2F;
+	output.color = rect.color * renderer_constants.tint;
+	output.id = i_id + 1u;
+	output.position = vec4<f32>(worldspace_to_clipspace(pos), layer_depth(rect.layer), 1.);
+	return output;
+}
+
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	return v2f.color;
+}
+
+@fragment
+fn f_main_id(v2f: V2F) -> ColorAndId {
+	return ColorAndId(v2f.color, v2f.id);
+}
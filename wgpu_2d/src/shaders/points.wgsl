@@ -12,7 +12,7 @@ struct V2F {
 
 @vertex
 fn v_main(point: Point) -> V2F {
-	let clip_space = worldspace_to_clipspace(pos);
+	let clip_space = worldspace_to_clipspace(point.position);
 
 	var output: V2F;
 	output.color = point.color;
@@ -0,0 +1,27 @@
+#include<common.wgsl>
+
+struct Vertex {
+	@location(0) color: vec4<f32>,
+	@location(1) position: vec2<f32>,
+}
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+}
+
+// Ribbon vertices are generated on the CPU so they only need to be transformed
+@vertex
+fn v_main(vertex: Vertex) -> V2F {
+	let clip_space = worldspace_to_clipspace(vertex.position);
+
+	var output: V2F;
+	output.color = vertex.color;
+	output.position = vec4<f32>(clip_space, 0., 1.);
+	return output;
+}
+
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	return v2f.color;
+}
@@ -0,0 +1,10 @@
+// Constants set per renderer rather than per instance, see `RendererConstants`
+
+struct RendererConstants {
+	// Multiplied with the color of every instance. White leaves colors unchanged
+	tint: vec4<f32>,
+	// Seconds, as set by the renderer. Not updated automatically
+	time: f32,
+}
+
+@group(1) @binding(0) var<uniform> renderer_constants: RendererConstants;
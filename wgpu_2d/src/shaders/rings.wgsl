@@ -1,10 +1,13 @@
 #include<common.wgsl>
+#include<renderer_constants.wgsl>
 
 struct Ring {
 	@location(0) color: vec4<f32>,
 	@location(1) center: vec2<f32>,
 	@location(2) outer_radius: f32,
 	@location(3) inner_radius: f32,
+	@location(4) edge_softness: f32,
+	@location(5) layer: u32,
 }
 
 struct V2F {
@@ -12,33 +15,55 @@ struct V2F {
 	@location(0) color: vec4<f32>,
 	@location(1) uv: vec2<f32>,
 	@location(2) radius_ratio: f32,
+	@location(3) @interpolate(flat) edge_softness: f32,
+	@location(4) @interpolate(flat) id: u32,
 }
 
 
 // Vertex Shader outputs a quad along with the UV coordinates
 @vertex 
-fn v_main (ring: Ring, @builtin(vertex_index) v_id: u32) -> V2F {
+fn v_main (
+	ring: Ring,
+	@builtin(vertex_index) v_id: u32,
+	@builtin(instance_index) i_id: u32,
+) -> V2F {
 	let pos = quad_strip[v_id] * ring.outer_radius + ring.center;
 
 	let clip_space = worldspace_to_clipspace(pos);
 
 	var output: V2F;
-	output.color = ring.color;
+	output.color = ring.color * renderer_constants.tint;
 	output.uv = quad_strip[v_id];
-	output.position = vec4<f32>(clip_space, 0., 1.);
+	output.position = vec4<f32>(clip_space, layer_depth(ring.layer), 1.);
 	output.radius_ratio = ring.inner_radius / ring.outer_radius;
+	output.edge_softness = ring.edge_softness;
+	output.id = i_id + 1u;
 	/* output.position = vec4<f32>(0., 0., 0., 1.); */
 	return output;
 }
 
 // Actual Circle is rendered in the fragment shader
+fn coverage(v2f: V2F) -> f32 {
+	let radius = length(v2f.uv);
+	// The wider of the two fades wins on each edge
+	let outer = min(aa_alpha(radius - 1.), edge_coverage(radius - 1., v2f.edge_softness));
+	let inner_distance = v2f.radius_ratio - radius;
+	let inner = min(aa_alpha(inner_distance), edge_coverage(inner_distance, v2f.edge_softness));
+
+	return min(outer, inner);
+}
+
 @fragment
 fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
-	let mag1 = clamp(dot(v2f.uv, v2f.uv) * -100 + 100, 0., 1.);
-	let mag2 = clamp(dot(v2f.uv, v2f.uv) * 100 - 100 * v2f.radius_ratio, 0., 1.);
-
-	let mag = min(mag1, mag2);
-	
-	return v2f.color * mag;
+	return v2f.color * coverage(v2f);
 	/* return vec4<f32>(1.); */
 }
+
+@fragment
+fn f_main_id(v2f: V2F) -> ColorAndId {
+	let mag = coverage(v2f);
+	if mag == 0. {
+		discard;
+	}
+	return ColorAndId(v2f.color * mag, v2f.id);
+}
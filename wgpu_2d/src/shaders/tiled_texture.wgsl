@@ -0,0 +1,37 @@
+#include<common.wgsl>
+
+struct Tiling {
+	// Size of one repetition of the texture in world units
+	tile_size: vec2<f32>,
+	// How far the texture moves when the camera pans, relative to the world. 1 moves
+	// with the world, 0 stays fixed on the screen
+	scroll_speed: vec2<f32>,
+}
+
+@group(1) @binding(0) var<uniform> tiling: Tiling;
+@group(1) @binding(1) var tex: texture_2d<f32>;
+@group(1) @binding(2) var samp: sampler;
+
+struct V2F {
+	@builtin(position) position: vec4<f32>,
+	@location(0) world: vec2<f32>,
+}
+
+// A quad covering the whole screen, with the world position of each corner
+@vertex 
+fn v_main (@builtin(vertex_index) v_id: u32) -> V2F {
+	var output: V2F;
+	output.position = vec4<f32>(quad_strip[v_id], layer_depth(0u), 1.);
+	output.world = clipspace_to_worldspace(quad_strip[v_id]);
+	return output;
+}
+
+@fragment
+fn f_main(v2f: V2F) -> @location(0) vec4<f32> {
+	// The part of the camera movement the texture doesn't follow
+	let offset = uni.view_port_origin * (1. - tiling.scroll_speed);
+	let uv = (v2f.world - offset) / tiling.tile_size;
+	// Keep the image upright when y points up
+	let flip = select(vec2<f32>(1., -1.), vec2<f32>(1., 1.), uni.coordinate_system == 0u);
+	return textureSample(tex, samp, uv * flip);
+}
@@ -0,0 +1,106 @@
+//! Helpers shared by the tests that need a GPU
+
+use wgpu::*;
+
+use crate::math::{Vector2, Vector4};
+use crate::rendering::{CenterRect, Circle};
+use crate::wgpu_context::WGPUContext;
+
+/// Width and height of the context made by [headless]
+pub const SIZE: u32 = 64;
+
+/// A headless context of [SIZE] by [SIZE] pixels. Machines without any adapter (e.g. CI
+/// without a software rasterizer) can't run GPU tests, so this returns `None` after
+/// noting the skipped test and the test should return early
+pub fn headless() -> Option<WGPUContext> {
+    headless_with(&Default::default())
+}
+
+/// Like [headless] with a custom descriptor
+pub fn headless_with(
+    descriptor: &crate::wgpu_context::WGPUContextDescriptor,
+) -> Option<WGPUContext> {
+    WGPUContext::try_new_headless([SIZE, SIZE], descriptor)
+        .inspect_err(|err| {
+            // Tests run on threads named after them
+            let test = std::thread::current().name().unwrap_or("test").to_owned();
+            eprintln!("No adapter available ({err}), skipping {test}");
+        })
+        .ok()
+}
+
+/// Reads back the offscreen texture of a [headless] context as RGBA8 rows from the top
+pub fn read_pixels(context: &WGPUContext) -> Vec<u8> {
+    read_texture(context.offscreen_texture().unwrap(), context)
+}
+
+/// Reads back the first layer of `texture` as tightly packed rows from the top, with as
+/// many bytes per texel as its format has
+pub fn read_texture(texture: &Texture, context: &WGPUContext) -> Vec<u8> {
+    let texel_size = texture
+        .format()
+        .block_copy_size(None)
+        .expect("Texture format can't be copied");
+    let row_size = texture.width() * texel_size;
+    let padded_row_size = row_size.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback = context.device().create_buffer(&BufferDescriptor {
+        label: None,
+        size: (padded_row_size * texture.height()) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = context.get_encoder();
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        TexelCopyBufferInfo {
+            buffer: &readback,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            depth_or_array_layers: 1,
+            ..texture.size()
+        },
+    );
+    context.queue().submit([encoder.finish()]);
+    readback
+        .slice(..)
+        .map_async(MapMode::Read, |result| result.unwrap());
+    context.device().poll(Maintain::Wait);
+    let padded = readback.slice(..).get_mapped_range();
+    padded
+        .chunks(padded_row_size as usize)
+        .flat_map(|row| &row[..row_size as usize])
+        .copied()
+        .collect()
+}
+
+/// The RGBA8 pixel at `x`, `y` of [read_pixels]
+pub fn pixel(pixels: &[u8], x: usize, y: usize) -> [u8; 4] {
+    pixels[(y * SIZE as usize + x) * 4..][..4]
+        .try_into()
+        .unwrap()
+}
+
+/// A white circle
+pub fn circle(position: [f32; 2], radius: f32) -> Circle {
+    Circle {
+        position: Vector2::new(position),
+        radius,
+        ..Default::default()
+    }
+}
+
+/// A white, unrotated rectangle on layer 0
+pub fn rect(center: [f32; 2], size: [f32; 2]) -> CenterRect {
+    CenterRect {
+        color: Vector4::new([1., 1., 1., 1.]),
+        center: Vector2::new(center),
+        size: Vector2::new(size),
+        rotation: 0.,
+        layer: 0,
+    }
+}
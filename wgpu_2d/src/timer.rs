@@ -1,4 +1,5 @@
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 pub struct Timer {
     start_time: Instant,
@@ -25,3 +26,168 @@ impl Timer {
         self.last_reset.elapsed().as_secs_f32()
     }
 }
+
+/// Caps the frame rate by sleeping out the rest of each frame.
+///
+/// Useful when presenting without vsync, where the render loop would otherwise run as
+/// fast as it can. Call [Self::wait] once per frame, e.g. before rendering
+pub struct FrameLimiter {
+    timer: Timer,
+    frame_time: Option<Duration>,
+}
+
+impl FrameLimiter {
+    // Sleeping can overshoot by about this much, so the end of each frame is spun out
+    const SPIN_TIME: Duration = Duration::from_millis(1);
+
+    /// `None` doesn't limit the frame rate
+    pub fn new(target_fps: Option<u32>) -> Self {
+        let mut limiter = Self {
+            timer: Timer::new(),
+            frame_time: None,
+        };
+        limiter.set_target_fps(target_fps);
+        limiter
+    }
+
+    /// `None` (or 0) stops limiting the frame rate
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) {
+        self.frame_time = target_fps
+            .filter(|&fps| fps > 0)
+            .map(|fps| Duration::from_secs(1) / fps);
+    }
+
+    /// The shortest time a frame can take. `None` if the frame rate isn't limited
+    pub fn target_frame_time(&self) -> Option<Duration> {
+        self.frame_time
+    }
+
+    /// Blocks until the target frame time has passed since the last call, then starts
+    /// the next frame. Returns immediately if the frame rate isn't limited or the frame
+    /// already took longer
+    pub fn wait(&mut self) {
+        if let Some(frame_time) = self.frame_time {
+            let elapsed = Duration::from_secs_f32(self.timer.elapsed_reset());
+            if let Some(remaining) = frame_time.checked_sub(elapsed + Self::SPIN_TIME) {
+                std::thread::sleep(remaining);
+            }
+            while Duration::from_secs_f32(self.timer.elapsed_reset()) < frame_time {
+                std::hint::spin_loop();
+            }
+        }
+        self.timer.reset();
+    }
+}
+
+/// Rolling frame time statistics over the last `capacity` frames
+///
+/// The app feeds each frame's delta in with [Self::record_frame]
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "FrameStats capacity must be non-zero");
+        Self {
+            frame_times: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a frame's delta, evicting the oldest one once `capacity` is reached
+    pub fn record_frame(&mut self, delta: Duration) {
+        if self.frame_times.len() == self.capacity {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(delta);
+    }
+
+    /// Average frame time over the recorded frames. Zero if nothing was recorded
+    pub fn avg_frame_time(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32
+    }
+
+    /// Frames per second derived from [Self::avg_frame_time]
+    pub fn fps(&self) -> f32 {
+        match self.avg_frame_time().as_secs_f32() {
+            0. => 0.,
+            avg => 1. / avg,
+        }
+    }
+
+    /// Frame time at percentile `p` (0.0 to 1.0) of the recorded frames,
+    /// e.g. `percentile(0.99)` for the 99th percentile
+    pub fn percentile(&self, p: f32) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut sorted = self.frame_times.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let index = ((sorted.len() - 1) as f32 * p.clamp(0., 1.)).round() as usize;
+        sorted[index]
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_times.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats_average() {
+        let mut stats = FrameStats::new(4);
+        for ms in [10, 20, 30, 40] {
+            stats.record_frame(Duration::from_millis(ms));
+        }
+        assert_eq!(stats.avg_frame_time(), Duration::from_millis(25));
+        assert!((stats.fps() - 40.).abs() < 1e-3);
+
+        // Oldest frame (10ms) is evicted
+        stats.record_frame(Duration::from_millis(50));
+        assert_eq!(stats.frame_count(), 4);
+        assert_eq!(stats.avg_frame_time(), Duration::from_millis(35));
+    }
+
+    #[test]
+    fn frame_stats_percentile() {
+        let mut stats = FrameStats::new(100);
+        (1..=100).for_each(|ms| stats.record_frame(Duration::from_millis(ms)));
+        assert_eq!(stats.percentile(0.), Duration::from_millis(1));
+        assert_eq!(stats.percentile(0.99), Duration::from_millis(99));
+        assert_eq!(stats.percentile(1.), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn frame_limiter_waits() {
+        let mut limiter = FrameLimiter::new(Some(100));
+        assert_eq!(limiter.target_frame_time(), Some(Duration::from_millis(10)));
+        limiter.wait();
+        let start = Instant::now();
+        limiter.wait();
+        limiter.wait();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        limiter.set_target_fps(Some(0));
+        assert_eq!(limiter.target_frame_time(), None);
+        limiter.set_target_fps(None);
+        let start = Instant::now();
+        limiter.wait();
+        assert!(start.elapsed() < Duration::from_millis(10));
+    }
+
+    #[test]
+    fn frame_stats_empty() {
+        let stats = FrameStats::new(8);
+        assert_eq!(stats.avg_frame_time(), Duration::ZERO);
+        assert_eq!(stats.fps(), 0.);
+        assert_eq!(stats.percentile(0.5), Duration::ZERO);
+    }
+}
@@ -6,7 +6,7 @@ pub use buffers::*;
 pub struct WGPUContext {
     #[allow(dead_code)]
     instance: Instance,
-    surface: Surface<'static>,
+    target: RenderTarget,
     #[allow(dead_code)]
     adapter: Adapter,
     device: Device,
@@ -14,49 +14,347 @@ pub struct WGPUContext {
     config: SurfaceConfiguration,
 }
 
+/// What a [WGPUContext] renders into
+enum RenderTarget {
+    /// A window surface
+    Surface(Surface<'static>),
+    /// An offscreen texture used by headless contexts
+    Offscreen(Texture),
+}
+
+/// The texture being rendered to for a single frame. Obtained with
+/// [WGPUContext::get_current_texture]
+pub enum FrameTexture {
+    Surface(SurfaceTexture),
+    Offscreen(Texture),
+}
+
+impl FrameTexture {
+    pub fn texture(&self) -> &Texture {
+        match self {
+            Self::Surface(surface_texture) => &surface_texture.texture,
+            Self::Offscreen(texture) => texture,
+        }
+    }
+
+    /// Presents the frame to the window. Does nothing for offscreen textures
+    pub fn present(self) {
+        match self {
+            Self::Surface(surface_texture) => surface_texture.present(),
+            Self::Offscreen(_) => (),
+        }
+    }
+}
+
+/// Options used when creating a [WGPUContext]
+#[derive(Debug, Clone)]
+pub struct WGPUContextDescriptor {
+    /// Features the device must support. Context creation fails if the
+    /// adapter does not support all of them
+    pub required_features: Features,
+    /// Features that are enabled only if the adapter supports them
+    pub optional_features: Features,
+    /// Picks an sRGB surface format when the surface supports one. Shaders then output
+    /// linear colors that are encoded on write, so blending and the clear color look the
+    /// same on every platform. Textures holding sRGB image data should be created with
+    /// [WGPUContext::image_texture_format] to be decoded when sampled.
+    ///
+    /// With `false` a non-sRGB format is preferred and colors are written unchanged
+    pub prefer_srgb: bool,
+    /// Usages requested for the surface textures. [TextureUsages::RENDER_ATTACHMENT] is
+    /// always included. Usages the surface doesn't support are dropped with a warning, so
+    /// check [WGPUContext::config] before e.g. copying from a frame with `COPY_SRC`
+    pub surface_usage: TextureUsages,
+    /// How many frames may be queued ahead of the one being presented. Lower values cut
+    /// the delay between input and the frame showing it, higher values keep the GPU busy
+    /// when frame times vary. 1 is lowest latency, 2 (the default) a good balance.
+    /// Can be changed later with [WGPUContext::set_max_frame_latency]
+    pub max_frame_latency: u32,
+    /// How the window compositor blends the surface with what is behind it. Transparent
+    /// windows need [CompositeAlphaMode::PreMultiplied] (matching the blending of this
+    /// crate) or [CompositeAlphaMode::PostMultiplied] together with a transparent clear
+    /// color. Falls back to [CompositeAlphaMode::Auto] with a warning if the surface
+    /// doesn't support the requested mode
+    pub alpha_mode: CompositeAlphaMode,
+    /// Debugging and validation flags of the wgpu instance. Validation catches API
+    /// misuse with readable errors and should be on during development, but costs CPU
+    /// time on every call, so it is best left off in release builds. Defaults to
+    /// [InstanceFlags::DEBUG] and [InstanceFlags::VALIDATION] in debug builds and to
+    /// no flags otherwise
+    pub instance_flags: InstanceFlags,
+}
+
+impl Default for WGPUContextDescriptor {
+    fn default() -> Self {
+        Self {
+            required_features: Features::empty(),
+            optional_features: Features::all_webgpu_mask()
+                & !Features::TEXTURE_COMPRESSION_ETC2
+                & !Features::SHADER_F16
+                & !Features::BGRA8UNORM_STORAGE
+                & !Features::TEXTURE_COMPRESSION_ASTC
+                | Features::PUSH_CONSTANTS
+                | Features::PIPELINE_CACHE,
+            prefer_srgb: true,
+            surface_usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            max_frame_latency: 2,
+            alpha_mode: CompositeAlphaMode::Auto,
+            instance_flags: if cfg!(debug_assertions) {
+                InstanceFlags::DEBUG | InstanceFlags::VALIDATION
+            } else {
+                InstanceFlags::empty()
+            },
+        }
+    }
+}
+
+/// The requested usages that the surface supports, plus the render attachment usage
+/// every surface has
+fn surface_usage(requested: TextureUsages, supported: TextureUsages) -> TextureUsages {
+    let unsupported = requested - supported;
+    if !unsupported.is_empty() {
+        log::warn!("Surface does not support {unsupported:?}, leaving it out");
+    }
+    (requested & supported) | TextureUsages::RENDER_ATTACHMENT
+}
+
+/// The requested alpha mode if the surface supports it, otherwise
+/// [CompositeAlphaMode::Auto]
+fn surface_alpha_mode(
+    requested: CompositeAlphaMode,
+    supported: &[CompositeAlphaMode],
+) -> CompositeAlphaMode {
+    if requested == CompositeAlphaMode::Auto || supported.contains(&requested) {
+        requested
+    } else {
+        log::warn!("Surface does not support {requested:?}, supported modes are {supported:?}");
+        CompositeAlphaMode::Auto
+    }
+}
+
+/// Picks the first format with the requested sRGB-ness, falling back to the
+/// surface's preferred format
+fn surface_format(formats: &[TextureFormat], prefer_srgb: bool) -> TextureFormat {
+    formats
+        .iter()
+        .copied()
+        .find(|format| format.is_srgb() == prefer_srgb)
+        .unwrap_or(formats[0])
+}
+
+/// Errors that can occur while creating a [WGPUContext]
+#[derive(Debug)]
+pub enum WGPUContextError {
+    CreateSurface(CreateSurfaceError),
+    NoAdapter,
+    /// The adapter does not support these required features
+    MissingFeatures(Features),
+    RequestDevice(RequestDeviceError),
+}
+
+impl std::fmt::Display for WGPUContextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateSurface(err) => write!(f, "Could not create surface: {err}"),
+            Self::NoAdapter => write!(f, "Could not find a compatible adapter"),
+            Self::MissingFeatures(features) => {
+                write!(f, "Adapter does not support required features: {features:?}")
+            }
+            Self::RequestDevice(err) => write!(f, "Could not create device and queue: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WGPUContextError {}
+
 impl WGPUContext {
+    /// Creates a context using the default [WGPUContextDescriptor]
+    ///
+    /// # Panics
+    /// If [Self::try_new] returns an error
     pub fn new(window: impl Into<SurfaceTarget<'static>>, size: [u32; 2]) -> Self {
+        Self::try_new(window, size, &WGPUContextDescriptor::default())
+            .unwrap_or_else(|err| panic!("Could not create WGPUContext: {err}"))
+    }
+
+    /// Same as [Self::new_async] but blocks the thread until the context is created
+    pub fn try_new(
+        window: impl Into<SurfaceTarget<'static>>,
+        size: [u32; 2],
+        descriptor: &WGPUContextDescriptor,
+    ) -> Result<Self, WGPUContextError> {
+        pollster::block_on(Self::try_new_async(window, size, descriptor))
+    }
+
+    /// Creates a context using the default [WGPUContextDescriptor] without blocking, for
+    /// applications that already run an async executor.
+    ///
+    /// Like [Self::new] this only uses the DX12 backend, so it doesn't work on the web
+    /// or outside of Windows
+    ///
+    /// # Panics
+    /// If [Self::try_new_async] returns an error
+    pub async fn new_async(window: impl Into<SurfaceTarget<'static>>, size: [u32; 2]) -> Self {
+        Self::try_new_async(window, size, &WGPUContextDescriptor::default())
+            .await
+            .unwrap_or_else(|err| panic!("Could not create WGPUContext: {err}"))
+    }
+
+    /// Creates a context rendering to `window`, with a surface of `size` physical pixels.
+    ///
+    /// The surface is created with the DX12 backend only. Fails if no adapter can present
+    /// to it or the device can't be created with the `descriptor`
+    pub async fn try_new_async(
+        window: impl Into<SurfaceTarget<'static>>,
+        size: [u32; 2],
+        descriptor: &WGPUContextDescriptor,
+    ) -> Result<Self, WGPUContextError> {
         let instance = Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::DX12,
-            flags: InstanceFlags::DEBUG | InstanceFlags::VALIDATION,
+            flags: descriptor.instance_flags,
             ..Default::default()
         });
         let surface = instance
             .create_surface(window)
-            .expect("Could not create surface");
+            .map_err(WGPUContextError::CreateSurface)?;
 
-        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            compatible_surface: Some(&surface),
-            ..Default::default()
-        }))
-        .expect("Could not create adapter");
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                ..Default::default()
+            })
+            .await
+            .ok_or(WGPUContextError::NoAdapter)?;
 
         let capabilities = surface.get_capabilities(&adapter);
+        let format = surface_format(&capabilities.formats, descriptor.prefer_srgb);
 
         let config = wgpu::SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: capabilities.formats[0],
+            usage: surface_usage(descriptor.surface_usage, capabilities.usages),
+            format,
             width: size[0],
             height: size[1],
             present_mode: wgpu::PresentMode::Fifo,
-            desired_maximum_frame_latency: 0,
-            alpha_mode: CompositeAlphaMode::Auto,
-            view_formats: vec![capabilities.formats[0]],
+            desired_maximum_frame_latency: descriptor.max_frame_latency,
+            alpha_mode: surface_alpha_mode(descriptor.alpha_mode, &capabilities.alpha_modes),
+            view_formats: vec![format],
         };
-        let (device, queue) = pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: Some("Device"),
-                required_features: Features::all_webgpu_mask() & 
-					!Features::TEXTURE_COMPRESSION_ETC2 &
-					!Features::SHADER_F16 &
-					!Features::BGRA8UNORM_STORAGE &
-					!Features::TEXTURE_COMPRESSION_ASTC,
-                memory_hints: MemoryHints::Performance,
+
+        let (device, queue) = Self::request_device(&adapter, descriptor).await?;
+
+        surface.configure(&device, &config);
+        Ok(Self {
+            instance,
+            target: RenderTarget::Surface(surface),
+            adapter,
+            device,
+            queue,
+            config,
+        })
+    }
+
+    /// Creates a context without a window using the default [WGPUContextDescriptor]
+    ///
+    /// # Panics
+    /// If [Self::try_new_headless] returns an error
+    pub fn new_headless(size: [u32; 2]) -> Self {
+        Self::try_new_headless(size, &WGPUContextDescriptor::default())
+            .unwrap_or_else(|err| panic!("Could not create headless WGPUContext: {err}"))
+    }
+
+    /// Creates a context without a window that renders into an offscreen texture
+    /// of the given size instead of a surface. Intended for tests and CI.
+    ///
+    /// Unlike [Self::try_new], any backend may be used since no surface needs to be
+    /// supported
+    pub fn try_new_headless(
+        size: [u32; 2],
+        descriptor: &WGPUContextDescriptor,
+    ) -> Result<Self, WGPUContextError> {
+        pollster::block_on(Self::try_new_headless_async(size, descriptor))
+    }
+
+    /// Same as [Self::try_new_headless] without blocking, see [Self::new_async]
+    pub async fn try_new_headless_async(
+        size: [u32; 2],
+        descriptor: &WGPUContextDescriptor,
+    ) -> Result<Self, WGPUContextError> {
+        let instance = Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            flags: descriptor.instance_flags,
+            ..Default::default()
+        });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: None,
                 ..Default::default()
-            },
-            None,
-        ))
-        .expect("Could not create device and queue");
+            })
+            .await
+            .ok_or(WGPUContextError::NoAdapter)?;
+
+        let format = if descriptor.prefer_srgb {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        };
+        let config = wgpu::SurfaceConfiguration {
+            // The offscreen texture is only useful if it can be read back
+            usage: descriptor.surface_usage
+                | TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::COPY_SRC,
+            format,
+            width: size[0],
+            height: size[1],
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: descriptor.max_frame_latency,
+            alpha_mode: descriptor.alpha_mode,
+            view_formats: vec![format],
+        };
+
+        let (device, queue) = Self::request_device(&adapter, descriptor).await?;
+
+        let texture = Self::create_offscreen_texture(&device, &config);
+        Ok(Self {
+            instance,
+            target: RenderTarget::Offscreen(texture),
+            adapter,
+            device,
+            queue,
+            config,
+        })
+    }
+
+    /// Requests a device with the required features and whichever optional features
+    /// the adapter supports
+    async fn request_device(
+        adapter: &Adapter,
+        descriptor: &WGPUContextDescriptor,
+    ) -> Result<(Device, Queue), WGPUContextError> {
+        let adapter_features = adapter.features();
+        let missing_features = descriptor.required_features - adapter_features;
+        if !missing_features.is_empty() {
+            return Err(WGPUContextError::MissingFeatures(missing_features));
+        }
+        let features =
+            descriptor.required_features | (descriptor.optional_features & adapter_features);
+        // Push constants are unusable with the default limit of 0 bytes
+        let mut required_limits = Limits::default();
+        if features.contains(Features::PUSH_CONSTANTS) {
+            required_limits.max_push_constant_size = adapter.limits().max_push_constant_size;
+        }
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Device"),
+                    required_features: features,
+                    required_limits,
+                    memory_hints: MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .map_err(WGPUContextError::RequestDevice)?;
 
         device.on_uncaptured_error(Box::new(|error| {
             match error {
@@ -71,19 +369,159 @@ impl WGPUContext {
             std::process::exit(25);
         }));
 
-        surface.configure(&device, &config);
-        Self {
-            instance,
-            surface,
-            adapter,
-            device,
-            queue,
-            config,
+        Ok((device, queue))
+    }
+
+    fn create_offscreen_texture(device: &Device, config: &SurfaceConfiguration) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("Offscreen render target"),
+            size: Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &config.view_formats,
+        })
+    }
+
+    /// Format to use for textures holding 8 bit RGBA image data, which is normally
+    /// sRGB encoded.
+    ///
+    /// On an sRGB surface this is [TextureFormat::Rgba8UnormSrgb] so texels are decoded
+    /// to linear when sampled and encoded again on write. Otherwise it is
+    /// [TextureFormat::Rgba8Unorm] and texels pass through unchanged. Either way images
+    /// look the same as their source
+    pub fn image_texture_format(&self) -> TextureFormat {
+        if self.config.format.is_srgb() {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        }
+    }
+
+    /// Features enabled on the device
+    pub fn features(&self) -> Features {
+        self.device.features()
+    }
+
+    /// Limits of the device
+    pub fn limits(&self) -> Limits {
+        self.device.limits()
+    }
+
+    /// Whether pipelines can use push constants. They need [Features::PUSH_CONSTANTS],
+    /// which is native only, and a non-zero [Limits::max_push_constant_size]
+    pub fn supports_push_constants(&self) -> bool {
+        self.features().contains(Features::PUSH_CONSTANTS)
+            && self.limits().max_push_constant_size > 0
+    }
+
+    /// Creates a query set for `count` GPU timestamps. [None] if the device doesn't
+    /// support [Features::TIMESTAMP_QUERY]
+    pub fn create_timestamp_query_set(&self, label: Option<&str>, count: u32) -> Option<QuerySet> {
+        if !self.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        Some(self.device.create_query_set(&QuerySetDescriptor {
+            label,
+            ty: QueryType::Timestamp,
+            count,
+        }))
+    }
+
+    /// Creates a cache that speeds up compiling pipelines, see
+    /// [crate::shader_manager::ShaderManager::set_pipeline_cache]. Pass the data saved
+    /// from a previous run, or `None` to start empty. Data that doesn't fit the current
+    /// adapter or driver is ignored.
+    ///
+    /// [None] if the device doesn't support [Features::PIPELINE_CACHE] (currently Vulkan
+    /// only)
+    ///
+    /// # Safety
+    /// `data` must come from [crate::shader_manager::ShaderManager::pipeline_cache_data]
+    /// or [PipelineCache::get_data]
+    pub unsafe fn create_pipeline_cache(&self, data: Option<&[u8]>) -> Option<PipelineCache> {
+        if !self.features().contains(Features::PIPELINE_CACHE) {
+            return None;
+        }
+        // SAFETY: Guaranteed by the caller
+        Some(unsafe {
+            self.device.create_pipeline_cache(&PipelineCacheDescriptor {
+                label: Some("Pipeline cache"),
+                data,
+                fallback: true,
+            })
+        })
+    }
+
+    /// Nanoseconds per tick of the timestamps written to a timestamp query set
+    pub fn timestamp_period(&self) -> f32 {
+        self.queue.get_timestamp_period()
+    }
+
+    /// The window surface. [None] for headless contexts
+    pub fn surface(&self) -> Option<&Surface<'static>> {
+        match &self.target {
+            RenderTarget::Surface(surface) => Some(surface),
+            RenderTarget::Offscreen(_) => None,
+        }
+    }
+
+    /// The texture rendered into by headless contexts. [None] when rendering to a window
+    pub fn offscreen_texture(&self) -> Option<&Texture> {
+        match &self.target {
+            RenderTarget::Surface(_) => None,
+            RenderTarget::Offscreen(texture) => Some(texture),
         }
     }
 
-    pub fn surface(&self) -> &Surface {
-        &self.surface
+    /// Gets the texture to render the next frame into
+    pub fn get_current_texture(&self) -> Result<FrameTexture, SurfaceError> {
+        match &self.target {
+            RenderTarget::Surface(surface) => {
+                surface.get_current_texture().map(FrameTexture::Surface)
+            }
+            RenderTarget::Offscreen(texture) => Ok(FrameTexture::Offscreen(texture.clone())),
+        }
+    }
+
+    /// Gets the texture for the next frame, recovering from errors that only
+    /// require skipping a frame.
+    ///
+    /// Returns `Ok(None)` if the frame should be skipped. A lost or outdated
+    /// surface (e.g. after a resize or GPU reset) is reconfigured so that the
+    /// next frame succeeds
+    pub fn acquire_frame(&self) -> Result<Option<FrameTexture>, SurfaceError> {
+        match self.get_current_texture() {
+            Ok(frame) => Ok(Some(frame)),
+            Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                log::warn!("Surface lost or outdated, reconfiguring");
+                self.reconfigure();
+                Ok(None)
+            }
+            Err(SurfaceError::Timeout) => {
+                log::warn!("Timed out waiting for surface texture, skipping frame");
+                Ok(None)
+            }
+            Err(SurfaceError::Other) => {
+                log::warn!("Could not get surface texture, skipping frame");
+                Ok(None)
+            }
+            Err(err @ SurfaceError::OutOfMemory) => Err(err),
+        }
+    }
+
+    /// Configures the surface again with the current config. Does nothing for
+    /// headless contexts
+    pub fn reconfigure(&self) {
+        if let RenderTarget::Surface(surface) = &self.target {
+            surface.configure(&self.device, &self.config);
+        }
     }
 
     pub fn device(&self) -> &Device {
@@ -98,16 +536,104 @@ impl WGPUContext {
         &self.config
     }
 
+    /// Sets [WGPUContextDescriptor::max_frame_latency] and reconfigures the surface
+    pub fn set_max_frame_latency(&mut self, max_frame_latency: u32) {
+        self.config.desired_maximum_frame_latency = max_frame_latency;
+        self.reconfigure();
+    }
+
     pub fn resize(&mut self, new_size: [u32; 2]) {
         self.config.width = new_size[0];
         self.config.height = new_size[1];
-        self.surface.configure(&self.device, &self.config);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => {
+                texture.destroy();
+                *texture = Self::create_offscreen_texture(&self.device, &self.config);
+            }
+        }
     }
 
     pub fn get_encoder(&self) -> CommandEncoder {
         self.device
             .create_command_encoder(&CommandEncoderDescriptor { label: None })
     }
+
+    /// Waits for the submitted work to finish, then releases the surface, device and
+    /// instance in that order, e.g. when one of several windows is closed. Dropping the
+    /// context also releases them, but without waiting and in field order.
+    ///
+    /// Resources created from the context, like [WGPUBuffer]s, keep what they need
+    /// alive and may be dropped before or after this. Debug builds log how many
+    /// buffers are still alive
+    pub fn destroy(self) {
+        let Self {
+            instance,
+            target,
+            adapter,
+            device,
+            queue,
+            config: _,
+        } = self;
+        // Returns once the queue is idle, so this doesn't block on later frames
+        let _ = device.poll(Maintain::Wait);
+
+        #[cfg(debug_assertions)]
+        if let Some(report) = instance.generate_report() {
+            let buffers = report.hub_report().buffers.num_kept_from_user;
+            if buffers > 0 {
+                log::debug!("Destroying WGPUContext with {buffers} buffers still alive");
+            }
+        }
+
+        if let RenderTarget::Offscreen(texture) = &target {
+            texture.destroy();
+        }
+        drop(target);
+        drop(queue);
+        drop(device);
+        drop(adapter);
+        drop(instance);
+    }
+}
+
+/// Coalesces window resizes so the surface is reconfigured at most once per frame.
+///
+/// Record every resize event with [Self::request] and call [Self::apply] before
+/// rendering. Only the latest size is applied
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResizeTracker {
+    pending: Option<[u32; 2]>,
+}
+
+impl ResizeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&mut self, size: [u32; 2]) {
+        self.pending = Some(size);
+    }
+
+    pub fn pending(&self) -> Option<[u32; 2]> {
+        self.pending
+    }
+
+    /// Takes the pending size. Zero sized requests, e.g. from a minimized window, are
+    /// dropped since a surface can't be configured with them
+    pub fn take(&mut self) -> Option<[u32; 2]> {
+        self.pending.take().filter(|[width, height]| *width > 0 && *height > 0)
+    }
+
+    /// Resizes `context` if a resize is pending and returns the new size, so the
+    /// caller can update anything else that depends on it like the uniform
+    pub fn apply(&mut self, context: &mut WGPUContext) -> Option<[u32; 2]> {
+        let size = self.take()?;
+        if size != [context.config().width, context.config().height] {
+            context.resize(size);
+        }
+        Some(size)
+    }
 }
 
 pub trait BufferData {
@@ -115,6 +641,23 @@ pub trait BufferData {
     type Buffers;
     fn create_buffers(&self, context: &WGPUContext) -> Self::Buffers;
     fn fill_buffers(&self, buffers: &mut Self::Buffers, context: &WGPUContext);
+    /// Whether [Self::fill_buffers] can write into `buffers` without outgrowing them.
+    /// Defaults to `false`, so callers fall back to [Self::create_buffers]
+    fn fits_buffers(&self, buffers: &Self::Buffers) -> bool {
+        let _ = buffers;
+        false
+    }
+    /// Same as [Self::fill_buffers] but records the writes into `uploader`.
+    /// Writes directly by default
+    fn stage_buffers(
+        &self,
+        buffers: &mut Self::Buffers,
+        uploader: &mut BufferUploader,
+        context: &WGPUContext,
+    ) {
+        let _ = uploader;
+        self.fill_buffers(buffers, context);
+    }
 }
 
 pub struct BufferAndData<T: BufferData> {
@@ -132,24 +675,61 @@ impl<T: BufferData> BufferAndData<T> {
     pub fn update_buffer(&mut self, context: &WGPUContext) {
         self.data.fill_buffers(&mut self.buffers, context);
     }
+
+    /// Records the upload into `uploader` if one is given, otherwise writes directly
+    /// like [Self::update_buffer]
+    pub fn update_buffer_with(
+        &mut self,
+        uploader: Option<&mut BufferUploader>,
+        context: &WGPUContext,
+    ) {
+        match uploader {
+            Some(uploader) => self.data.stage_buffers(&mut self.buffers, uploader, context),
+            None => self.update_buffer(context),
+        }
+    }
+}
+
+impl<T: BufferData + Clone> BufferAndData<T> {
+    /// Copy of the CPU side data, e.g. for an undo stack. This clones all of the data,
+    /// so snapshots of large instance vectors are expensive and best taken once per edit
+    /// rather than every frame
+    pub fn snapshot(&self) -> T {
+        self.data.clone()
+    }
+
+    /// Replaces the data with `snapshot`, usually one taken by [Self::snapshot], and
+    /// uploads it into the existing buffers, so bind groups made from them stay valid.
+    ///
+    /// If the snapshot holds more elements than the buffers have room for, they are
+    /// re-created instead and any bind group using them has to be made again
+    pub fn restore(&mut self, snapshot: T, context: &WGPUContext) {
+        if !snapshot.fits_buffers(&self.buffers) {
+            self.buffers = snapshot.create_buffers(context);
+        }
+        snapshot.fill_buffers(&mut self.buffers, context);
+        self.data = snapshot;
+    }
 }
 
 mod buffers {
     use super::WGPUContext;
 
+    use wgpu::util::StagingBelt;
     use wgpu::*;
 
     use bytemuck::Pod;
 
     use std::num::NonZero;
 
+    const UNIFORM_BUFFER_ALIGNMENT: u64 = 16;
+
     pub struct WGPUBuffer {
         buffer: Buffer,
     }
 
     impl WGPUBuffer {
         pub fn new_uniform(size: u64, context: &WGPUContext) -> Self {
-            const UNIFORM_BUFFER_ALIGNMENT: u64 = 16;
             Self {
                 buffer: Self::new(
                     (((size - 1) / UNIFORM_BUFFER_ALIGNMENT) + 1) * UNIFORM_BUFFER_ALIGNMENT,
@@ -169,6 +749,18 @@ mod buffers {
             }
         }
 
+        /// A storage buffer that can also be bound as a vertex buffer, e.g. for instances
+        /// written by a compute shader and drawn without a copy
+        pub fn new_storage_vertex(size: u64, context: &WGPUContext) -> Self {
+            Self {
+                buffer: Self::new(
+                    size,
+                    BufferUsages::COPY_DST | BufferUsages::STORAGE | BufferUsages::VERTEX,
+                    context,
+                ),
+            }
+        }
+
         pub fn new_vertex(size: u64, context: &WGPUContext) -> Self {
             Self {
                 buffer: Self::new(size, BufferUsages::COPY_DST | BufferUsages::VERTEX, context),
@@ -181,6 +773,73 @@ mod buffers {
             }
         }
 
+        /// Creates a uniform buffer that already holds `data`, padded to the uniform
+        /// buffer alignment
+        pub fn new_uniform_init<T: Pod>(data: &T, context: &WGPUContext) -> Self {
+            Self::new_init(
+                bytemuck::bytes_of(data),
+                UNIFORM_BUFFER_ALIGNMENT,
+                BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+                context,
+            )
+        }
+
+        /// Creates a storage buffer that already holds `data`
+        pub fn new_storage_init<T: Pod>(data: &[T], context: &WGPUContext) -> Self {
+            Self::new_init(
+                bytemuck::cast_slice(data),
+                COPY_BUFFER_ALIGNMENT,
+                BufferUsages::COPY_DST | BufferUsages::STORAGE,
+                context,
+            )
+        }
+
+        /// Creates a vertex buffer that already holds `data`. Cheaper than creating
+        /// the buffer and then writing to it for large static data
+        pub fn new_vertex_init<T: Pod>(data: &[T], context: &WGPUContext) -> Self {
+            Self::new_init(
+                bytemuck::cast_slice(data),
+                COPY_BUFFER_ALIGNMENT,
+                BufferUsages::COPY_DST | BufferUsages::VERTEX,
+                context,
+            )
+        }
+
+        /// Creates an index buffer that already holds `data`
+        pub fn new_index_init<T: Pod>(data: &[T], context: &WGPUContext) -> Self {
+            Self::new_init(
+                bytemuck::cast_slice(data),
+                COPY_BUFFER_ALIGNMENT,
+                BufferUsages::COPY_DST | BufferUsages::INDEX,
+                context,
+            )
+        }
+
+        /// Creates the buffer mapped and copies `data` into it directly, skipping the
+        /// staging copy a queue write needs. The size is rounded up to `alignment`
+        pub(super) fn new_init(
+            data: &[u8],
+            alignment: u64,
+            usage: BufferUsages,
+            context: &WGPUContext,
+        ) -> Self {
+            if data.is_empty() {
+                return Self {
+                    buffer: Self::new(0, usage, context),
+                };
+            }
+            let size = (data.len() as u64).div_ceil(alignment) * alignment;
+            let buffer = context.device().create_buffer(&BufferDescriptor {
+                label: None,
+                size,
+                usage,
+                mapped_at_creation: true,
+            });
+            buffer.slice(..).get_mapped_range_mut()[..data.len()].copy_from_slice(data);
+            buffer.unmap();
+            Self { buffer }
+        }
+
         pub fn size(&self) -> u64 {
             self.buffer.size()
         }
@@ -205,33 +864,236 @@ mod buffers {
             self.buffer.destroy();
         }
 
-        pub fn write_iter<'a, I, T>(&mut self, mut data: I, context: &WGPUContext)
+        /// # Panics
+        /// If [Self::try_write_iter] returns an error
+        pub fn write_iter<'a, I, T>(&mut self, data: I, context: &WGPUContext)
+        where
+            I: Iterator<Item = &'a T>,
+            T: Pod + Sized,
+        {
+            self.try_write_iter(data, context)
+                .unwrap_or_else(|err| panic!("{err}"));
+        }
+
+        /// Writes the elements of `data` to the start of the buffer without growing it.
+        ///
+        /// If `data` does not fit, the elements that do fit are still written
+        pub fn try_write_iter<'a, I, T>(
+            &mut self,
+            data: I,
+            context: &WGPUContext,
+        ) -> Result<(), BufferWriteError>
         where
             I: Iterator<Item = &'a T>,
             T: Pod + Sized,
         {
+            let buffer_size = self.size();
+            let Some(size) = NonZero::new(buffer_size) else {
+                return copy_iter(&mut [], data);
+            };
             let mut buffer_slice = context
                 .queue()
-                .write_buffer_with(&self.buffer, 0, NonZero::new(self.size()).unwrap())
-                .expect("Could not write to buffer");
-            let mut buffer_iter = buffer_slice.chunks_mut(std::mem::size_of::<T>());
-            loop {
-                match (buffer_iter.next(), data.next()) {
-                    (Some(buffer_slice), Some(data_elem)) => {
-                        buffer_slice.copy_from_slice(bytemuck::bytes_of(data_elem))
-                    }
-                    (_, None) => break,
-                    _ => panic!("Size of data is greater than size of buffer"),
-                }
-            }
+                .write_buffer_with(&self.buffer, 0, size)
+                .ok_or(BufferWriteError::Rejected {
+                    buffer_size,
+                    write_size: size.get(),
+                })?;
+            copy_iter(&mut buffer_slice, data)
+        }
+
+        /// # Panics
+        /// If [Self::try_stage_iter] returns an error
+        pub fn stage_iter<'a, I, T>(
+            &mut self,
+            data: I,
+            uploader: &mut BufferUploader,
+            context: &WGPUContext,
+        ) where
+            I: Iterator<Item = &'a T>,
+            T: Pod + Sized,
+        {
+            self.try_stage_iter(data, uploader, context)
+                .unwrap_or_else(|err| panic!("{err}"));
+        }
+
+        /// Same as [Self::try_write_iter] but records the write into `uploader`
+        pub fn try_stage_iter<'a, I, T>(
+            &mut self,
+            data: I,
+            uploader: &mut BufferUploader,
+            context: &WGPUContext,
+        ) -> Result<(), BufferWriteError>
+        where
+            I: Iterator<Item = &'a T>,
+            T: Pod + Sized,
+        {
+            let Some(size) = NonZero::new(self.size()) else {
+                return copy_iter(&mut [], data);
+            };
+            copy_iter(&mut uploader.view(&self.buffer, size, context), data)
+        }
+
+        /// Writes a contiguous slice in a single copy, growing the buffer if needed.
+        /// Prefer this over `write_iter` when the data is already laid out as it should be on the GPU
+        pub fn write_slice<T: Pod>(&mut self, data: &[T], context: &WGPUContext) {
+            self.write_data(bytemuck::cast_slice(data), context);
         }
 
         pub fn write_data(&mut self, data: &[u8], context: &WGPUContext) {
             self.resize(data.len() as u64, context);
             context.queue().write_buffer(&self.buffer, 0, data);
         }
+
+        /// Same as [Self::write_slice] but records the write into `uploader`
+        pub fn stage_slice<T: Pod>(
+            &mut self,
+            data: &[T],
+            uploader: &mut BufferUploader,
+            context: &WGPUContext,
+        ) {
+            self.stage_data(bytemuck::cast_slice(data), uploader, context);
+        }
+
+        /// Same as [Self::write_data] but records the write into `uploader`
+        pub fn stage_data(
+            &mut self,
+            data: &[u8],
+            uploader: &mut BufferUploader,
+            context: &WGPUContext,
+        ) {
+            self.resize(data.len() as u64, context);
+            if let Some(size) = NonZero::new(data.len() as u64) {
+                uploader
+                    .view(&self.buffer, size, context)
+                    .copy_from_slice(data);
+            }
+        }
+    }
+
+    /// Copies the elements of `data` into consecutive chunks of `buffer`.
+    ///
+    /// If `data` does not fit, the elements that do fit are still copied
+    fn copy_iter<'a, I, T>(buffer: &mut [u8], mut data: I) -> Result<(), BufferWriteError>
+    where
+        I: Iterator<Item = &'a T>,
+        T: Pod + Sized,
+    {
+        let buffer_size = buffer.len() as u64;
+        let element_size = std::mem::size_of::<T>() as u64;
+        let mut buffer_iter = buffer.chunks_mut(element_size as usize);
+        let mut written = 0;
+        loop {
+            match (buffer_iter.next(), data.next()) {
+                (Some(buffer_slice), Some(data_elem)) => {
+                    buffer_slice.copy_from_slice(bytemuck::bytes_of(data_elem));
+                    written += 1;
+                }
+                (_, None) => return Ok(()),
+                (None, Some(_)) => {
+                    let count = written + 1 + data.count() as u64;
+                    return Err(BufferWriteError::TooSmall {
+                        buffer_size,
+                        data_size: count * element_size,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Batches buffer writes into one command encoder through a [StagingBelt], so that
+    /// many small updates in a frame share a single submission.
+    ///
+    /// Writes are recorded with the `stage_*` methods of [WGPUBuffer] or with
+    /// [super::BufferAndData::update_buffer_with]. They only reach the buffers once
+    /// [Self::submit] is called, which must happen before rendering the frame that
+    /// uses them. Staged sizes must be a multiple of [COPY_BUFFER_ALIGNMENT]
+    pub struct BufferUploader {
+        belt: StagingBelt,
+        encoder: Option<CommandEncoder>,
     }
 
+    impl BufferUploader {
+        /// Size of each staging buffer used by [Self::default]
+        pub const DEFAULT_CHUNK_SIZE: u64 = 64 * 1024;
+
+        /// Staging buffers are allocated `chunk_size` bytes at a time and reused once
+        /// the GPU is done with them. Larger writes get a staging buffer of their own
+        pub fn new(chunk_size: u64) -> Self {
+            Self {
+                belt: StagingBelt::new(chunk_size),
+                encoder: None,
+            }
+        }
+
+        /// A mapped staging range that is copied into the start of `buffer` on submit
+        fn view(
+            &mut self,
+            buffer: &Buffer,
+            size: NonZero<u64>,
+            context: &WGPUContext,
+        ) -> BufferViewMut<'_> {
+            let encoder = self.encoder.get_or_insert_with(|| {
+                context
+                    .device()
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("Buffer upload encoder"),
+                    })
+            });
+            self.belt
+                .write_buffer(encoder, buffer, 0, size, context.device())
+        }
+
+        /// Submits every write recorded since the last call. Does nothing if there
+        /// were none
+        pub fn submit(&mut self, context: &WGPUContext) {
+            let Some(encoder) = self.encoder.take() else {
+                return;
+            };
+            self.belt.finish();
+            context.queue().submit([encoder.finish()]);
+            self.belt.recall();
+        }
+    }
+
+    impl Default for BufferUploader {
+        fn default() -> Self {
+            Self::new(Self::DEFAULT_CHUNK_SIZE)
+        }
+    }
+
+    /// Errors that can occur while writing to a [WGPUBuffer]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum BufferWriteError {
+        /// The data does not fit in the buffer
+        TooSmall { buffer_size: u64, data_size: u64 },
+        /// wgpu refused to map `write_size` bytes of the buffer for writing. The
+        /// reason is reported to the device's error handler
+        Rejected { buffer_size: u64, write_size: u64 },
+    }
+
+    impl std::fmt::Display for BufferWriteError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::TooSmall {
+                    buffer_size,
+                    data_size,
+                } => write!(
+                    f,
+                    "Could not write {data_size} bytes to a buffer of {buffer_size} bytes"
+                ),
+                Self::Rejected {
+                    buffer_size,
+                    write_size,
+                } => write!(
+                    f,
+                    "wgpu rejected a write of {write_size} bytes to a buffer of {buffer_size} bytes"
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for BufferWriteError {}
+
     impl std::ops::Deref for WGPUBuffer {
         type Target = Buffer;
         fn deref(&self) -> &Self::Target {
@@ -251,3 +1113,335 @@ mod buffers {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Vector2, Vector4};
+    use crate::rendering::{
+        CenterRect, CircleRenderer, DebugOverlay, RectangleRenderer, Render, Renderer2D, Ring,
+        RingRenderer,
+    };
+    use crate::shader_manager::ShaderManager;
+    use crate::test_utils::*;
+
+    #[test]
+    fn headless_render() {
+        let Some(context) = headless() else { return };
+        assert!(context.surface().is_none());
+
+        let shader_manager = ShaderManager::new("");
+        let mut renderer = Renderer2D::new(&context);
+        let circles = CircleRenderer::new(
+            vec![circle([32., 32.], 16.)],
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        );
+        let rings = RingRenderer::new(
+            vec![Ring {
+                color: Vector4::new([1., 0., 0., 1.]),
+                position: Vector2::new([32., 32.]),
+                outer_radius: 24.,
+                inner_radius: 20.,
+                edge_softness: 1.,
+                layer: 0,
+            }],
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        );
+        let rects = RectangleRenderer::new(
+            vec![CenterRect {
+                color: Vector4::new([0., 1., 0., 1.]),
+                ..rect([8., 8.], [8., 8.])
+            }],
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        );
+        let mut overlay = DebugOverlay::new(
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        );
+        overlay.grid(16.);
+        overlay.push_aabb(
+            Vector2::new([16., 16.]),
+            Vector2::new([48., 48.]),
+            Vector4::new([1., 0., 1., 1.]),
+        );
+        overlay.upload(renderer.uniform(), &context);
+        let items: [&dyn Render; 4] = [&circles, &rings, &rects, &overlay];
+        renderer
+            .render(items, &context, &shader_manager)
+            .expect("Could not render frame");
+        context.device().poll(Maintain::Wait);
+    }
+
+    #[test]
+    fn array_buffer_data() {
+        let Some(context) = headless() else { return };
+
+        let circle = circle([32., 32.], 16.);
+        let buffers = BufferAndData::new([circle; 4], &context);
+        assert_eq!(
+            buffers.buffers.0.size(),
+            4 * std::mem::size_of::<Vector4<f32>>() as u64
+        );
+        assert_eq!(
+            buffers.buffers.2.size(),
+            4 * std::mem::size_of::<f32>() as u64
+        );
+    }
+
+    #[test]
+    fn array_field_buffer_data() {
+        #[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, derive::VertexBufferData)]
+        #[repr(C)]
+        struct Quad {
+            color: Vector4<f32>,
+            corners: [Vector2<f32>; 4],
+        }
+        const LAYOUT: [VertexBufferLayout; 2] = crate::vertex_buffer_layout!(
+            ([f32; 4], Instance, &vertex_attr_array![0 => Float32x4]),
+            (
+                [[f32; 2]; 4],
+                Instance,
+                &vertex_attr_array![1 => Float32x2, 2 => Float32x2, 3 => Float32x2, 4 => Float32x2]
+            ),
+        );
+
+        let Some(context) = headless() else { return };
+
+        let quad = Quad {
+            color: Vector4::new([1., 1., 1., 1.]),
+            corners: [Vector2::new([0., 0.]); 4],
+        };
+        let buffers = BufferAndData::new(vec![quad; 3], &context);
+        assert_eq!(buffers.buffers.1.size(), 3 * LAYOUT[1].array_stride);
+        assert_eq!(LAYOUT[1].array_stride, 32);
+        assert_eq!(LAYOUT[1].attributes[3].offset, 24);
+    }
+
+    #[test]
+    fn buffer_uploader_batches_writes() {
+        let Some(context) = headless() else { return };
+
+        let circle = circle([32., 32.], 16.);
+        let mut circles = BufferAndData::new(vec![circle; 4], &context);
+        let mut rects = BufferAndData::new(
+            vec![CenterRect {
+                color: Vector4::new([0., 1., 0., 1.]),
+                ..rect([8., 8.], [8., 8.])
+            }],
+            &context,
+        );
+
+        let mut uploader = BufferUploader::default();
+        // Several frames so the staging buffers get recalled and reused
+        for frame in 0..3 {
+            circles.data[0].radius = frame as f32;
+            circles.update_buffer_with(Some(&mut uploader), &context);
+            rects.update_buffer_with(Some(&mut uploader), &context);
+            uploader.submit(&context);
+            context.device().poll(Maintain::Wait);
+        }
+
+        // Nothing staged, so this must not submit an empty encoder
+        uploader.submit(&context);
+        circles.update_buffer_with(None, &context);
+    }
+
+    #[test]
+    fn snapshot_restores_instances() {
+        let Some(context) = headless() else { return };
+        let shader_manager = ShaderManager::new("");
+        let mut renderer = Renderer2D::new(&context);
+        let at = |x: f32| circle([x, 32.], 8.);
+        let mut circles = CircleRenderer::new(
+            vec![at(16.)],
+            renderer.uniform_bind_group_layout(),
+            &context,
+            &shader_manager,
+        );
+        let before = circles.snapshot();
+        circles.modify(0, |c| c.position = Vector2::new([48., 32.]));
+        circles.sync(&context);
+        let after = circles.snapshot();
+
+        circles.restore(before, &context);
+        renderer
+            .render([&circles], &context, &shader_manager)
+            .expect("Could not render frame");
+        let pixels = read_pixels(&context);
+        assert!(pixel(&pixels, 16, 32)[0] > 200);
+        assert!(pixel(&pixels, 48, 32)[0] < 100);
+
+        // Snapshots may hold more instances than the buffer was created with
+        let mut redo = after;
+        redo.push(at(32.));
+        circles.restore(redo, &context);
+        assert_eq!(circles.snapshot().len(), 2);
+        renderer
+            .render([&circles], &context, &shader_manager)
+            .expect("Could not render frame");
+        let pixels = read_pixels(&context);
+        assert!(pixel(&pixels, 16, 32)[0] < 100);
+        assert!(pixel(&pixels, 48, 32)[0] > 200);
+    }
+
+    #[test]
+    fn restore_writes_in_place() {
+        let Some(context) = headless() else { return };
+
+        let mut circles = BufferAndData::new(vec![circle([16., 32.], 8.); 4], &context);
+        let buffer = Buffer::clone(&circles.buffers.6);
+        circles.restore(vec![circle([48., 32.], 8.); 2], &context);
+        assert_eq!(*circles.buffers.6, buffer);
+        assert_eq!(circles.data.len(), 2);
+
+        // Bind groups made from the old buffers would have to be re-created here
+        circles.restore(vec![circle([48., 32.], 8.); 8], &context);
+        assert_ne!(*circles.buffers.6, buffer);
+        assert_eq!(circles.buffers.6.size(), 8 * 4);
+    }
+
+    #[test]
+    fn buffer_init_holds_data() {
+        let Some(context) = headless() else { return };
+
+        // Padded to the copy alignment
+        let vertices = WGPUBuffer::new_vertex_init(&[1u16, 2, 3], &context);
+        assert_eq!(vertices.size(), 8);
+        let uniform = WGPUBuffer::new_uniform_init(&[1f32, 2., 3.], &context);
+        assert_eq!(uniform.size(), 16);
+        assert_eq!(WGPUBuffer::new_index_init::<u32>(&[], &context).size(), 0);
+
+        let data = [1u32, 2, 3, 4];
+        let buffer = WGPUBuffer::new_init(
+            bytemuck::cast_slice(&data),
+            4,
+            BufferUsages::COPY_SRC,
+            &context,
+        );
+        let readback = context.device().create_buffer(&BufferDescriptor {
+            label: None,
+            size: buffer.size(),
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = context
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(&buffer, 0, &readback, 0, buffer.size());
+        context.queue().submit([encoder.finish()]);
+        readback.slice(..).map_async(MapMode::Read, |result| result.unwrap());
+        context.device().poll(Maintain::Wait);
+        assert_eq!(&*readback.slice(..).get_mapped_range(), bytemuck::cast_slice::<_, u8>(&data));
+    }
+
+    #[test]
+    fn destroy_many_contexts() {
+        for _ in 0..8 {
+            let Some(context) = headless() else { return };
+            let shader_manager = ShaderManager::new("");
+            let mut renderer = Renderer2D::new(&context);
+            let circles = CircleRenderer::new(
+                vec![circle([32., 32.], 16.)],
+                renderer.uniform_bind_group_layout(),
+                &context,
+                &shader_manager,
+            );
+            renderer
+                .render([&circles], &context, &shader_manager)
+                .expect("Could not render frame");
+            context.destroy();
+            // Buffers outliving their context are destroyed without error
+            drop(circles);
+            drop(renderer);
+        }
+    }
+
+    #[test]
+    fn resize_tracker_keeps_latest() {
+        let mut tracker = ResizeTracker::new();
+        assert_eq!(tracker.take(), None);
+        tracker.request([800, 600]);
+        tracker.request([801, 600]);
+        assert_eq!(tracker.take(), Some([801, 600]));
+        assert_eq!(tracker.take(), None);
+        tracker.request([0, 600]);
+        assert_eq!(tracker.take(), None);
+    }
+
+    #[test]
+    fn alpha_mode_falls_back_to_auto() {
+        let supported = [CompositeAlphaMode::Opaque, CompositeAlphaMode::PreMultiplied];
+        assert_eq!(
+            surface_alpha_mode(CompositeAlphaMode::PreMultiplied, &supported),
+            CompositeAlphaMode::PreMultiplied
+        );
+        assert_eq!(
+            surface_alpha_mode(CompositeAlphaMode::PostMultiplied, &supported),
+            CompositeAlphaMode::Auto
+        );
+    }
+
+    #[test]
+    fn surface_format_prefers_srgb() {
+        let formats = [TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb];
+        assert_eq!(surface_format(&formats, true), TextureFormat::Bgra8UnormSrgb);
+        assert_eq!(surface_format(&formats, false), TextureFormat::Bgra8Unorm);
+        assert_eq!(
+            surface_format(&[TextureFormat::Rgba16Float], true),
+            TextureFormat::Rgba16Float
+        );
+    }
+
+    #[test]
+    fn surface_usage_drops_unsupported() {
+        let requested = TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC;
+        assert_eq!(surface_usage(requested, requested), requested);
+        assert_eq!(
+            surface_usage(requested, TextureUsages::RENDER_ATTACHMENT),
+            TextureUsages::RENDER_ATTACHMENT
+        );
+        assert_eq!(
+            surface_usage(TextureUsages::empty(), requested),
+            TextureUsages::RENDER_ATTACHMENT
+        );
+    }
+
+    #[test]
+    fn try_write_iter_too_small() {
+        let Some(context) = headless() else { return };
+
+        let mut buffer = WGPUBuffer::new_vertex(8, &context);
+        assert_eq!(buffer.try_write_iter([1f32, 2.].iter(), &context), Ok(()));
+        assert_eq!(
+            buffer.try_write_iter([1f32, 2., 3., 4.].iter(), &context),
+            Err(BufferWriteError::TooSmall {
+                buffer_size: 8,
+                data_size: 16
+            })
+        );
+    }
+
+    #[test]
+    fn instance_flags() {
+        // Tests are built with debug assertions
+        let descriptor = WGPUContextDescriptor::default();
+        assert!(descriptor.instance_flags.contains(InstanceFlags::VALIDATION));
+
+        let descriptor = WGPUContextDescriptor {
+            instance_flags: InstanceFlags::empty(),
+            ..Default::default()
+        };
+        let Some(context) = headless_with(&descriptor) else { return };
+        let shader_manager = ShaderManager::new("");
+        Renderer2D::new(&context)
+            .render([] as [&CircleRenderer; 0], &context, &shader_manager)
+            .expect("Could not render frame");
+    }
+}
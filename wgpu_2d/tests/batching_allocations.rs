@@ -0,0 +1,63 @@
+//! Checks that ordering and batching a render call doesn't allocate once the draw order
+//! has grown. This replaces the global allocator, so it lives in its own test binary
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use wgpu::RenderPass;
+use wgpu_2d::rendering::{batch_items, Render};
+use wgpu_2d::shader_manager::ShaderManager;
+use wgpu_2d::wgpu_context::WGPUContext;
+
+// Counts the allocations made on the current thread, so that tests running in parallel
+// don't affect each other
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+struct Labeled(Option<&'static str>);
+
+impl Render for Labeled {
+    fn render(&self, _: &mut RenderPass, _: &WGPUContext, _: &ShaderManager) {
+        unreachable!()
+    }
+
+    fn pipeline_label(&self) -> Option<&str> {
+        self.0
+    }
+}
+
+// Render calls keep up to 32 items on the stack
+const ITEMS: usize = 32;
+
+fn frame(order: &mut Vec<(usize, usize)>) -> usize {
+    let labels = [Some("circle"), Some("rects"), None];
+    let items = (0..ITEMS).map(|i| Labeled(labels[i % 3]));
+    let before = ALLOCATIONS.with(Cell::get);
+    batch_items(items, order);
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn ordering_does_not_allocate() {
+    let mut order = Vec::new();
+    // Only the first frame grows the order
+    assert!(frame(&mut order) > 0);
+    assert_eq!(frame(&mut order), 0);
+}